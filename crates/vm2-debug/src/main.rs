@@ -0,0 +1,141 @@
+//! Interactive debugger for EraVM bytecodes.
+//!
+//! Loads a bytecode, calldata and an optional state file into a [`World`](zksync_vm2::World),
+//! and offers a REPL to control execution and inspect VM state. Run with `--help` for usage.
+
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process,
+};
+
+use anyhow::Context as _;
+use primitive_types::H160;
+use zksync_vm2::{Settings, VirtualMachine};
+
+use crate::{
+    repl::{Debugger, Flow},
+    world::{parse_address, parse_hex, read_bytecode, DebugWorld},
+};
+
+mod repl;
+mod world;
+
+const USAGE: &str = "\
+Usage: vm2-debug <bytecode> [options]
+
+Arguments:
+  <bytecode>            File with the EraVM bytecode to execute (raw or hex-encoded)
+
+Options:
+  --calldata <hex>      Calldata passed to the executed program [default: empty]
+  --state <file>        State file deploying other contracts and setting storage slots.
+                        Each line is either `contract <address> <bytecode file>`
+                        or `storage <address> <key> <value>`; `#` starts a comment
+  --address <address>   Address the bytecode is deployed at [default: 0x10000]
+  --gas <gas>           Gas given to the executed program [default: 4294967295]
+  --help                Print this message";
+
+#[derive(Debug)]
+struct Args {
+    bytecode: PathBuf,
+    calldata: Vec<u8>,
+    state: Option<PathBuf>,
+    address: H160,
+    gas: u32,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut bytecode = None;
+        let mut calldata = vec![];
+        let mut state = None;
+        let mut address = H160::from_low_u64_be(0x10000);
+        let mut gas = u32::MAX;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .with_context(|| format!("missing value for `{arg}`"))
+            };
+            match arg.as_str() {
+                "--calldata" => calldata = parse_hex(&value()?)?,
+                "--state" => state = Some(value()?.into()),
+                "--address" => address = parse_address(&value()?)?,
+                "--gas" => gas = value()?.parse().context("invalid gas")?,
+                _ if arg.starts_with("--") => anyhow::bail!("unknown option `{arg}`"),
+                _ if bytecode.is_none() => bytecode = Some(PathBuf::from(&arg)),
+                _ => anyhow::bail!("unexpected argument `{arg}`"),
+            }
+        }
+
+        Ok(Self {
+            bytecode: bytecode.context("missing bytecode")?,
+            calldata,
+            state,
+            address,
+            gas,
+        })
+    }
+}
+
+fn create_debugger(args: &Args) -> anyhow::Result<Debugger> {
+    let mut world = DebugWorld::default();
+    if let Some(state) = &args.state {
+        world.load_state(state)?;
+    }
+    let bytecode = read_bytecode(&args.bytecode)?;
+    world.add_contract(args.address, &bytecode)?;
+    let program = world
+        .program_at(args.address)
+        .expect("contract was just deployed");
+
+    let vm = VirtualMachine::new(
+        args.address,
+        program,
+        H160::zero(),
+        &args.calldata,
+        args.gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    Ok(Debugger::new(vm, world))
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Vec<_> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return Ok(());
+    }
+    let args = match Args::parse(args.into_iter()) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err:#}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    let mut debugger = create_debugger(&args)?;
+
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    debugger.print_location(&mut stdout)?;
+    loop {
+        write!(stdout, "(vm2) ")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break; // EOF
+        }
+        match debugger.execute(&line, &mut stdout) {
+            Ok(Flow::Continue) => {}
+            Ok(Flow::Quit) => break,
+            Err(err) => writeln!(stdout, "error: {err:#}")?,
+        }
+    }
+    Ok(())
+}
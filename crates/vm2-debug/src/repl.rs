@@ -0,0 +1,365 @@
+//! REPL commands.
+//!
+//! Execution is controlled via [`VirtualMachine`] methods; all VM state is inspected
+//! via [`StateInterface`] and [`CallframeInterface`].
+
+use std::io::Write;
+
+use anyhow::Context as _;
+use primitive_types::H160;
+use zksync_vm2::{Breakpoint, DisassembledInstruction, ExecutionEnd, VirtualMachine};
+use zksync_vm2_interface::{CallframeInterface, HeapId, StateInterface};
+
+use crate::world::{parse_address, parse_u256, DebugWorld};
+
+const HELP: &str = "\
+Execution:
+  step [n], s [n]          Execute n instructions (default: 1), entering calls
+  next, n                  Execute one instruction, stepping over calls
+  continue, c              Run until a breakpoint or the end of execution
+  finish, f                Run until the current frame returns
+Breakpoints:
+  break [address] <pc>     Set a breakpoint (default address: the current contract)
+  delete [address] <pc>    Remove a breakpoint
+  breakpoints              List breakpoints
+Inspection:
+  instruction, i           Print the current instruction
+  registers, r             Print registers
+  flags                    Print execution flags
+  stack [n]                Print the top n stack slots of the current frame (default: 8)
+  heap <heap> <offset> [n] Print n words (default: 1) from `main` or `aux` heap of the current frame,
+                           or from the heap with the specified numeric ID
+  frames, bt               Print the callstack
+  storage                  Print storage slots accessed so far
+Other:
+  help, h                  Print this message
+  quit, q                  Exit the debugger";
+
+/// What the REPL should do after a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Debugging session for a single VM.
+#[derive(Debug)]
+pub(crate) struct Debugger {
+    vm: VirtualMachine<(), DebugWorld>,
+    world: DebugWorld,
+    /// Set once the VM has finished execution, after which it cannot be resumed.
+    end: Option<ExecutionEnd>,
+}
+
+impl Debugger {
+    pub(crate) fn new(vm: VirtualMachine<(), DebugWorld>, world: DebugWorld) -> Self {
+        Self {
+            vm,
+            world,
+            end: None,
+        }
+    }
+
+    /// Executes a single command.
+    pub(crate) fn execute(&mut self, line: &str, out: &mut impl Write) -> anyhow::Result<Flow> {
+        let parts: Vec<_> = line.split_whitespace().collect();
+        let Some((&command, args)) = parts.split_first() else {
+            return Ok(Flow::Continue);
+        };
+
+        match (command, args) {
+            ("step" | "s", []) => self.run(out, Self::step_instruction)?,
+            ("step" | "s", [count]) => {
+                let count: usize = count.parse().context("invalid instruction count")?;
+                self.run(out, |this| {
+                    for _ in 0..count {
+                        if let Some(end) = this.step_instruction() {
+                            return Some(end);
+                        }
+                    }
+                    None
+                })?;
+            }
+            ("next" | "n", []) => self.run(out, |this| this.run_while_deeper_than(0))?,
+            ("continue" | "c", []) => {
+                self.run(out, |this| Some(this.vm.run(&mut this.world, &mut ())))?
+            }
+            ("finish" | "f", []) => self.run(out, |this| this.run_while_deeper_than(1))?,
+
+            ("break" | "b", args) => {
+                let breakpoint = self.parse_breakpoint(args)?;
+                self.vm.add_breakpoint(breakpoint);
+            }
+            ("delete" | "d", args) => {
+                let breakpoint = self.parse_breakpoint(args)?;
+                anyhow::ensure!(self.vm.remove_breakpoint(breakpoint), "no such breakpoint");
+            }
+            ("breakpoints", []) => {
+                for breakpoint in self.vm.breakpoints() {
+                    writeln!(out, "{:#x} pc {}", breakpoint.code_hash, breakpoint.pc)?;
+                }
+            }
+
+            ("instruction" | "i", []) => self.print_location(out)?,
+            ("registers" | "r", []) => self.print_registers(out)?,
+            ("flags", []) => {
+                let flags = self.vm.flags();
+                writeln!(
+                    out,
+                    "lt: {}, eq: {}, gt: {}",
+                    flags.less_than, flags.equal, flags.greater
+                )?;
+            }
+            ("stack", []) => self.print_stack(out, 8)?,
+            ("stack", [count]) => {
+                self.print_stack(out, count.parse().context("invalid slot count")?)?;
+            }
+            ("heap", [heap, offset]) => self.print_heap(out, heap, offset, 1)?,
+            ("heap", [heap, offset, count]) => {
+                let count = count.parse().context("invalid word count")?;
+                self.print_heap(out, heap, offset, count)?;
+            }
+            ("frames" | "bt", []) => self.print_frames(out)?,
+            ("storage", []) => {
+                for ((address, key), value) in self.vm.get_storage_state() {
+                    writeln!(out, "{address:?} [{key:#x}] = {value:#x}")?;
+                }
+            }
+
+            ("help" | "h", []) => writeln!(out, "{HELP}")?,
+            ("quit" | "q", []) => return Ok(Flow::Quit),
+            _ => anyhow::bail!("unknown command or invalid arguments; see `help`"),
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Runs the VM using `run` and reports where it has stopped.
+    fn run(
+        &mut self,
+        out: &mut impl Write,
+        run: impl FnOnce(&mut Self) -> Option<ExecutionEnd>,
+    ) -> anyhow::Result<()> {
+        if let Some(end) = &self.end {
+            anyhow::bail!("execution has finished: {}", format_end(end));
+        }
+
+        match run(self) {
+            Some(ExecutionEnd::Breakpoint(breakpoint)) => {
+                writeln!(out, "Stopped at breakpoint (pc {})", breakpoint.pc)?;
+            }
+            Some(ExecutionEnd::Watchpoint(hit)) => writeln!(out, "Watchpoint: {hit:?}")?,
+            Some(ExecutionEnd::SuspendedOnHook(hook)) => writeln!(out, "Suspended on hook {hook}")?,
+            Some(
+                end @ (ExecutionEnd::ProgramFinished(_)
+                | ExecutionEnd::Reverted(_)
+                | ExecutionEnd::Panicked(_)),
+            ) => {
+                writeln!(out, "Execution finished: {}", format_end(&end))?;
+                self.end = Some(end);
+                return Ok(());
+            }
+            Some(end) => writeln!(out, "Stopped: {end:?}")?,
+            None => {}
+        }
+        self.print_location(out)
+    }
+
+    /// Executes one instruction, even if there is a breakpoint at it.
+    fn step_instruction(&mut self) -> Option<ExecutionEnd> {
+        match self.vm.step(&mut self.world, &mut ()) {
+            // The first step only reports the breakpoint; the second one executes the instruction.
+            Some(ExecutionEnd::Breakpoint(_)) => self.vm.step(&mut self.world, &mut ()),
+            end => end,
+        }
+    }
+
+    /// Executes one instruction, and then continues execution while the callstack is deeper than
+    /// it was initially minus `levels`.
+    fn run_while_deeper_than(&mut self, levels: usize) -> Option<ExecutionEnd> {
+        let depth = self.vm.number_of_callframes().saturating_sub(levels);
+        if let Some(end) = self.step_instruction() {
+            return Some(end);
+        }
+        while self.vm.number_of_callframes() > depth {
+            if let Some(end) = self.vm.step(&mut self.world, &mut ()) {
+                return Some(end);
+            }
+        }
+        None
+    }
+
+    fn parse_breakpoint(&mut self, args: &[&str]) -> anyhow::Result<Breakpoint> {
+        let (address, pc) = match args {
+            [pc] => (self.vm.current_frame().code_address(), pc),
+            [address, pc] => (parse_address(address)?, pc),
+            _ => anyhow::bail!("expected `[address] <pc>`"),
+        };
+        let code_hash = self
+            .world
+            .code_hash(address)
+            .with_context(|| format!("no contract deployed at {address:?}"))?;
+        Ok(Breakpoint {
+            code_hash,
+            pc: pc.parse().context("invalid program counter")?,
+        })
+    }
+
+    /// Prints the current contract, program counter and instruction.
+    pub(crate) fn print_location(&mut self, out: &mut impl Write) -> anyhow::Result<()> {
+        let frame = self.vm.current_frame();
+        let address = frame.code_address();
+        let Some(pc) = frame.program_counter() else {
+            writeln!(out, "{address:?}: panicking")?;
+            return Ok(());
+        };
+        let instruction = instruction_at(&frame, pc);
+        writeln!(
+            out,
+            "{address:?} pc {pc}: {instruction} [{:#018x}]",
+            instruction.raw()
+        )?;
+        Ok(())
+    }
+
+    fn print_registers(&self, out: &mut impl Write) -> anyhow::Result<()> {
+        for register in 1..16 {
+            let (value, is_pointer) = self.vm.read_register(register);
+            let pointer_marker = if is_pointer { " (pointer)" } else { "" };
+            writeln!(out, "r{register:<2} = {value:#x}{pointer_marker}")?;
+        }
+        Ok(())
+    }
+
+    fn print_stack(&mut self, out: &mut impl Write, count: u16) -> anyhow::Result<()> {
+        let frame = self.vm.current_frame();
+        let sp = frame.stack_pointer();
+        writeln!(out, "sp = {sp}")?;
+        for index in (sp.saturating_sub(count)..sp).rev() {
+            let (value, is_pointer) = frame.read_stack(index);
+            let pointer_marker = if is_pointer { " (pointer)" } else { "" };
+            writeln!(out, "[{index}] = {value:#x}{pointer_marker}")?;
+        }
+        Ok(())
+    }
+
+    fn print_heap(
+        &mut self,
+        out: &mut impl Write,
+        heap: &str,
+        offset: &str,
+        count: u32,
+    ) -> anyhow::Result<()> {
+        let heap = match heap {
+            "main" => self.vm.current_frame().heap(),
+            "aux" => self.vm.current_frame().aux_heap(),
+            id => HeapId::from_u32_unchecked(id.parse().context("invalid heap")?),
+        };
+        let offset = parse_u256(offset)?;
+        anyhow::ensure!(offset.bits() <= 32, "offset is too large");
+        let offset = offset.low_u32();
+        for i in 0..count {
+            let word_offset = i
+                .checked_mul(32)
+                .and_then(|delta| offset.checked_add(delta))
+                .context("offset is too large")?;
+            let value = self.vm.read_heap_u256(heap, word_offset);
+            writeln!(out, "{word_offset:#x}: {value:#x}")?;
+        }
+        Ok(())
+    }
+
+    fn print_frames(&mut self, out: &mut impl Write) -> anyhow::Result<()> {
+        for n in 0..self.vm.number_of_callframes() {
+            let frame = self.vm.callframe(n);
+            let kind = if frame.is_near_call() { "near" } else { "far" };
+            let pc = frame
+                .program_counter()
+                .map_or_else(|| "panicking".to_owned(), |pc| format!("pc {pc}"));
+            writeln!(
+                out,
+                "#{n} {kind} {address:?} (code {code:?}, caller {caller:?}), {pc}, gas {gas}",
+                address = frame.address(),
+                code = frame.code_address(),
+                caller = frame.caller(),
+                gas = frame.gas(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the raw instruction at `pc` from the bytecode of the frame.
+fn instruction_at(frame: &impl CallframeInterface, pc: u16) -> DisassembledInstruction {
+    let word = frame.read_contract_code(pc / 4);
+    // Instructions are packed into words big-endian, while `U256` limbs are little-endian.
+    DisassembledInstruction::from_raw(word.0[3 - usize::from(pc % 4)])
+}
+
+fn format_end(end: &ExecutionEnd) -> String {
+    match end {
+        ExecutionEnd::ProgramFinished(data) => format!("returned 0x{}", to_hex(data)),
+        ExecutionEnd::Reverted(data) => format!("reverted with 0x{}", to_hex(data)),
+        other => format!("{other:?}"),
+    }
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use zksync_vm2::Settings;
+
+    use super::*;
+
+    fn execute(debugger: &mut Debugger, command: &str) -> String {
+        let mut out = vec![];
+        debugger.execute(command, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn debugging_session() {
+        // Far calls an address without code, with the call itself as the exception handler,
+        // until it runs out of gas.
+        let bytecode = include_bytes!("../../vm2/src/tests/bytecodes/call_far");
+        let address = H160::from_low_u64_be(0x_1234_5678_90ab_cdef);
+        let mut world = DebugWorld::default();
+        world.add_contract(address, bytecode).unwrap();
+        let program = world.program_at(address).unwrap();
+        let vm = VirtualMachine::new(
+            address,
+            program,
+            H160::zero(),
+            &[],
+            10_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+        let mut debugger = Debugger::new(vm, world);
+
+        let output = execute(&mut debugger, "frames");
+        assert!(output.starts_with("#0 far"), "{output}");
+        let output = execute(&mut debugger, "i");
+        assert!(output.contains("pc 0:"), "{output}");
+        execute(&mut debugger, "b 0");
+        assert_eq!(execute(&mut debugger, "breakpoints").lines().count(), 1);
+        assert_eq!(execute(&mut debugger, "registers").lines().count(), 15);
+        assert_eq!(execute(&mut debugger, "heap main 0 2").lines().count(), 2);
+
+        let output = execute(&mut debugger, "c");
+        assert!(output.starts_with("Stopped at breakpoint"), "{output}");
+        execute(&mut debugger, "delete 0");
+        let output = execute(&mut debugger, "c");
+        assert!(
+            output.starts_with("Execution finished: Panicked"),
+            "{output}"
+        );
+        assert!(debugger.execute("s", &mut vec![]).is_err());
+        assert_eq!(debugger.execute("quit", &mut vec![]).unwrap(), Flow::Quit);
+    }
+}
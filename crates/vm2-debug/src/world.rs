@@ -0,0 +1,244 @@
+//! [`World`] implementation backed by bytecodes and storage loaded from files.
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
+use anyhow::Context as _;
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW;
+use zksync_vm2::{Program, StorageInterface, StorageSlot, World};
+
+/// World with a fixed set of contracts and initial storage.
+#[derive(Debug, Default)]
+pub(crate) struct DebugWorld {
+    address_to_hash: BTreeMap<H160, U256>,
+    hash_to_contract: BTreeMap<U256, Program<(), Self>>,
+    storage: BTreeMap<(H160, U256), U256>,
+}
+
+impl DebugWorld {
+    /// Deploys `bytecode` at `address`, returning the bytecode hash.
+    pub(crate) fn add_contract(&mut self, address: H160, bytecode: &[u8]) -> anyhow::Result<U256> {
+        anyhow::ensure!(
+            !bytecode.is_empty() && bytecode.len() % 32 == 0,
+            "bytecode length must be a positive multiple of 32 bytes, got {}",
+            bytecode.len()
+        );
+        let len_in_words =
+            u16::try_from(bytecode.len() / 32).context("bytecode must not exceed 65535 words")?;
+
+        // The hash only needs to be unique; it mimics the versioned hash layout expected by the VM.
+        let mut hasher = DefaultHasher::new();
+        self.hash_to_contract.len().hash(&mut hasher);
+        bytecode.hash(&mut hasher);
+        let mut hash_bytes = [0; 32];
+        hash_bytes[0] = 1;
+        hash_bytes[2..4].copy_from_slice(&len_in_words.to_be_bytes());
+        hash_bytes[24..].copy_from_slice(&hasher.finish().to_be_bytes());
+        let hash = U256::from_big_endian(&hash_bytes);
+
+        let program = Program::new(bytecode, false).with_code_hash(hash);
+        self.address_to_hash.insert(address, hash);
+        self.hash_to_contract.insert(hash, program);
+        Ok(hash)
+    }
+
+    /// Returns the program deployed at `address`.
+    pub(crate) fn program_at(&self, address: H160) -> Option<Program<(), Self>> {
+        let hash = self.address_to_hash.get(&address)?;
+        self.hash_to_contract.get(hash).cloned()
+    }
+
+    /// Returns the bytecode hash of the contract deployed at `address`.
+    pub(crate) fn code_hash(&self, address: H160) -> Option<U256> {
+        self.address_to_hash.get(&address).copied()
+    }
+
+    /// Loads a state file. Each non-empty line of the file that isn't a `#` comment is either
+    ///
+    /// - `contract <address> <bytecode file>`, which deploys a contract, or
+    /// - `storage <address> <key> <value>`, which sets a storage slot.
+    ///
+    /// Bytecode file paths are relative to the state file.
+    pub(crate) fn load_state(&mut self, path: &Path) -> anyhow::Result<()> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed reading state file `{}`", path.display()))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        for (i, line) in contents.lines().enumerate() {
+            self.load_state_line(base_dir, line)
+                .with_context(|| format!("invalid line {} in state file", i + 1))?;
+        }
+        Ok(())
+    }
+
+    fn load_state_line(&mut self, base_dir: &Path, line: &str) -> anyhow::Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        let parts: Vec<_> = line.split_whitespace().collect();
+        match parts.as_slice() {
+            ["contract", address, bytecode_path] => {
+                let address = parse_address(address)?;
+                let bytecode = read_bytecode(&base_dir.join(bytecode_path))?;
+                self.add_contract(address, &bytecode)?;
+            }
+            ["storage", address, key, value] => {
+                let address = parse_address(address)?;
+                self.storage
+                    .insert((address, parse_u256(key)?), parse_u256(value)?);
+            }
+            _ => anyhow::bail!("unrecognized entry `{line}`"),
+        }
+        Ok(())
+    }
+}
+
+impl StorageInterface for DebugWorld {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        let deployer_address = H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let value = if contract == deployer_address {
+            // Code info of deployed contracts, keyed by the contract address
+            let mut key_bytes = [0; 32];
+            key.to_big_endian(&mut key_bytes);
+            let address = H160::from_slice(&key_bytes[12..]);
+            self.address_to_hash.get(&address).copied()
+        } else {
+            self.storage.get(&(contract, key)).copied()
+        };
+
+        match value {
+            Some(value) => StorageSlot {
+                value,
+                is_write_initial: false,
+            },
+            None => StorageSlot::EMPTY,
+        }
+    }
+
+    fn cost_of_writing_storage(&mut self, _initial_slot: StorageSlot, _new_value: U256) -> u32 {
+        0
+    }
+
+    fn is_free_storage_slot(&self, _contract: &H160, _key: &U256) -> bool {
+        false
+    }
+}
+
+impl World<()> for DebugWorld {
+    fn decommit(&mut self, hash: U256) -> Program<(), Self> {
+        self.hash_to_contract
+            .get(&hash)
+            .cloned()
+            .unwrap_or_else(|| panic!("no contract with bytecode hash {hash:#x}"))
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        self.decommit(hash)
+            .code_page()
+            .iter()
+            .flat_map(|word| {
+                let mut buffer = [0_u8; 32];
+                word.to_big_endian(&mut buffer);
+                buffer
+            })
+            .collect()
+    }
+}
+
+/// Reads a bytecode file, which either contains raw bytes or a hex string (optionally `0x`-prefixed).
+pub(crate) fn read_bytecode(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let bytes =
+        fs::read(path).with_context(|| format!("failed reading bytecode `{}`", path.display()))?;
+    match std::str::from_utf8(&bytes) {
+        Ok(text) if !text.trim().is_empty() => parse_hex(text.trim()).or(Ok(bytes)),
+        _ => Ok(bytes),
+    }
+}
+
+pub(crate) fn parse_hex(s: &str) -> anyhow::Result<Vec<u8>> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    anyhow::ensure!(s.len() % 2 == 0, "hex string has odd length");
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            let byte = s.get(i..i + 2).context("non-ASCII hex string")?;
+            u8::from_str_radix(byte, 16).with_context(|| format!("invalid hex byte `{byte}`"))
+        })
+        .collect()
+}
+
+/// Parses a `U256` value, either hex (`0x`-prefixed) or decimal.
+pub(crate) fn parse_u256(s: &str) -> anyhow::Result<U256> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(s).ok(),
+    };
+    parsed.with_context(|| format!("invalid number `{s}`"))
+}
+
+/// Parses an address, which may be shortened (e.g., `0x8002`).
+pub(crate) fn parse_address(s: &str) -> anyhow::Result<H160> {
+    let value = parse_u256(s)?;
+    anyhow::ensure!(value.bits() <= 160, "address `{s}` is too large");
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    Ok(H160::from_slice(&bytes[12..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsing_numbers() {
+        assert_eq!(parse_u256("0x10").unwrap(), U256::from(16_u64));
+        assert_eq!(parse_u256("10").unwrap(), U256::from(10_u64));
+        assert!(parse_u256("0xzz").is_err());
+        assert_eq!(
+            parse_address("0x8002").unwrap(),
+            H160::from_low_u64_be(0x8002)
+        );
+        assert!(parse_address(&format!("0x1{}", "0".repeat(40))).is_err());
+        assert_eq!(parse_hex("0x0aff").unwrap(), [10, 255]);
+        assert!(parse_hex("0xa").is_err());
+    }
+
+    #[test]
+    fn loading_state() {
+        let mut world = DebugWorld::default();
+        let base_dir = Path::new(".");
+        world.load_state_line(base_dir, "  # comment").unwrap();
+        world
+            .load_state_line(base_dir, "storage 0x1234 0x1 5")
+            .unwrap();
+        assert!(world
+            .load_state_line(base_dir, "storage 0x1234 0x1")
+            .is_err());
+
+        let slot = world.read_storage(H160::from_low_u64_be(0x1234), U256::one());
+        assert_eq!(slot.value, U256::from(5_u64));
+        assert!(!slot.is_write_initial);
+        let slot = world.read_storage(H160::from_low_u64_be(0x1234), U256::zero());
+        assert_eq!(slot.value, U256::zero());
+    }
+
+    #[test]
+    fn deployed_contracts_are_visible_to_deployer() {
+        let mut world = DebugWorld::default();
+        let address = H160::repeat_byte(0x23);
+        let hash = world.add_contract(address, &[0; 64]).unwrap();
+        assert_eq!(world.code_hash(address), Some(hash));
+        assert!(world.add_contract(address, &[0; 33]).is_err());
+
+        let deployer_address = H160::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+        let key = U256::from_big_endian(address.as_bytes());
+        assert_eq!(world.read_storage(deployer_address, key).value, hash);
+        assert_eq!(world.decommit_code(hash), [0; 64]);
+    }
+}
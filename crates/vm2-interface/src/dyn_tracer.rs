@@ -0,0 +1,733 @@
+use primitive_types::{H160, U256};
+
+use crate::{
+    opcodes,
+    v2::{FarCallEnter, FarCallExit, HeapAccess, PanicReason, StorageAccess, Tracer},
+    CallframeInterface, CallingMode, CycleStats, Event, Flags, GlobalStateInterface, HeapId,
+    L2ToL1Log, Opcode, OpcodeType, ReturnType, ShouldStop, StateInterface,
+};
+
+/// Object-safe counterpart of [`GlobalStateInterface`] used by [`DynTracer`]s.
+///
+/// Methods returning `impl Trait` in [`StateInterface`] return boxed trait objects here.
+#[allow(missing_docs)] // methods are documented in `StateInterface` and `GlobalStateInterface`
+pub trait DynStateInterface {
+    fn read_register(&self, register: u8) -> (U256, bool);
+    fn set_register(&mut self, register: u8, value: U256, is_pointer: bool);
+
+    fn current_frame(&mut self) -> Box<dyn CallframeInterface + '_>;
+    fn number_of_callframes(&self) -> usize;
+    fn callframe(&mut self, n: usize) -> Box<dyn CallframeInterface + '_>;
+
+    fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8;
+    fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256;
+    fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256);
+
+    fn flags(&self) -> Flags;
+    fn set_flags(&mut self, flags: Flags);
+
+    fn transaction_number(&self) -> u16;
+    fn set_transaction_number(&mut self, value: u16);
+
+    fn context_u128_register(&self) -> u128;
+    fn set_context_u128_register(&mut self, value: u128);
+
+    fn get_storage_state(&self) -> Box<dyn Iterator<Item = ((H160, U256), U256)> + '_>;
+    fn get_storage(&mut self, address: H160, slot: U256) -> U256;
+
+    fn get_transient_storage_state(&self) -> Box<dyn Iterator<Item = ((H160, U256), U256)> + '_>;
+    fn get_transient_storage(&self, address: H160, slot: U256) -> U256;
+    fn write_transient_storage(&mut self, address: H160, slot: U256, value: U256);
+
+    fn events(&self) -> Box<dyn Iterator<Item = Event> + '_>;
+    fn l2_to_l1_logs(&self) -> Box<dyn Iterator<Item = L2ToL1Log> + '_>;
+
+    fn pubdata(&self) -> i32;
+    fn set_pubdata(&mut self, value: i32);
+}
+
+/// Object-safe counterpart of [`Tracer`], allowing to choose tracers at runtime.
+///
+/// Unlike `Tracer`, methods take the opcode as a value and the VM state as a [`DynStateInterface`] trait object.
+/// A list of dynamic tracers, `Vec<Box<dyn DynTracer>>`, implements `Tracer` by calling each tracer in order.
+/// Conversely, static tracers can be put into such a list by wrapping them into a [`TracerAdapter`].
+///
+/// # Overhead
+///
+/// A static `Tracer` is monomorphized for each opcode, so that callbacks it doesn't use are optimized away.
+/// Dynamic tracers are called via a virtual call for every executed instruction, twice per instruction and tracer,
+/// and the VM cannot skip any callbacks. Additionally, [`DynStateInterface`] methods are virtual calls,
+/// and the methods returning call frames or iterators allocate. Adapting a `Tracer` to `DynTracer` adds
+/// a `match` on the opcode, and boxes the call frames the tracer accesses.
+///
+/// Thus, executing the VM with dynamic tracers is noticeably slower than with static ones, even if
+/// the tracer list is empty. Use `()` or a static tracer in hot paths where tracing is not required.
+///
+/// # Examples
+///
+/// ```
+/// # use zksync_vm2_interface::{v2::Tracer, DynStateInterface, DynTracer, Opcode, TracerAdapter};
+/// struct FarCallCounter(usize);
+///
+/// impl DynTracer for FarCallCounter {
+///     fn before_instruction(&mut self, opcode: Opcode, _state: &mut dyn DynStateInterface) {
+///         if let Opcode::FarCall(_) = opcode {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+///
+/// fn choose_tracers(count_far_calls: bool) -> Vec<Box<dyn DynTracer>> {
+///     // Static tracers can be used as well.
+///     let mut tracers: Vec<Box<dyn DynTracer>> = vec![Box::new(TracerAdapter(()))];
+///     if count_far_calls {
+///         tracers.push(Box::new(FarCallCounter(0)));
+///     }
+///     tracers
+/// }
+///
+/// fn assert_tracer<T: Tracer>(_: &T) {}
+/// assert_tracer(&choose_tracers(true));
+/// ```
+pub trait DynTracer {
+    /// Dynamic counterpart of [`Tracer::before_instruction()`].
+    ///
+    /// The default implementation does nothing.
+    fn before_instruction(&mut self, opcode: Opcode, state: &mut dyn DynStateInterface) {
+        let _ = (opcode, state);
+    }
+
+    /// Dynamic counterpart of [`Tracer::after_instruction()`].
+    ///
+    /// The default implementation does nothing.
+    #[must_use]
+    fn after_instruction(
+        &mut self,
+        opcode: Opcode,
+        state: &mut dyn DynStateInterface,
+    ) -> ShouldStop {
+        let _ = (opcode, state);
+        ShouldStop::Continue
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_extra_prover_cycles()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        let _ = stats;
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_far_call_enter()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        let _ = call;
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_far_call_exit()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        let _ = call;
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_storage_access()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_storage_access(&mut self, access: StorageAccess) {
+        let _ = access;
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_heap_access()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        let _ = access;
+    }
+
+    /// Dynamic counterpart of [`Tracer::on_panic()`].
+    ///
+    /// The default implementation does nothing.
+    fn on_panic(&mut self, reason: PanicReason) {
+        let _ = reason;
+    }
+}
+
+impl<D: DynTracer + ?Sized> Tracer for Vec<Box<D>> {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let mut state = StaticState(state);
+        for tracer in self {
+            tracer.before_instruction(OP::VALUE, &mut state);
+        }
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let mut state = StaticState(state);
+        let mut should_stop = ShouldStop::Continue;
+        for tracer in self {
+            should_stop = should_stop.merge(tracer.after_instruction(OP::VALUE, &mut state));
+        }
+        should_stop
+    }
+
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        for tracer in self {
+            tracer.on_extra_prover_cycles(stats);
+        }
+    }
+
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        for tracer in self {
+            tracer.on_far_call_enter(call);
+        }
+    }
+
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        for tracer in self {
+            tracer.on_far_call_exit(call);
+        }
+    }
+
+    fn on_storage_access(&mut self, access: StorageAccess) {
+        for tracer in self {
+            tracer.on_storage_access(access);
+        }
+    }
+
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        for tracer in self {
+            tracer.on_heap_access(access);
+        }
+    }
+
+    fn on_panic(&mut self, reason: PanicReason) {
+        for tracer in self {
+            tracer.on_panic(reason);
+        }
+    }
+}
+
+/// Adapter allowing to use a static [`Tracer`] as a [`DynTracer`], e.g. to put it into a list of dynamic tracers.
+///
+/// In addition to the overhead of dynamic tracers, the adapter `match`es the opcode to call the static tracer
+/// and boxes the call frames the tracer accesses.
+#[derive(Debug, Default)]
+pub struct TracerAdapter<T>(pub T);
+
+impl<T: Tracer> DynTracer for TracerAdapter<T> {
+    fn before_instruction(&mut self, opcode: Opcode, state: &mut dyn DynStateInterface) {
+        struct Before<'a, 's, T>(&'a mut T, DynState<'s>);
+
+        impl<T: Tracer> OpcodeVisitor for Before<'_, '_, T> {
+            type Output = ();
+
+            fn visit<OP: OpcodeType>(mut self) {
+                self.0.before_instruction::<OP, _>(&mut self.1);
+            }
+        }
+
+        visit_opcode(opcode, Before(&mut self.0, DynState(state)));
+    }
+
+    fn after_instruction(
+        &mut self,
+        opcode: Opcode,
+        state: &mut dyn DynStateInterface,
+    ) -> ShouldStop {
+        struct After<'a, 's, T>(&'a mut T, DynState<'s>);
+
+        impl<T: Tracer> OpcodeVisitor for After<'_, '_, T> {
+            type Output = ShouldStop;
+
+            fn visit<OP: OpcodeType>(mut self) -> ShouldStop {
+                self.0.after_instruction::<OP, _>(&mut self.1)
+            }
+        }
+
+        visit_opcode(opcode, After(&mut self.0, DynState(state)))
+    }
+
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        self.0.on_extra_prover_cycles(stats);
+    }
+
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        self.0.on_far_call_enter(call);
+    }
+
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        self.0.on_far_call_exit(call);
+    }
+
+    fn on_storage_access(&mut self, access: StorageAccess) {
+        self.0.on_storage_access(access);
+    }
+
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        self.0.on_heap_access(access);
+    }
+
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.0.on_panic(reason);
+    }
+}
+
+/// Allows to call generic code for an [`Opcode`] value.
+trait OpcodeVisitor {
+    type Output;
+
+    fn visit<OP: OpcodeType>(self) -> Self::Output;
+}
+
+fn visit_opcode<V: OpcodeVisitor>(opcode: Opcode, visitor: V) -> V::Output {
+    use opcodes::{Delegate, FarCall, Mimic, Normal, Panic, Ret, Revert};
+
+    match opcode {
+        Opcode::Nop => visitor.visit::<opcodes::Nop>(),
+        Opcode::Add => visitor.visit::<opcodes::Add>(),
+        Opcode::Sub => visitor.visit::<opcodes::Sub>(),
+        Opcode::And => visitor.visit::<opcodes::And>(),
+        Opcode::Or => visitor.visit::<opcodes::Or>(),
+        Opcode::Xor => visitor.visit::<opcodes::Xor>(),
+        Opcode::ShiftLeft => visitor.visit::<opcodes::ShiftLeft>(),
+        Opcode::ShiftRight => visitor.visit::<opcodes::ShiftRight>(),
+        Opcode::RotateLeft => visitor.visit::<opcodes::RotateLeft>(),
+        Opcode::RotateRight => visitor.visit::<opcodes::RotateRight>(),
+        Opcode::Mul => visitor.visit::<opcodes::Mul>(),
+        Opcode::Div => visitor.visit::<opcodes::Div>(),
+        Opcode::NearCall => visitor.visit::<opcodes::NearCall>(),
+        Opcode::FarCall(CallingMode::Normal) => visitor.visit::<FarCall<Normal>>(),
+        Opcode::FarCall(CallingMode::Delegate) => visitor.visit::<FarCall<Delegate>>(),
+        Opcode::FarCall(CallingMode::Mimic) => visitor.visit::<FarCall<Mimic>>(),
+        Opcode::Ret(ReturnType::Normal) => visitor.visit::<Ret<Normal>>(),
+        Opcode::Ret(ReturnType::Revert) => visitor.visit::<Ret<Revert>>(),
+        Opcode::Ret(ReturnType::Panic) => visitor.visit::<Ret<Panic>>(),
+        Opcode::Jump => visitor.visit::<opcodes::Jump>(),
+        Opcode::Event => visitor.visit::<opcodes::Event>(),
+        Opcode::L2ToL1Message => visitor.visit::<opcodes::L2ToL1Message>(),
+        Opcode::Decommit => visitor.visit::<opcodes::Decommit>(),
+        Opcode::This => visitor.visit::<opcodes::This>(),
+        Opcode::Caller => visitor.visit::<opcodes::Caller>(),
+        Opcode::CodeAddress => visitor.visit::<opcodes::CodeAddress>(),
+        Opcode::ErgsLeft => visitor.visit::<opcodes::ErgsLeft>(),
+        Opcode::SP => visitor.visit::<opcodes::SP>(),
+        Opcode::ContextMeta => visitor.visit::<opcodes::ContextMeta>(),
+        Opcode::ContextU128 => visitor.visit::<opcodes::ContextU128>(),
+        Opcode::SetContextU128 => visitor.visit::<opcodes::SetContextU128>(),
+        Opcode::IncrementTxNumber => visitor.visit::<opcodes::IncrementTxNumber>(),
+        Opcode::AuxMutating0 => visitor.visit::<opcodes::AuxMutating0>(),
+        Opcode::PrecompileCall => visitor.visit::<opcodes::PrecompileCall>(),
+        Opcode::HeapRead => visitor.visit::<opcodes::HeapRead>(),
+        Opcode::HeapWrite => visitor.visit::<opcodes::HeapWrite>(),
+        Opcode::AuxHeapRead => visitor.visit::<opcodes::AuxHeapRead>(),
+        Opcode::AuxHeapWrite => visitor.visit::<opcodes::AuxHeapWrite>(),
+        Opcode::StaticMemoryRead => visitor.visit::<opcodes::StaticMemoryRead>(),
+        Opcode::StaticMemoryWrite => visitor.visit::<opcodes::StaticMemoryWrite>(),
+        Opcode::PointerRead => visitor.visit::<opcodes::PointerRead>(),
+        Opcode::PointerAdd => visitor.visit::<opcodes::PointerAdd>(),
+        Opcode::PointerSub => visitor.visit::<opcodes::PointerSub>(),
+        Opcode::PointerPack => visitor.visit::<opcodes::PointerPack>(),
+        Opcode::PointerShrink => visitor.visit::<opcodes::PointerShrink>(),
+        Opcode::StorageRead => visitor.visit::<opcodes::StorageRead>(),
+        Opcode::StorageWrite => visitor.visit::<opcodes::StorageWrite>(),
+        Opcode::TransientStorageRead => visitor.visit::<opcodes::TransientStorageRead>(),
+        Opcode::TransientStorageWrite => visitor.visit::<opcodes::TransientStorageWrite>(),
+    }
+}
+
+/// [`DynStateInterface`] implementation backed by a [`GlobalStateInterface`], used to call [`DynTracer`]s.
+struct StaticState<'a, S>(&'a mut S);
+
+impl<S: GlobalStateInterface> DynStateInterface for StaticState<'_, S> {
+    fn read_register(&self, register: u8) -> (U256, bool) {
+        self.0.read_register(register)
+    }
+
+    fn set_register(&mut self, register: u8, value: U256, is_pointer: bool) {
+        self.0.set_register(register, value, is_pointer);
+    }
+
+    fn current_frame(&mut self) -> Box<dyn CallframeInterface + '_> {
+        Box::new(self.0.current_frame())
+    }
+
+    fn number_of_callframes(&self) -> usize {
+        self.0.number_of_callframes()
+    }
+
+    fn callframe(&mut self, n: usize) -> Box<dyn CallframeInterface + '_> {
+        Box::new(self.0.callframe(n))
+    }
+
+    fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8 {
+        self.0.read_heap_byte(heap, offset)
+    }
+
+    fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256 {
+        self.0.read_heap_u256(heap, offset)
+    }
+
+    fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256) {
+        self.0.write_heap_u256(heap, offset, value);
+    }
+
+    fn flags(&self) -> Flags {
+        self.0.flags()
+    }
+
+    fn set_flags(&mut self, flags: Flags) {
+        self.0.set_flags(flags);
+    }
+
+    fn transaction_number(&self) -> u16 {
+        self.0.transaction_number()
+    }
+
+    fn set_transaction_number(&mut self, value: u16) {
+        self.0.set_transaction_number(value);
+    }
+
+    fn context_u128_register(&self) -> u128 {
+        self.0.context_u128_register()
+    }
+
+    fn set_context_u128_register(&mut self, value: u128) {
+        self.0.set_context_u128_register(value);
+    }
+
+    fn get_storage_state(&self) -> Box<dyn Iterator<Item = ((H160, U256), U256)> + '_> {
+        Box::new(self.0.get_storage_state())
+    }
+
+    fn get_storage(&mut self, address: H160, slot: U256) -> U256 {
+        self.0.get_storage(address, slot)
+    }
+
+    fn get_transient_storage_state(&self) -> Box<dyn Iterator<Item = ((H160, U256), U256)> + '_> {
+        Box::new(self.0.get_transient_storage_state())
+    }
+
+    fn get_transient_storage(&self, address: H160, slot: U256) -> U256 {
+        self.0.get_transient_storage(address, slot)
+    }
+
+    fn write_transient_storage(&mut self, address: H160, slot: U256, value: U256) {
+        self.0.write_transient_storage(address, slot, value);
+    }
+
+    fn events(&self) -> Box<dyn Iterator<Item = Event> + '_> {
+        Box::new(self.0.events())
+    }
+
+    fn l2_to_l1_logs(&self) -> Box<dyn Iterator<Item = L2ToL1Log> + '_> {
+        Box::new(self.0.l2_to_l1_logs())
+    }
+
+    fn pubdata(&self) -> i32 {
+        self.0.pubdata()
+    }
+
+    fn set_pubdata(&mut self, value: i32) {
+        self.0.set_pubdata(value);
+    }
+}
+
+/// [`GlobalStateInterface`] implementation backed by a [`DynStateInterface`], used to adapt [`Tracer`]s.
+struct DynState<'a>(&'a mut dyn DynStateInterface);
+
+impl StateInterface for DynState<'_> {
+    fn read_register(&self, register: u8) -> (U256, bool) {
+        self.0.read_register(register)
+    }
+
+    fn set_register(&mut self, register: u8, value: U256, is_pointer: bool) {
+        self.0.set_register(register, value, is_pointer);
+    }
+
+    fn current_frame(&mut self) -> impl CallframeInterface + '_ {
+        self.0.current_frame()
+    }
+
+    fn number_of_callframes(&self) -> usize {
+        self.0.number_of_callframes()
+    }
+
+    fn callframe(&mut self, n: usize) -> impl CallframeInterface + '_ {
+        self.0.callframe(n)
+    }
+
+    fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8 {
+        self.0.read_heap_byte(heap, offset)
+    }
+
+    fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256 {
+        self.0.read_heap_u256(heap, offset)
+    }
+
+    fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256) {
+        self.0.write_heap_u256(heap, offset, value);
+    }
+
+    fn flags(&self) -> Flags {
+        self.0.flags()
+    }
+
+    fn set_flags(&mut self, flags: Flags) {
+        self.0.set_flags(flags);
+    }
+
+    fn transaction_number(&self) -> u16 {
+        self.0.transaction_number()
+    }
+
+    fn set_transaction_number(&mut self, value: u16) {
+        self.0.set_transaction_number(value);
+    }
+
+    fn context_u128_register(&self) -> u128 {
+        self.0.context_u128_register()
+    }
+
+    fn set_context_u128_register(&mut self, value: u128) {
+        self.0.set_context_u128_register(value);
+    }
+
+    fn get_storage_state(&self) -> impl Iterator<Item = ((H160, U256), U256)> {
+        self.0.get_storage_state()
+    }
+
+    fn get_transient_storage_state(&self) -> impl Iterator<Item = ((H160, U256), U256)> {
+        self.0.get_transient_storage_state()
+    }
+
+    fn get_transient_storage(&self, address: H160, slot: U256) -> U256 {
+        self.0.get_transient_storage(address, slot)
+    }
+
+    fn write_transient_storage(&mut self, address: H160, slot: U256, value: U256) {
+        self.0.write_transient_storage(address, slot, value);
+    }
+
+    fn events(&self) -> impl Iterator<Item = Event> {
+        self.0.events()
+    }
+
+    fn l2_to_l1_logs(&self) -> impl Iterator<Item = L2ToL1Log> {
+        self.0.l2_to_l1_logs()
+    }
+
+    fn pubdata(&self) -> i32 {
+        self.0.pubdata()
+    }
+
+    fn set_pubdata(&mut self, value: i32) {
+        self.0.set_pubdata(value);
+    }
+}
+
+impl GlobalStateInterface for DynState<'_> {
+    fn get_storage(&mut self, address: H160, slot: U256) -> U256 {
+        self.0.get_storage(address, slot)
+    }
+}
+
+impl<C: CallframeInterface + ?Sized> CallframeInterface for Box<C> {
+    fn address(&self) -> H160 {
+        (**self).address()
+    }
+
+    fn set_address(&mut self, address: H160) {
+        (**self).set_address(address);
+    }
+
+    fn code_address(&self) -> H160 {
+        (**self).code_address()
+    }
+
+    fn set_code_address(&mut self, address: H160) {
+        (**self).set_code_address(address);
+    }
+
+    fn caller(&self) -> H160 {
+        (**self).caller()
+    }
+
+    fn set_caller(&mut self, address: H160) {
+        (**self).set_caller(address);
+    }
+
+    fn program_counter(&self) -> Option<u16> {
+        (**self).program_counter()
+    }
+
+    fn set_program_counter(&mut self, value: u16) {
+        (**self).set_program_counter(value);
+    }
+
+    fn exception_handler(&self) -> u16 {
+        (**self).exception_handler()
+    }
+
+    fn set_exception_handler(&mut self, value: u16) {
+        (**self).set_exception_handler(value);
+    }
+
+    fn is_static(&self) -> bool {
+        (**self).is_static()
+    }
+
+    fn is_kernel(&self) -> bool {
+        (**self).is_kernel()
+    }
+
+    fn gas(&self) -> u32 {
+        (**self).gas()
+    }
+
+    fn set_gas(&mut self, new_gas: u32) {
+        (**self).set_gas(new_gas);
+    }
+
+    fn context_u128(&self) -> u128 {
+        (**self).context_u128()
+    }
+
+    fn set_context_u128(&mut self, value: u128) {
+        (**self).set_context_u128(value);
+    }
+
+    fn is_near_call(&self) -> bool {
+        (**self).is_near_call()
+    }
+
+    fn read_stack(&self, index: u16) -> (U256, bool) {
+        (**self).read_stack(index)
+    }
+
+    fn write_stack(&mut self, index: u16, value: U256, is_pointer: bool) {
+        (**self).write_stack(index, value, is_pointer);
+    }
+
+    fn stack_pointer(&self) -> u16 {
+        (**self).stack_pointer()
+    }
+
+    fn set_stack_pointer(&mut self, value: u16) {
+        (**self).set_stack_pointer(value);
+    }
+
+    fn heap(&self) -> HeapId {
+        (**self).heap()
+    }
+
+    fn heap_bound(&self) -> u32 {
+        (**self).heap_bound()
+    }
+
+    fn set_heap_bound(&mut self, value: u32) {
+        (**self).set_heap_bound(value);
+    }
+
+    fn aux_heap(&self) -> HeapId {
+        (**self).aux_heap()
+    }
+
+    fn aux_heap_bound(&self) -> u32 {
+        (**self).aux_heap_bound()
+    }
+
+    fn set_aux_heap_bound(&mut self, value: u32) {
+        (**self).set_aux_heap_bound(value);
+    }
+
+    fn read_contract_code(&self, slot: u16) -> U256 {
+        (**self).read_contract_code(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testonly::DummyState;
+
+    #[derive(Debug, Default)]
+    struct OpcodeLog(Vec<Opcode>);
+
+    impl DynTracer for OpcodeLog {
+        fn before_instruction(&mut self, opcode: Opcode, _: &mut dyn DynStateInterface) {
+            self.0.push(opcode);
+        }
+    }
+
+    struct StopOnRevert;
+
+    impl DynTracer for StopOnRevert {
+        fn after_instruction(
+            &mut self,
+            opcode: Opcode,
+            _: &mut dyn DynStateInterface,
+        ) -> ShouldStop {
+            if opcode == Opcode::Ret(ReturnType::Revert) {
+                ShouldStop::Stop
+            } else {
+                ShouldStop::Continue
+            }
+        }
+    }
+
+    /// Static tracer adapted to `DynTracer`.
+    #[derive(Debug, Default)]
+    struct FarCallCounter(usize);
+
+    impl Tracer for FarCallCounter {
+        fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _: &mut S) {
+            if let Opcode::FarCall(CallingMode::Mimic) = OP::VALUE {
+                self.0 += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn opcodes_are_passed_as_values() {
+        let mut tracers = vec![Box::new(OpcodeLog::default())];
+        tracers.before_instruction::<opcodes::Nop, _>(&mut DummyState);
+        tracers.before_instruction::<opcodes::Ret<opcodes::Panic>, _>(&mut DummyState);
+        assert_eq!(tracers[0].0, [Opcode::Nop, Opcode::Ret(ReturnType::Panic)]);
+    }
+
+    #[test]
+    fn static_tracers_can_be_used_dynamically() {
+        let mut counter = TracerAdapter(FarCallCounter::default());
+        for opcode in [
+            Opcode::FarCall(CallingMode::Normal),
+            Opcode::FarCall(CallingMode::Mimic),
+            Opcode::Add,
+            Opcode::FarCall(CallingMode::Mimic),
+        ] {
+            counter.before_instruction(opcode, &mut StaticState(&mut DummyState));
+        }
+        assert_eq!(counter.0 .0, 2);
+    }
+
+    #[test]
+    fn any_tracer_can_stop_execution() {
+        let mut tracers: Vec<Box<dyn DynTracer>> = vec![
+            Box::new(StopOnRevert),
+            Box::new(TracerAdapter(FarCallCounter::default())),
+        ];
+        let should_stop =
+            tracers.after_instruction::<opcodes::Ret<opcodes::Normal>, _>(&mut DummyState);
+        assert!(matches!(should_stop, ShouldStop::Continue));
+        let should_stop =
+            tracers.after_instruction::<opcodes::Ret<opcodes::Revert>, _>(&mut DummyState);
+        assert!(matches!(should_stop, ShouldStop::Stop));
+
+        tracers.reverse();
+        let should_stop =
+            tracers.after_instruction::<opcodes::Ret<opcodes::Revert>, _>(&mut DummyState);
+        assert!(matches!(should_stop, ShouldStop::Stop));
+    }
+}
@@ -92,7 +92,9 @@
 //! }
 //! ```
 
-pub use self::{state_interface::*, tracer_interface::*};
+pub use self::{dyn_tracer::*, state_interface::*, tracer_interface::*};
 
+mod dyn_tracer;
 mod state_interface;
 mod tracer_interface;
+pub mod v2;
@@ -298,7 +298,7 @@ pub enum ShouldStop {
 impl ShouldStop {
     #[must_use]
     #[inline(always)]
-    fn merge(self, other: ShouldStop) -> ShouldStop {
+    pub(crate) fn merge(self, other: ShouldStop) -> ShouldStop {
         match (self, other) {
             (ShouldStop::Continue, ShouldStop::Continue) => ShouldStop::Continue,
             _ => ShouldStop::Stop,
@@ -0,0 +1,501 @@
+//! Version 2 of the tracer interface, which reports VM events (far calls, storage and heap accesses, panics)
+//! in addition to executed instructions.
+//!
+//! Following the [crate-level rules](crate), the original [`Tracer`](crate::Tracer) is not changed. Instead,
+//! this module defines a new [`Tracer`] trait. Existing tracers work with VMs using this version by wrapping them
+//! into a [`V1Adapter`], and tracers that need the new events should implement this trait instead of the original one.
+//!
+//! Like original tracers, multiple tracers can be combined by building a linked list out of tuples.
+//!
+//! # Examples
+//!
+//! ```
+//! # use zksync_vm2_interface::{
+//! #     v2::{FarCallEnter, Tracer, V1Adapter}, GlobalStateInterface, Opcode, OpcodeType,
+//! # };
+//! struct FarCallCounter(usize);
+//!
+//! impl Tracer for FarCallCounter {
+//!     fn on_far_call_enter(&mut self, call: FarCallEnter) {
+//!         if !call.is_failed {
+//!             self.0 += 1;
+//!         }
+//!     }
+//! }
+//!
+//! // Tracer implementing the original interface
+//! struct InstructionCounter(usize);
+//!
+//! impl zksync_vm2_interface::Tracer for InstructionCounter {
+//!     fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _: &mut S) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let tracer = (FarCallCounter(0), V1Adapter(InstructionCounter(0)));
+//! # fn assert_tracer(_: &impl Tracer) {}
+//! # assert_tracer(&tracer);
+//! ```
+
+use primitive_types::{H160, U256};
+
+use crate::{
+    CallingMode, CycleStats, GlobalStateInterface, HeapId, OpcodeType, ReturnType, ShouldStop,
+    Tracer as TracerV1,
+};
+
+/// EraVM instruction and event tracer.
+///
+/// Instruction callbacks are the same as for the [original tracer](crate::Tracer). Event callbacks are called
+/// between [`Self::before_instruction()`] and [`Self::after_instruction()`] of the instruction causing the event.
+pub trait Tracer {
+    /// This method is executed before an instruction handler.
+    ///
+    /// The default implementation does nothing.
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let _ = state;
+    }
+
+    /// This method is executed after an instruction handler.
+    ///
+    /// The return value indicates whether the VM should continue or stop execution.
+    ///
+    /// The default implementation does nothing.
+    #[must_use]
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let _ = state;
+        ShouldStop::Continue
+    }
+
+    /// Provides cycle statistics for "complex" instructions from the prover perspective (mostly precompile calls).
+    ///
+    /// The default implementation does nothing.
+    fn on_extra_prover_cycles(&mut self, _stats: CycleStats) {}
+
+    /// Called when a far call pushes a new frame, after the frame is set up but before any of its instructions
+    /// are executed. Hence, this is called between [`Self::before_instruction()`] and [`Self::after_instruction()`]
+    /// for the [`FarCall`](crate::opcodes::FarCall) instruction.
+    ///
+    /// A far call *always* pushes a frame, even if it fails (e.g., because the called contract cannot be decommitted);
+    /// in this case, [`FarCallEnter::is_failed`] is set and the new frame immediately panics.
+    ///
+    /// The default implementation does nothing.
+    fn on_far_call_enter(&mut self, _call: FarCallEnter) {}
+
+    /// Called when a frame pushed by a far call is popped, after the return value is resolved and before
+    /// the control is returned to the caller. This is called between [`Self::before_instruction()`]
+    /// and [`Self::after_instruction()`] for the [`Ret`](crate::opcodes::Ret) instruction, including panics not caused
+    /// by a `Ret` instruction (e.g., running out of gas).
+    ///
+    /// Returning from the initial frame does not pop it, so this method is not called in this case.
+    ///
+    /// The default implementation does nothing.
+    fn on_far_call_exit(&mut self, _call: FarCallExit) {}
+
+    /// Called on each storage or transient storage access performed by a
+    /// [`StorageRead`](crate::opcodes::StorageRead), [`StorageWrite`](crate::opcodes::StorageWrite),
+    /// [`TransientStorageRead`](crate::opcodes::TransientStorageRead) or
+    /// [`TransientStorageWrite`](crate::opcodes::TransientStorageWrite) instruction, between [`Self::before_instruction()`]
+    /// and [`Self::after_instruction()`]. Storage reads performed by the VM itself (e.g., reading code info
+    /// during far calls) and accesses via [`StateInterface`](crate::StateInterface) are not reported.
+    ///
+    /// The default implementation does nothing.
+    fn on_storage_access(&mut self, _access: StorageAccess) {}
+
+    /// Called on each heap word read or written by a [`HeapRead`](crate::opcodes::HeapRead), [`HeapWrite`](crate::opcodes::HeapWrite),
+    /// [`AuxHeapRead`](crate::opcodes::AuxHeapRead), [`AuxHeapWrite`](crate::opcodes::AuxHeapWrite),
+    /// [`StaticMemoryRead`](crate::opcodes::StaticMemoryRead), [`StaticMemoryWrite`](crate::opcodes::StaticMemoryWrite)
+    /// or [`PointerRead`](crate::opcodes::PointerRead) instruction, between [`Self::before_instruction()`]
+    /// and [`Self::after_instruction()`]. Instructions that panic (e.g., because they cannot pay for heap growth)
+    /// do not access the heap. Heap accesses performed by precompiles or by the VM itself (e.g., when returning
+    /// from the initial frame) are not reported.
+    ///
+    /// The default implementation does nothing.
+    fn on_heap_access(&mut self, _access: HeapAccess) {}
+
+    /// Called when the VM panics for the specified reason, before the panicking frame is unwound.
+    /// This is called between [`Self::before_instruction()`] and [`Self::after_instruction()`]
+    /// for the [`Ret`](crate::opcodes::Ret) instruction performing the panic; if the panic is caused by another instruction,
+    /// the `Ret` instruction is executed right after it.
+    ///
+    /// Explicit panics (i.e., executing a panicking `Ret` instruction) are not reported.
+    ///
+    /// The default implementation does nothing.
+    fn on_panic(&mut self, _reason: PanicReason) {}
+}
+
+/// Adapter allowing to use a tracer implementing the [original interface](crate::Tracer) with VMs using this version.
+/// The adapted tracer ignores all events.
+#[derive(Debug, Clone, Default)]
+pub struct V1Adapter<T>(pub T);
+
+impl<T: TracerV1> Tracer for V1Adapter<T> {
+    #[inline(always)]
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        self.0.before_instruction::<OP, S>(state);
+    }
+
+    #[inline(always)]
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        self.0.after_instruction::<OP, S>(state)
+    }
+
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        self.0.on_extra_prover_cycles(stats);
+    }
+}
+
+/// No-op tracer implementation.
+impl Tracer for () {}
+
+// Multiple tracers can be combined by building a linked list out of tuples.
+impl<A: Tracer, B: Tracer> Tracer for (A, B) {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        self.0.before_instruction::<OP, S>(state);
+        self.1.before_instruction::<OP, S>(state);
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        self.0
+            .after_instruction::<OP, S>(state)
+            .merge(self.1.after_instruction::<OP, S>(state))
+    }
+
+    fn on_extra_prover_cycles(&mut self, stats: CycleStats) {
+        self.0.on_extra_prover_cycles(stats);
+        self.1.on_extra_prover_cycles(stats);
+    }
+
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        self.0.on_far_call_enter(call);
+        self.1.on_far_call_enter(call);
+    }
+
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        self.0.on_far_call_exit(call);
+        self.1.on_far_call_exit(call);
+    }
+
+    fn on_storage_access(&mut self, access: StorageAccess) {
+        self.0.on_storage_access(access);
+        self.1.on_storage_access(access);
+    }
+
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        self.0.on_heap_access(access);
+        self.1.on_heap_access(access);
+    }
+
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.0.on_panic(reason);
+        self.1.on_panic(reason);
+    }
+}
+
+/// Fat pointer to a slice of a heap, such as far call calldata or returndata.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeapPointer {
+    /// Heap the pointer refers to.
+    pub heap: HeapId,
+    /// Start of the pointed slice in bytes.
+    pub start: u32,
+    /// Length of the pointed slice in bytes.
+    pub length: u32,
+    /// Additional pointer offset inside the `start..(start + length)` range.
+    pub offset: u32,
+}
+
+/// Far call information supplied to [`Tracer::on_far_call_enter()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct FarCallEnter {
+    /// Calling mode of the far call.
+    pub calling_mode: CallingMode,
+    /// Address of the caller as seen by the called frame (i.e., `msg.sender`).
+    pub caller: H160,
+    /// Address of the called frame. Differs from `code_address` for delegate calls.
+    pub address: H160,
+    /// Address of the executed code.
+    pub code_address: H160,
+    /// Gas passed to the new frame, including mandated gas.
+    pub gas: u32,
+    /// Whether the new frame is static.
+    pub is_static: bool,
+    /// Calldata pointer passed to the new frame. Zero if the far call has failed.
+    pub calldata: HeapPointer,
+    /// Whether the far call has failed before executing any code. The new frame immediately panics in this case.
+    pub is_failed: bool,
+}
+
+impl FarCallEnter {
+    /// Creates far call information with the provided fields.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        calling_mode: CallingMode,
+        caller: H160,
+        address: H160,
+        code_address: H160,
+        gas: u32,
+        is_static: bool,
+        calldata: HeapPointer,
+        is_failed: bool,
+    ) -> Self {
+        Self {
+            calling_mode,
+            caller,
+            address,
+            code_address,
+            gas,
+            is_static,
+            calldata,
+            is_failed,
+        }
+    }
+}
+
+/// Far call information supplied to [`Tracer::on_far_call_exit()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct FarCallExit {
+    /// Return type. May be [`ReturnType::Panic`] even for a non-panicking `Ret` instruction
+    /// if the returned pointer is invalid.
+    pub return_type: ReturnType,
+    /// Address of the returning frame.
+    pub address: H160,
+    /// Address of the code executed by the returning frame.
+    pub code_address: H160,
+    /// Gas left in the returning frame; it is returned to the caller.
+    pub gas_left: u32,
+    /// Returndata pointer, or `None` on panic.
+    pub returndata: Option<HeapPointer>,
+}
+
+impl FarCallExit {
+    /// Creates far call information with the provided fields.
+    pub fn new(
+        return_type: ReturnType,
+        address: H160,
+        code_address: H160,
+        gas_left: u32,
+        returndata: Option<HeapPointer>,
+    ) -> Self {
+        Self {
+            return_type,
+            address,
+            code_address,
+            gas_left,
+            returndata,
+        }
+    }
+}
+
+/// Storage access information supplied to [`Tracer::on_storage_access()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StorageAccess {
+    /// Whether the accessed storage is transient.
+    pub is_transient: bool,
+    /// Address of the contract owning the storage.
+    pub address: H160,
+    /// Storage key.
+    pub key: U256,
+    /// Value of the slot before the access.
+    pub read_value: U256,
+    /// Value written to the slot, or `None` for reads.
+    pub written_value: Option<U256>,
+    /// Whether the slot is warm, which makes the access cheaper. A slot is warm if it was read or written before
+    /// in the same VM run, including by frames that were reverted afterwards and by storage reads performed
+    /// by the VM itself (e.g., reading code info during far calls). Always `false` for transient storage.
+    pub is_warm: bool,
+    /// Whether this is an initial write to the slot, i.e. the slot is not present in the persistent storage.
+    /// Always `false` for reads and transient storage.
+    pub is_initial: bool,
+    /// Gas refunded for the access. Always 0 for transient storage.
+    pub refund: u32,
+    /// Change in pubdata caused by the access. Always 0 for reads and transient storage.
+    pub pubdata_cost: i32,
+}
+
+impl StorageAccess {
+    /// Creates a persistent storage access with the provided fields.
+    #[allow(clippy::too_many_arguments)]
+    pub fn persistent(
+        address: H160,
+        key: U256,
+        read_value: U256,
+        written_value: Option<U256>,
+        is_warm: bool,
+        is_initial: bool,
+        refund: u32,
+        pubdata_cost: i32,
+    ) -> Self {
+        Self {
+            is_transient: false,
+            address,
+            key,
+            read_value,
+            written_value,
+            is_warm,
+            is_initial,
+            refund,
+            pubdata_cost,
+        }
+    }
+
+    /// Creates a transient storage access with the provided fields.
+    pub fn transient(
+        address: H160,
+        key: U256,
+        read_value: U256,
+        written_value: Option<U256>,
+    ) -> Self {
+        Self {
+            is_transient: true,
+            address,
+            key,
+            read_value,
+            written_value,
+            is_warm: false,
+            is_initial: false,
+            refund: 0,
+            pubdata_cost: 0,
+        }
+    }
+
+    /// Checks whether this access is a write.
+    pub fn is_write(&self) -> bool {
+        self.written_value.is_some()
+    }
+}
+
+/// Heap access information supplied to [`Tracer::on_heap_access()`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct HeapAccess {
+    /// Accessed heap.
+    pub heap: HeapId,
+    /// Offset of the accessed 32-byte word in the heap.
+    pub offset: u32,
+    /// Read or written value. For fat pointer reads, bytes outside the pointer are read as zeros.
+    pub value: U256,
+    /// Whether the word was written.
+    pub is_write: bool,
+}
+
+impl HeapAccess {
+    /// Creates a heap read with the provided fields.
+    pub fn read(heap: HeapId, offset: u32, value: U256) -> Self {
+        Self {
+            heap,
+            offset,
+            value,
+            is_write: false,
+        }
+    }
+
+    /// Creates a heap write with the provided fields.
+    pub fn write(heap: HeapId, offset: u32, value: U256) -> Self {
+        Self {
+            heap,
+            offset,
+            value,
+            is_write: true,
+        }
+    }
+}
+
+/// Reason of a VM panic supplied to [`Tracer::on_panic()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PanicReason {
+    /// Not enough ergs to pay for an instruction, heap growth or decommitment.
+    OutOfErgs,
+    /// Instruction that may cause side effects executed in a static context.
+    StaticViolation,
+    /// Kernel-only instruction executed outside the kernel mode.
+    KernelViolation,
+    /// Invalid instruction was executed.
+    InvalidInstruction,
+    /// Heap offset or bound does not fit into 32 bits.
+    HeapGrowthOverflow,
+    /// Far call to a contract whose code could not be decommitted (e.g., has malformed code info).
+    FarCallDecommitFailed,
+    /// Far call to an unsupported shard.
+    FarCallShardFailure,
+    /// Invalid fat pointer, e.g. a pointer operation on a non-pointer value or a pointer with out-of-bounds offset.
+    InvalidFatPointer,
+    /// Non-kernel frame returned a pointer to its calldata or to another heap owned by an older frame.
+    ReturnedCalldataPointer,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{opcodes, testonly::DummyState};
+
+    /// Tracer implementing the original interface.
+    struct FarCallCounter(usize);
+
+    impl TracerV1 for FarCallCounter {
+        fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _: &mut S) {
+            if let crate::Opcode::FarCall(_) = OP::VALUE {
+                self.0 += 1;
+            }
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct FarCallLog(Vec<FarCallEnter>);
+
+    impl Tracer for FarCallLog {
+        fn on_far_call_enter(&mut self, call: FarCallEnter) {
+            self.0.push(call);
+        }
+    }
+
+    fn far_call<T: Tracer>(tracer: &mut T, call: FarCallEnter) {
+        tracer.before_instruction::<opcodes::FarCall<opcodes::Normal>, _>(&mut DummyState);
+        tracer.on_far_call_enter(call);
+        let _ = tracer.after_instruction::<opcodes::FarCall<opcodes::Normal>, _>(&mut DummyState);
+    }
+
+    #[test]
+    fn adapted_original_tracers_compose_with_new_ones() {
+        let pointer = HeapPointer {
+            heap: HeapId::FIRST_CALLDATA,
+            start: 0,
+            length: 32,
+            offset: 0,
+        };
+        let call = FarCallEnter::new(
+            CallingMode::Normal,
+            H160::repeat_byte(1),
+            H160::repeat_byte(2),
+            H160::repeat_byte(2),
+            1_000,
+            false,
+            pointer,
+            false,
+        );
+
+        let mut tracer = (
+            V1Adapter((FarCallCounter(0), ((), FarCallCounter(0)))),
+            (FarCallLog::default(), ((), FarCallLog::default())),
+        );
+        far_call(&mut tracer, call);
+        let (V1Adapter((first_counter, ((), second_counter))), (first_log, ((), second_log))) =
+            tracer;
+        assert_eq!((first_counter.0, second_counter.0), (1, 1));
+        assert_eq!(first_log.0, [call]);
+        assert_eq!(second_log.0, [call]);
+    }
+}
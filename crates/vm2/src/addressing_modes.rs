@@ -1,5 +1,7 @@
 //! Addressing modes supported by EraVM.
 
+use std::fmt;
+
 #[cfg(feature = "arbitrary")]
 use arbitrary::{Arbitrary, Unstructured};
 use enum_dispatch::enum_dispatch;
@@ -176,6 +178,18 @@ impl Arguments {
         self
     }
 
+    /// Returns the `src0`, `src1`, `dst0` and `dst1` register indices and the two immediates, i.e. the operand fields
+    /// of the encoded instruction. Fields not used by the instruction are zero.
+    pub(crate) fn encoded_operands(&self) -> ([u8; 4], [u16; 2]) {
+        let registers = [
+            self.source_registers.register1().0,
+            self.source_registers.register2().0,
+            self.destination_registers.register1().0,
+            self.destination_registers.register2().0,
+        ];
+        (registers, [self.immediate1, self.immediate2])
+    }
+
     /// Returns the register that receives the second output (`dst1`) of the instruction.
     /// Stored for every opcode during decoding so `full_boilerplate` can clear it when unwritten.
     pub(crate) fn dst1_register(&self) -> Register {
@@ -183,6 +197,33 @@ impl Arguments {
     }
 }
 
+/// Outputs all argument fields in an assembly-like format, e.g. `.gt src=(r1, r2) dst=(r3, r0) imm=(5, 0) ergs=6`,
+/// followed by ` kernel_only` and / or ` non_static` mode requirements.
+impl fmt::Display for Arguments {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} src=(r{}, r{}) dst=(r{}, r{}) imm=({}, {}) ergs={}",
+            self.predicate().assembly_suffix(),
+            self.source_registers.register1().0,
+            self.source_registers.register2().0,
+            self.destination_registers.register1().0,
+            self.destination_registers.register2().0,
+            self.immediate1,
+            self.immediate2,
+            self.get_static_gas_cost()
+        )?;
+        let requirements = self.mode_requirements();
+        if requirements.0 & 1 != 0 {
+            formatter.write_str(" kernel_only")?;
+        }
+        if requirements.0 & 2 != 0 {
+            formatter.write_str(" non_static")?;
+        }
+        Ok(())
+    }
+}
+
 /// Register passed as a first instruction argument.
 ///
 /// It must not be used simultaneously with [`AbsoluteStack`], [`RelativeStack`], [`AdvanceStackPointer`],
@@ -0,0 +1,485 @@
+//! Assembler for textual EraVM assembly.
+
+use std::{collections::HashMap, error, fmt};
+
+use primitive_types::U256;
+use zkevm_opcode_defs::{
+    decoding::EncodingModeProduction, Condition, DecodedOpcode, Opcode, OPCODES_TABLE,
+    SET_FLAGS_FLAG_IDX,
+};
+
+use crate::{
+    decode::predicate,
+    disassembler::{can_set_flags, mnemonic, modifiers, operand_slots, OperandForm, OperandSlot},
+};
+
+pub(crate) const CONDITIONS: [Condition; 8] = [
+    Condition::Always,
+    Condition::Gt,
+    Condition::Lt,
+    Condition::Eq,
+    Condition::Ge,
+    Condition::Le,
+    Condition::Ne,
+    Condition::GtOrLt,
+];
+
+/// Error returned by [`assemble()`]. Line numbers are 1-based.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AssemblyError {
+    /// A line could not be parsed.
+    Syntax {
+        /// Number of the offending line.
+        line: usize,
+        /// Description of the problem.
+        message: String,
+    },
+    /// An instruction is well-formed, but EraVM has no encoding for its combination of modifiers and operands
+    /// (e.g., an immediate used as a destination).
+    Unencodable {
+        /// Number of the offending line.
+        line: usize,
+    },
+    /// A label is defined more than once.
+    DuplicateLabel {
+        /// Number of the line with the repeated definition.
+        line: usize,
+        /// Name of the label.
+        label: String,
+    },
+    /// A label is referenced, but never defined.
+    UndefinedLabel {
+        /// Number of the line with the reference.
+        line: usize,
+        /// Name of the label.
+        label: String,
+    },
+    /// The program has more instructions than can be addressed, or doesn't fit into the code page.
+    TooLong,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax { line, message } => write!(formatter, "line {line}: {message}"),
+            Self::Unencodable { line } => write!(
+                formatter,
+                "line {line}: instruction cannot be encoded with the specified modifiers and operands"
+            ),
+            Self::DuplicateLabel { line, label } => {
+                write!(formatter, "line {line}: label `{label}` is already defined")
+            }
+            Self::UndefinedLabel { line, label } => {
+                write!(formatter, "line {line}: label `{label}` is not defined")
+            }
+            Self::TooLong => formatter.write_str("program does not fit into the code page"),
+        }
+    }
+}
+
+impl error::Error for AssemblyError {}
+
+/// Assembles textual EraVM assembly into bytecode accepted by [`Program::new()`](crate::Program::new()).
+///
+/// The syntax is the one produced by [`DisassembledInstruction`](crate::DisassembledInstruction), so the output
+/// of the disassembler can be assembled back into the same bytecode. In addition:
+///
+/// - `;` starts a comment that lasts until the end of the line.
+/// - `name:` defines a label. Labels can be referenced as `@name` wherever an immediate is expected,
+///   including stack and code page addresses (e.g., `code[r1 + @table]`).
+/// - Immediates can be written in decimal or in `0x`-prefixed hex.
+/// - Trailing register destinations can be omitted, in which case they default to `r0`; e.g., `jump @loop`.
+/// - `.cell <value>` adds a 256-bit word to the constants section of the code page. A label defined
+///   right before `.cell` refers to the index of the word in the code page.
+///
+/// Instructions are padded with `invalid` to a whole number of code page words, and are followed by constants.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be parsed or contains instructions with no EraVM encoding.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblyError> {
+    let mut assembler = Assembler::default();
+    for (i, line) in source.lines().enumerate() {
+        assembler.parse_line(i + 1, line)?;
+    }
+    assembler.finish()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LabelTarget {
+    Instruction(usize),
+    Cell(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    Number(u16),
+    Label(&'a str),
+}
+
+#[derive(Debug)]
+struct ParsedOperand<'a> {
+    form: OperandForm,
+    is_push: bool,
+    register: u8,
+    value: Value<'a>,
+}
+
+impl ParsedOperand<'_> {
+    fn fits(&self, slot: OperandSlot, src0: OperandForm, dst0: OperandForm) -> bool {
+        match slot {
+            OperandSlot::Src0 => self.form == src0 && !self.is_push,
+            OperandSlot::Dst0 => {
+                self.form == dst0
+                    && !matches!(self.form, OperandForm::Immediate | OperandForm::CodePage)
+                    && (self.is_push || self.form != OperandForm::StackPushPop)
+            }
+            OperandSlot::Src1 | OperandSlot::Dst1 => self.form == OperandForm::Register,
+            OperandSlot::Label0 | OperandSlot::Label1 => self.form == OperandForm::Immediate,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingInstruction<'a> {
+    line: usize,
+    variant_index: usize,
+    condition: Condition,
+    registers: [u8; 4],
+    immediates: [Value<'a>; 2],
+}
+
+#[derive(Debug, Default)]
+struct Assembler<'a> {
+    instructions: Vec<PendingInstruction<'a>>,
+    cells: Vec<U256>,
+    labels: HashMap<&'a str, LabelTarget>,
+    unbound_labels: Vec<&'a str>,
+}
+
+impl<'a> Assembler<'a> {
+    fn parse_line(&mut self, line: usize, text: &'a str) -> Result<(), AssemblyError> {
+        let syntax = |message| AssemblyError::Syntax { line, message };
+
+        let mut text = text.split_once(';').map_or(text, |(code, _)| code).trim();
+        while let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_identifier(label) {
+                return Err(syntax(format!("invalid label `{label}`")));
+            }
+            if self.labels.contains_key(label) || self.unbound_labels.contains(&label) {
+                return Err(AssemblyError::DuplicateLabel {
+                    line,
+                    label: label.to_owned(),
+                });
+            }
+            self.unbound_labels.push(label);
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        let (head, operands) = text
+            .split_once(char::is_whitespace)
+            .map_or((text, ""), |(head, operands)| (head, operands.trim()));
+        if head == ".cell" {
+            let value = parse_u256(operands).map_err(syntax)?;
+            self.bind_labels(LabelTarget::Cell(self.cells.len()));
+            self.cells.push(value);
+        } else {
+            let instruction = parse_instruction(line, head, operands)?;
+            self.bind_labels(LabelTarget::Instruction(self.instructions.len()));
+            self.instructions.push(instruction);
+        }
+        Ok(())
+    }
+
+    fn bind_labels(&mut self, target: LabelTarget) {
+        for label in self.unbound_labels.drain(..) {
+            self.labels.insert(label, target);
+        }
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, AssemblyError> {
+        self.bind_labels(LabelTarget::Instruction(self.instructions.len()));
+
+        let instruction_words = self.instructions.len().div_ceil(4);
+        if u16::try_from(instruction_words + self.cells.len()).is_err() {
+            return Err(AssemblyError::TooLong);
+        }
+        let resolve = |value: Value<'_>, line: usize| match value {
+            Value::Number(number) => Ok(number),
+            Value::Label(label) => {
+                let target = match self.labels.get(label) {
+                    Some(LabelTarget::Instruction(index)) => *index,
+                    Some(LabelTarget::Cell(index)) => instruction_words + index,
+                    None => {
+                        return Err(AssemblyError::UndefinedLabel {
+                            line,
+                            label: label.to_owned(),
+                        })
+                    }
+                };
+                u16::try_from(target).map_err(|_| AssemblyError::TooLong)
+            }
+        };
+
+        let mut bytecode = Vec::with_capacity((instruction_words + self.cells.len()) * 32);
+        for instruction in &self.instructions {
+            let [src0_reg_idx, src1_reg_idx, dst0_reg_idx, dst1_reg_idx] = instruction.registers;
+            let encoded = DecodedOpcode::<8, EncodingModeProduction> {
+                variant: OPCODES_TABLE[instruction.variant_index],
+                condition: instruction.condition,
+                src0_reg_idx,
+                src1_reg_idx,
+                dst0_reg_idx,
+                dst1_reg_idx,
+                imm_0: resolve(instruction.immediates[0], instruction.line)?,
+                imm_1: resolve(instruction.immediates[1], instruction.line)?,
+            }
+            .serialize_as_integer();
+            bytecode.extend_from_slice(&encoded.to_be_bytes());
+        }
+
+        let padding = encoded_invalid().to_be_bytes();
+        for _ in self.instructions.len()..instruction_words * 4 {
+            bytecode.extend_from_slice(&padding);
+        }
+        for cell in &self.cells {
+            let mut word = [0; 32];
+            cell.to_big_endian(&mut word);
+            bytecode.extend_from_slice(&word);
+        }
+        Ok(bytecode)
+    }
+}
+
+fn encoded_invalid() -> u64 {
+    let variant = OPCODES_TABLE
+        .iter()
+        .copied()
+        .find(|variant| matches!(variant.opcode, Opcode::Invalid(_)))
+        .expect("`invalid` opcode must have an encoding");
+    DecodedOpcode::<8, EncodingModeProduction> {
+        variant,
+        condition: Condition::Always,
+        src0_reg_idx: 0,
+        src1_reg_idx: 0,
+        dst0_reg_idx: 0,
+        dst1_reg_idx: 0,
+        imm_0: 0,
+        imm_1: 0,
+    }
+    .serialize_as_integer()
+}
+
+fn parse_instruction<'a>(
+    line: usize,
+    head: &str,
+    operands: &'a str,
+) -> Result<PendingInstruction<'a>, AssemblyError> {
+    let syntax = |message| AssemblyError::Syntax { line, message };
+
+    let (head, sets_flags) = head
+        .strip_suffix('!')
+        .map_or((head, false), |head| (head, true));
+    // Some mnemonics contain dots (e.g., `far_call.delegate`), so the longest matching one is chosen.
+    let (opcode, name) = OPCODES_TABLE
+        .iter()
+        .map(|variant| (variant.opcode, mnemonic(variant.opcode)))
+        .filter(|(_, name)| {
+            head.strip_prefix(name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .max_by_key(|(_, name)| name.len())
+        .ok_or_else(|| syntax(format!("unknown mnemonic `{head}`")))?;
+
+    let mut flags = vec![];
+    let mut condition = None;
+    for part in head[name.len()..].split('.').skip(1) {
+        let modifier = modifiers(opcode)
+            .iter()
+            .find(|(modifier, _)| modifier.strip_prefix('.') == Some(part));
+        let predicate_condition = CONDITIONS.into_iter().find(|&candidate| {
+            predicate(candidate).assembly_suffix().strip_prefix('.') == Some(part)
+        });
+        if let Some(&(_, index)) = modifier.filter(|(_, index)| !flags.contains(index)) {
+            flags.push(index);
+        } else if let Some(predicate_condition) =
+            predicate_condition.filter(|_| condition.is_none())
+        {
+            condition = Some(predicate_condition);
+        } else {
+            return Err(syntax(format!(
+                "unexpected modifier `.{part}` for `{name}`"
+            )));
+        }
+    }
+    if sets_flags {
+        if !can_set_flags(opcode) {
+            return Err(syntax(format!("`{name}` cannot set flags")));
+        }
+        flags.push(SET_FLAGS_FLAG_IDX);
+    }
+
+    let operands = if operands.is_empty() {
+        vec![]
+    } else {
+        operands
+            .split(',')
+            .map(parse_operand)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(syntax)?
+    };
+
+    let (variant_index, slots) = OPCODES_TABLE
+        .iter()
+        .enumerate()
+        .filter(|(_, variant)| {
+            mnemonic(variant.opcode) == name
+                && variant
+                    .flags
+                    .iter()
+                    .enumerate()
+                    .all(|(index, &flag)| flag == flags.contains(&index))
+        })
+        .find_map(|(index, variant)| {
+            let slots = operand_slots(
+                variant.opcode,
+                &variant.flags,
+                variant.src0_operand_type,
+                variant.dst0_operand_type,
+            );
+            let src0 = OperandForm::of(variant.src0_operand_type);
+            let dst0 = OperandForm::of(variant.dst0_operand_type);
+            let omitted_slots_fit = slots.iter().skip(operands.len()).all(|&slot| {
+                slot == OperandSlot::Dst1
+                    || (slot == OperandSlot::Dst0 && dst0 == OperandForm::Register)
+            });
+            let fits = operands.len() <= slots.len()
+                && omitted_slots_fit
+                && operands
+                    .iter()
+                    .zip(&slots)
+                    .all(|(operand, &slot)| operand.fits(slot, src0, dst0));
+            fits.then_some((index, slots))
+        })
+        .ok_or(AssemblyError::Unencodable { line })?;
+
+    let mut instruction = PendingInstruction {
+        line,
+        variant_index,
+        condition: condition.unwrap_or(Condition::Always),
+        registers: [0; 4],
+        immediates: [Value::Number(0); 2],
+    };
+    for (operand, slot) in operands.iter().zip(slots) {
+        match slot {
+            OperandSlot::Src0 => {
+                instruction.registers[0] = operand.register;
+                instruction.immediates[0] = operand.value;
+            }
+            OperandSlot::Src1 => instruction.registers[1] = operand.register,
+            OperandSlot::Dst0 => {
+                instruction.registers[2] = operand.register;
+                instruction.immediates[1] = operand.value;
+            }
+            OperandSlot::Dst1 => instruction.registers[3] = operand.register,
+            OperandSlot::Label0 => instruction.immediates[0] = operand.value,
+            OperandSlot::Label1 => instruction.immediates[1] = operand.value,
+        }
+    }
+    Ok(instruction)
+}
+
+fn parse_operand(text: &str) -> Result<ParsedOperand<'_>, String> {
+    const ADDRESSED_FORMS: [(&str, OperandForm, bool); 5] = [
+        ("stack+=[", OperandForm::StackPushPop, true),
+        ("stack-=[", OperandForm::StackPushPop, false),
+        ("stack-[", OperandForm::RelativeStack, false),
+        ("stack[", OperandForm::AbsoluteStack, false),
+        ("code[", OperandForm::CodePage, false),
+    ];
+
+    let text = text.trim();
+    for (prefix, form, is_push) in ADDRESSED_FORMS {
+        let Some(address) = text.strip_prefix(prefix) else {
+            continue;
+        };
+        let address = address
+            .strip_suffix(']')
+            .ok_or_else(|| format!("missing `]` in operand `{text}`"))?;
+        let mut register = None;
+        let mut value = None;
+        for part in address.split('+').map(str::trim) {
+            if let Some(parsed) = parse_register(part) {
+                if register.replace(parsed).is_some() {
+                    return Err(format!("address `{address}` has multiple registers"));
+                }
+            } else if value.replace(parse_value(part)?).is_some() {
+                return Err(format!("address `{address}` has multiple offsets"));
+            }
+        }
+        return Ok(ParsedOperand {
+            form,
+            is_push,
+            register: register.unwrap_or(0),
+            value: value.unwrap_or(Value::Number(0)),
+        });
+    }
+
+    Ok(if let Some(register) = parse_register(text) {
+        ParsedOperand {
+            form: OperandForm::Register,
+            is_push: false,
+            register,
+            value: Value::Number(0),
+        }
+    } else {
+        ParsedOperand {
+            form: OperandForm::Immediate,
+            is_push: false,
+            register: 0,
+            value: parse_value(text)?,
+        }
+    })
+}
+
+fn parse_register(text: &str) -> Option<u8> {
+    let index = text.strip_prefix('r')?.parse::<u8>().ok()?;
+    (index < 16).then_some(index)
+}
+
+fn parse_value(text: &str) -> Result<Value<'_>, String> {
+    match text.strip_prefix('@') {
+        Some(label) if is_identifier(label) => Ok(Value::Label(label)),
+        Some(label) => parse_u16(label).map(Value::Number),
+        None => parse_u16(text).map(Value::Number),
+    }
+}
+
+fn parse_u16(text: &str) -> Result<u16, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid 16-bit immediate `{text}`"))
+}
+
+fn parse_u256(text: &str) -> Result<U256, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(text).ok(),
+    };
+    parsed.ok_or_else(|| format!("invalid 256-bit value `{text}`"))
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '.')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '_' | '.' | '$'))
+}
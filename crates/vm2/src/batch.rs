@@ -0,0 +1,314 @@
+//! Speculative parallel execution of independent transactions.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    num::NonZeroUsize,
+    panic,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::v2::Tracer;
+
+use crate::{
+    precompiles::Precompiles, CachingWorld, ExecutionEnd, Program, ProgramCache, StorageInterface,
+    StorageSlot, VirtualMachine, World,
+};
+
+/// [`World`] used by VMs in a [`BatchExecutor`]: the shared base world with storage writes of previously
+/// committed transactions applied on top of it.
+///
+/// Programs are decoded from [`World::decommit_code()`] of the base world exactly like in a [`CachingWorld`],
+/// and cached in a [`ProgramCache`] shared by all VMs of the executor. Hooks are disabled for decoded programs;
+/// instructions are fused if enabled with [`BatchExecutor::with_fusion()`].
+pub struct BatchWorld<T, W> {
+    base: W,
+    committed_writes: Arc<BTreeMap<(H160, U256), U256>>,
+    programs: Arc<ProgramCache<T, Self>>,
+    fuse_instructions: bool,
+}
+
+impl<T, W: fmt::Debug> fmt::Debug for BatchWorld<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("BatchWorld")
+            .field("base", &self.base)
+            .field("committed_writes", &self.committed_writes.len())
+            .field("programs", &self.programs)
+            .finish()
+    }
+}
+
+impl<T, W> BatchWorld<T, W> {
+    /// Returns a reference to the base world.
+    pub fn base(&self) -> &W {
+        &self.base
+    }
+
+    /// Returns storage writes of previously committed transactions visible to this world.
+    pub fn committed_writes(&self) -> &BTreeMap<(H160, U256), U256> {
+        &self.committed_writes
+    }
+}
+
+impl<T, W: StorageInterface> StorageInterface for BatchWorld<T, W> {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        let slot = self.base.read_storage(contract, key);
+        match self.committed_writes.get(&(contract, key)) {
+            // Initialness is defined relative to the base state, so it's retained.
+            Some(&value) => StorageSlot { value, ..slot },
+            None => slot,
+        }
+    }
+
+    fn read_storage_value(&mut self, contract: H160, key: U256) -> U256 {
+        match self.committed_writes.get(&(contract, key)) {
+            Some(&value) => value,
+            None => self.base.read_storage_value(contract, key),
+        }
+    }
+
+    fn cost_of_writing_storage(&mut self, initial_slot: StorageSlot, new_value: U256) -> u32 {
+        self.base.cost_of_writing_storage(initial_slot, new_value)
+    }
+
+    fn is_free_storage_slot(&self, contract: &H160, key: &U256) -> bool {
+        self.base.is_free_storage_slot(contract, key)
+    }
+}
+
+impl<T: Tracer, W: World<T>> World<T> for BatchWorld<T, W> {
+    fn decommit(&mut self, hash: U256) -> Program<T, Self> {
+        let base = &mut self.base;
+        self.programs
+            .get_or_decode_bytecode(hash, false, self.fuse_instructions, || {
+                base.decommit_code(hash)
+            })
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        self.base.decommit_code(hash)
+    }
+
+    fn precompiles(&self) -> &impl Precompiles {
+        self.base.precompiles()
+    }
+}
+
+/// Result of executing a single transaction in a [`BatchExecutor`].
+pub struct TransactionResult<T, W> {
+    /// How the VM execution ended.
+    pub end: ExecutionEnd,
+    /// VM after execution. Its [`WorldDiff`](crate::WorldDiff) contains changes made by the transaction.
+    pub vm: VirtualMachine<T, BatchWorld<T, W>>,
+    /// Tracer used for execution.
+    pub tracer: T,
+    /// Whether the transaction conflicted with a preceding transaction and was re-executed serially.
+    pub was_reexecuted: bool,
+}
+
+impl<T: fmt::Debug, W> fmt::Debug for TransactionResult<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("TransactionResult")
+            .field("end", &self.end)
+            .field("tracer", &self.tracer)
+            .field("was_reexecuted", &self.was_reexecuted)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Executes batches of transactions speculatively in parallel against a read-only base world.
+///
+/// Each transaction is run in a separate [`VirtualMachine`] with its own [`WorldDiff`](crate::WorldDiff),
+/// as if it were executed first in the batch. Afterwards, transactions are committed in order: if a transaction
+/// read or wrote a storage slot written by a preceding transaction, it's re-executed serially on top of
+/// the storage writes of all preceding transactions. The results are thus the same as if each transaction
+/// was executed in a new VM on top of the storage writes of all preceding transactions.
+///
+/// This is *not* the same as executing all transactions in a single VM: only storage values are carried over
+/// between transactions. Slots accessed by preceding transactions are not warm, and pubdata already paid
+/// for them is not taken into account, so storage refunds, pubdata costs and consequently gas usage
+/// may differ from executing the transactions in a single VM.
+///
+/// Accesses are tracked per slot regardless of reverts, so a transaction that accessed a conflicting slot
+/// in a reverted frame is re-executed as well. Only storage is shared between transactions;
+/// the base world must be able to decommit all bytecodes used by the batch.
+pub struct BatchExecutor<T, W> {
+    world: W,
+    threads: NonZeroUsize,
+    programs: Arc<ProgramCache<T, BatchWorld<T, W>>>,
+    fuse_instructions: bool,
+}
+
+impl<T, W: fmt::Debug> fmt::Debug for BatchExecutor<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("BatchExecutor")
+            .field("world", &self.world)
+            .field("threads", &self.threads)
+            .field("programs", &self.programs)
+            .field("fuse_instructions", &self.fuse_instructions)
+            .finish()
+    }
+}
+
+impl<T, W> BatchExecutor<T, W> {
+    /// Creates an executor running transactions on at most `threads` threads. Worlds of VMs are cloned
+    /// from `world`, and decoded programs are cached, holding at most `program_cache_capacity` programs.
+    pub fn new(world: W, threads: NonZeroUsize, program_cache_capacity: usize) -> Self {
+        Self {
+            world,
+            threads,
+            programs: Arc::new(ProgramCache::new(program_cache_capacity)),
+            fuse_instructions: false,
+        }
+    }
+
+    /// Sets whether decoded programs fuse instruction pairs into superinstructions (disabled by default).
+    /// See [`Program::new_with_fusion()`] for details.
+    #[must_use]
+    pub fn with_fusion(mut self, fuse_instructions: bool) -> Self {
+        self.fuse_instructions = fuse_instructions;
+        self
+    }
+
+    /// Returns the program cache shared by all VMs of this executor.
+    pub fn program_cache(&self) -> &Arc<ProgramCache<T, BatchWorld<T, W>>> {
+        &self.programs
+    }
+}
+
+impl<T, W> BatchExecutor<T, W>
+where
+    T: Tracer + Send,
+    W: World<T> + Clone + Send,
+{
+    /// Executes `transactions` and returns their results in the same order.
+    ///
+    /// `start` creates a VM for a transaction, e.g. by decommitting the program of the called contract
+    /// from the provided world. VMs are run to completion with a tracer created by `new_tracer`
+    /// for the transaction; a re-executed transaction gets a new tracer.
+    ///
+    /// # Panics
+    ///
+    /// Propagates panics of `start`, `new_tracer` and of the world.
+    pub fn execute<Tx, F, N>(
+        &self,
+        transactions: &[Tx],
+        start: F,
+        new_tracer: N,
+    ) -> Vec<TransactionResult<T, W>>
+    where
+        Tx: Sync,
+        F: Fn(&Tx, &mut BatchWorld<T, W>) -> VirtualMachine<T, BatchWorld<T, W>> + Sync,
+        N: Fn(&Tx) -> T + Sync,
+    {
+        let mut results = self.execute_speculatively(transactions, &start, &new_tracer);
+
+        let mut committed_writes = Arc::new(BTreeMap::new());
+        for (result, transaction) in results.iter_mut().zip(transactions) {
+            let has_conflict = result
+                .vm
+                .world_diff()
+                .accessed_storage_slots()
+                .any(|slot| committed_writes.contains_key(&slot));
+            if has_conflict {
+                let mut world = self.world(committed_writes.clone());
+                *result = execute_transaction(transaction, &start, &new_tracer, &mut world);
+                result.was_reexecuted = true;
+            }
+
+            // The world used for re-execution is dropped at this point, so the writes aren't copied.
+            let storage_state = result.vm.world_diff().get_storage_state();
+            Arc::make_mut(&mut committed_writes).extend(
+                storage_state
+                    .iter()
+                    .map(|(slot, entry)| (*slot, entry.value)),
+            );
+        }
+        results
+    }
+
+    fn execute_speculatively<Tx, F, N>(
+        &self,
+        transactions: &[Tx],
+        start: &F,
+        new_tracer: &N,
+    ) -> Vec<TransactionResult<T, W>>
+    where
+        Tx: Sync,
+        F: Fn(&Tx, &mut BatchWorld<T, W>) -> VirtualMachine<T, BatchWorld<T, W>> + Sync,
+        N: Fn(&Tx) -> T + Sync,
+    {
+        let next_transaction = AtomicUsize::new(0);
+        let thread_count = self.threads.get().min(transactions.len());
+        let mut results: Vec<(usize, TransactionResult<T, W>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..thread_count)
+                .map(|_| {
+                    let mut world = self.world(Arc::default());
+                    let next_transaction = &next_transaction;
+                    scope.spawn(move || {
+                        let mut results = vec![];
+                        loop {
+                            let index = next_transaction.fetch_add(1, Ordering::Relaxed);
+                            let Some(transaction) = transactions.get(index) else {
+                                return results;
+                            };
+                            let result =
+                                execute_transaction(transaction, start, new_tracer, &mut world);
+                            results.push((index, result));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+                })
+                .collect()
+        });
+        results.sort_unstable_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    fn world(&self, committed_writes: Arc<BTreeMap<(H160, U256), U256>>) -> BatchWorld<T, W> {
+        BatchWorld {
+            base: self.world.clone(),
+            committed_writes,
+            programs: self.programs.clone(),
+            fuse_instructions: self.fuse_instructions,
+        }
+    }
+}
+
+fn execute_transaction<T, W, Tx, F, N>(
+    transaction: &Tx,
+    start: &F,
+    new_tracer: &N,
+    world: &mut BatchWorld<T, W>,
+) -> TransactionResult<T, W>
+where
+    T: Tracer,
+    W: World<T>,
+    F: Fn(&Tx, &mut BatchWorld<T, W>) -> VirtualMachine<T, BatchWorld<T, W>>,
+    N: Fn(&Tx) -> T,
+{
+    let mut vm = start(transaction, world);
+    let mut tracer = new_tracer(transaction);
+    let end = vm.run(world, &mut tracer);
+    TransactionResult {
+        end,
+        vm,
+        tracer,
+        was_reexecuted: false,
+    }
+}
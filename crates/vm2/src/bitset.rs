@@ -1,3 +1,5 @@
+use crate::checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder};
+
 /// Bitset with `1 << 16` elements. Used to store pointer flags for VM [`Stack`](crate::stack::Stack).
 #[derive(Clone, PartialEq, Debug, Hash)]
 pub(crate) struct Bitset([u64; 1 << 10]);
@@ -22,6 +24,28 @@ impl Bitset {
     }
 }
 
+// Only non-zero words are stored since pointer flags are usually sparse.
+impl Checkpoint for Bitset {
+    fn encode(&self, encoder: &mut Encoder) {
+        let words: Vec<(u16, u64)> = (0_u16..)
+            .zip(self.0)
+            .filter(|&(_, word)| word != 0)
+            .collect();
+        encoder.put(&words);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let mut bitset = Self::default();
+        for (idx, word) in decoder.get::<Vec<(u16, u64)>>()? {
+            *bitset
+                .0
+                .get_mut(usize::from(idx))
+                .ok_or(CheckpointError::Malformed("pointer flag out of bounds"))? = word;
+        }
+        Ok(bitset)
+    }
+}
+
 #[inline(always)]
 fn slot_and_bit(i: u16) -> (usize, u64) {
     ((i >> 6) as usize, 1u64 << (i & 0b_0011_1111))
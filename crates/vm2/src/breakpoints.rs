@@ -0,0 +1,169 @@
+//! Instruction breakpoints.
+//!
+//! Breakpoints are implemented by patching the handlers of the affected instructions in a per-VM copy
+//! of the [`Program`], so instructions without a breakpoint run exactly as fast as without any.
+//! Patched copies are cached per bytecode hash, so repeated far calls to a contract don't copy its program again.
+
+use std::collections::{BTreeSet, HashMap};
+
+use primitive_types::U256;
+#[cfg(not(feature = "single_instruction_test"))]
+use zksync_vm2_interface::v2::Tracer;
+
+use crate::Program;
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::{instruction::ExecutionStatus, ExecutionEnd, VirtualMachine, World};
+
+/// Location of a breakpoint: an instruction in the program with the specified bytecode hash.
+///
+/// Breakpoints apply to programs [decommitted](crate::World::decommit()) by the VM, and to programs
+/// explicitly [marked](Program::with_code_hash()) with a bytecode hash, such as the initial (bootloader) program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Breakpoint {
+    /// Bytecode hash of the program.
+    pub code_hash: U256,
+    /// Index of the instruction in the program.
+    pub pc: u16,
+}
+
+/// Breakpoints set on a [`VirtualMachine`].
+#[derive(Debug)]
+pub(crate) struct Breakpoints<T, W> {
+    set: BTreeSet<Breakpoint>,
+    /// Breakpoint the VM has stopped on. The instruction at this breakpoint is executed on resume.
+    hit: Option<Breakpoint>,
+    /// Programs with breakpoints patched in together with the programs they were patched from, keyed by bytecode hash.
+    /// Entries are removed when breakpoints in the corresponding bytecode change.
+    patched: HashMap<U256, (Program<T, W>, Program<T, W>)>,
+}
+
+impl<T, W> Default for Breakpoints<T, W> {
+    fn default() -> Self {
+        Self {
+            set: BTreeSet::new(),
+            hit: None,
+            patched: HashMap::new(),
+        }
+    }
+}
+
+impl<T, W> Breakpoints<T, W> {
+    fn pcs(&self, code_hash: U256) -> BTreeSet<u16> {
+        let start = Breakpoint { code_hash, pc: 0 };
+        let end = Breakpoint {
+            code_hash,
+            pc: u16::MAX,
+        };
+        self.set.range(start..=end).map(|bp| bp.pc).collect()
+    }
+}
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> Breakpoints<T, W> {
+    /// Patches breakpoints into a program entering the callstack.
+    pub(crate) fn patch(&mut self, program: Program<T, W>) -> Program<T, W> {
+        if self.set.is_empty() {
+            return program;
+        }
+        let Some(code_hash) = program.code_hash() else {
+            return program;
+        };
+        // The world may supply a different program for the same hash (e.g., after evicting it from a cache).
+        if let Some((original, patched)) = self.patched.get(&code_hash) {
+            if *original == program {
+                return patched.clone();
+            }
+        }
+
+        let pcs = self.pcs(code_hash);
+        if pcs.is_empty() {
+            return program;
+        }
+        let patched = program.with_breakpoints(&pcs);
+        self.patched.insert(code_hash, (program, patched.clone()));
+        patched
+    }
+}
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Sets a breakpoint. [`Self::run()`] and related methods return [`ExecutionEnd::Breakpoint`] when the VM
+    /// is about to execute the instruction at the breakpoint. On resume, the instruction is executed normally.
+    ///
+    /// The breakpoint takes effect immediately, including for the programs of the callframes currently on the callstack.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if self.breakpoints.set.insert(breakpoint) {
+            self.breakpoints.patched.remove(&breakpoint.code_hash);
+            self.patch_callstack(Some(breakpoint.code_hash));
+        }
+    }
+
+    /// Removes a previously [set](Self::add_breakpoint()) breakpoint. Returns `false` if the breakpoint wasn't set.
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        let removed = self.breakpoints.set.remove(&breakpoint);
+        if removed {
+            self.breakpoints.patched.remove(&breakpoint.code_hash);
+            self.patch_callstack(Some(breakpoint.code_hash));
+        }
+        removed
+    }
+
+    /// Returns all breakpoints set on this VM, ordered by bytecode hash and program counter.
+    pub fn breakpoints(&self) -> impl Iterator<Item = Breakpoint> + '_ {
+        self.breakpoints.set.iter().copied()
+    }
+
+    /// Re-patches programs of all callframes, or only of the ones running the bytecode with the specified hash.
+    pub(crate) fn patch_callstack(&mut self, code_hash: Option<U256>) {
+        let frames = self
+            .state
+            .previous_frames
+            .iter_mut()
+            .chain([&mut self.state.current_frame]);
+        for frame in frames {
+            let Some(frame_code_hash) = frame.program.code_hash() else {
+                continue;
+            };
+            if code_hash.is_some_and(|hash| hash != frame_code_hash) {
+                continue;
+            }
+            let pcs = self.breakpoints.pcs(frame_code_hash);
+            if pcs.is_empty() && !frame.program.has_breakpoints() {
+                continue;
+            }
+            let program = frame.program.with_breakpoints(&pcs);
+            frame.replace_program(program);
+        }
+    }
+
+    /// Forgets the breakpoint the VM has stopped on, so that it is hit again if reached.
+    pub(crate) fn reset_breakpoint_hit(&mut self) {
+        self.breakpoints.hit = None;
+    }
+}
+
+/// Handler patched into instructions with a breakpoint.
+#[cfg(not(feature = "single_instruction_test"))]
+pub(crate) fn breakpoint<T: Tracer, W: World<T>>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus {
+    let frame = &vm.state.current_frame;
+    let pc = frame.get_pc_as_u16();
+    let breakpoint = Breakpoint {
+        code_hash: frame
+            .program
+            .code_hash()
+            .expect("breakpoints are only patched into programs with a known bytecode hash"),
+        pc,
+    };
+    // The breakpoint may have been removed while a copy of a patched program was stored in a snapshot.
+    if vm.breakpoints.hit.take() != Some(breakpoint) && vm.breakpoints.set.contains(&breakpoint) {
+        vm.breakpoints.hit = Some(breakpoint);
+        return ExecutionStatus::Stopped(ExecutionEnd::Breakpoint(breakpoint));
+    }
+
+    let handler = frame.program.original_handler(pc);
+    handler(vm, world, tracer)
+}
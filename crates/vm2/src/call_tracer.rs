@@ -0,0 +1,368 @@
+//! Tracer building a tree of far and near calls.
+
+use primitive_types::H160;
+use zksync_vm2_interface::{
+    v2::{FarCallEnter, FarCallExit, HeapPointer, PanicReason, Tracer},
+    CallframeInterface, CallingMode, GlobalStateInterface, Opcode, OpcodeType, ReturnType,
+    ShouldStop, StateInterface,
+};
+
+use crate::{
+    json::{write_address, write_array, write_bytes, write_quantity, write_string, JsonObject},
+    ExecutionEnd, FatPointer, RevertReason,
+};
+
+/// Kind of a [`Call`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    /// Far call with the specified calling mode. The initial frame of the VM is reported as a normal far call.
+    Far(CallingMode),
+    /// Near call, i.e. a call within the same contract.
+    Near,
+}
+
+/// Node of a call tree built by [`CallTracer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// Kind of the call.
+    pub kind: CallKind,
+    /// Caller as seen by the called frame. For delegate and mimic calls, this is not the calling contract.
+    pub caller: H160,
+    /// Address of the contract, whose storage and context are used by the called frame.
+    pub address: H160,
+    /// Address of the executed code. Differs from `address` for delegate calls.
+    pub code_address: H160,
+    /// Gas given to the called frame, after applying the 63/64 rule and including mandated gas.
+    pub gas: u32,
+    /// Gas used by the called frame, i.e. the part of `gas` not returned to the caller.
+    /// Zero if the call hasn't returned.
+    pub gas_used: u32,
+    /// Calldata passed via the far-call ABI fat pointer. Always empty for near calls and failed far calls.
+    pub calldata: Vec<u8>,
+    /// Data returned from a far call. Empty for near calls, panics, and calls that haven't returned.
+    pub returndata: Vec<u8>,
+    /// How the call has returned, or `None` if it hasn't.
+    pub outcome: Option<ReturnType>,
+    /// Reason of the panic if the call has panicked other than by executing a panicking `ret` instruction.
+    pub panic_reason: Option<PanicReason>,
+    /// Calls made by the called frame in the order they were made.
+    pub calls: Vec<Call>,
+}
+
+impl Call {
+    fn new(
+        kind: CallKind,
+        caller: H160,
+        address: H160,
+        code_address: H160,
+        gas: u32,
+        calldata: Vec<u8>,
+    ) -> Self {
+        Self {
+            kind,
+            caller,
+            address,
+            code_address,
+            gas,
+            gas_used: 0,
+            calldata,
+            returndata: vec![],
+            outcome: None,
+            panic_reason: None,
+            calls: vec![],
+        }
+    }
+
+    /// Decodes the revert reason from `returndata` if the call has reverted. Returns `None` for all other outcomes.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        (self.outcome == Some(ReturnType::Revert)).then(|| RevertReason::decode(&self.returndata))
+    }
+
+    /// Serializes this call and its subcalls to JSON, using a format close to the geth `callTracer`.
+    ///
+    /// Calls are objects with the following fields:
+    ///
+    /// - `type`: `CALL`, `DELEGATECALL` or `MIMICCALL` for far calls, and `NEARCALL` for near calls
+    /// - `from`, `to`, `codeAddress`: `caller`, `address` and `code_address`, as hex strings
+    /// - `gas`, `gasUsed`: hex numbers
+    /// - `input`, `output`: `calldata` and `returndata`, as hex strings
+    /// - `outcome`: `ok`, `revert`, `panic`, or `null` if the call hasn't returned
+    /// - `panicReason`: [`panic_reason`](Self::panic_reason) in the `Debug` format (e.g., `OutOfErgs`); only present
+    ///   if set
+    /// - `revertReason`: [decoded revert reason](Self::revert_reason()) as a string; only present for reverts
+    ///   with non-empty returndata
+    /// - `calls`: array of subcalls
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        let mut object = JsonObject::new(out);
+        let call_type = match self.kind {
+            CallKind::Far(CallingMode::Normal) => "CALL",
+            CallKind::Far(CallingMode::Delegate) => "DELEGATECALL",
+            CallKind::Far(CallingMode::Mimic) => "MIMICCALL",
+            CallKind::Near => "NEARCALL",
+        };
+        write_string(object.field("type"), call_type);
+        write_address(object.field("from"), self.caller);
+        write_address(object.field("to"), self.address);
+        write_address(object.field("codeAddress"), self.code_address);
+        write_quantity(object.field("gas"), self.gas);
+        write_quantity(object.field("gasUsed"), self.gas_used);
+        write_bytes(object.field("input"), &self.calldata);
+        write_bytes(object.field("output"), &self.returndata);
+        match self.outcome {
+            Some(ReturnType::Normal) => write_string(object.field("outcome"), "ok"),
+            Some(ReturnType::Revert) => write_string(object.field("outcome"), "revert"),
+            Some(ReturnType::Panic) => write_string(object.field("outcome"), "panic"),
+            None => object.field("outcome").push_str("null"),
+        }
+        if let Some(reason) = self.panic_reason {
+            write_string(object.field("panicReason"), &format!("{reason:?}"));
+        }
+        match self.revert_reason() {
+            None | Some(RevertReason::Empty) => {}
+            Some(reason) => write_string(object.field("revertReason"), &reason.to_string()),
+        }
+        write_array(object.field("calls"), &self.calls, |out, call| {
+            call.write_json(out);
+        });
+        object.finish();
+    }
+}
+
+#[derive(Debug)]
+struct OpenCall {
+    call: Call,
+    /// Gas of the calling frame right after a near call. The gas returned by the call is added to it.
+    /// Unused for far calls, which report the returned gas directly.
+    caller_gas: u32,
+}
+
+/// [`Tracer`] building a tree of far and near calls, similar to the geth `callTracer`.
+///
+/// The tree is rooted at the initial frame of the VM; the tracer must thus be used from the start of execution.
+/// Since the data returned from the initial frame isn't visible to tracers, [`Self::finish()`] must be called
+/// with the result of the execution to complete the root call.
+///
+/// Far calls are tracked using [far call events](Tracer::on_far_call_enter()) reported by the VM, so the tree is accurate
+/// in cases that are easy to get wrong when tracing opcodes:
+///
+/// - A far call that fails (e.g., because the callee cannot be decommitted) still pushes a frame, which panics
+///   at its first instruction. Such calls are reported with a `Panic` outcome and empty calldata.
+/// - A panic caused by an instruction (e.g., running out of gas) returns from the current near call if there is one,
+///   and from the current far call otherwise.
+/// - Returning an invalid returndata pointer turns a return into a panic.
+///
+/// The VM reports no events for near calls, so they are tracked based on the executed `NearCall` and `Ret` instructions.
+///
+/// This tracer implements the [v2 tracer interface](zksync_vm2_interface::v2). It can be combined with other
+/// tracers (e.g., a [`GasProfiler`](crate::GasProfiler) or a [`StepTracer`](crate::StepTracer)) using tuples.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    open_calls: Vec<OpenCall>,
+    root: Option<Call>,
+    /// Calldata of the far call entered by the current instruction, read after the instruction.
+    entered_calldata: Option<HeapPointer>,
+    /// Far call exited by the current instruction, closed after the instruction.
+    exited_call: Option<FarCallExit>,
+    /// Reason of the panic performed by the current instruction.
+    panic_reason: Option<PanicReason>,
+}
+
+impl CallTracer {
+    /// Creates a tracer with an empty call tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Completes the root call based on how the VM execution ended. Does nothing if the execution is not over
+    /// (e.g., if it was stopped by a tracer or suspended on a hook).
+    pub fn finish(&mut self, end: &ExecutionEnd) {
+        let (outcome, returndata) = match end {
+            ExecutionEnd::ProgramFinished(output) => (ReturnType::Normal, output.as_slice()),
+            ExecutionEnd::Reverted(output) => (ReturnType::Revert, output.as_slice()),
+            ExecutionEnd::Panicked(_) => (ReturnType::Panic, &[][..]),
+            _ => return,
+        };
+        if let [root] = self.open_calls.as_mut_slice() {
+            root.call.outcome = Some(outcome);
+            root.call.returndata = returndata.to_vec();
+            self.close_call();
+        }
+    }
+
+    /// Returns the call tree, or `None` if no instructions were traced.
+    ///
+    /// Calls that haven't returned yet are included with no outcome.
+    pub fn into_call_tree(mut self) -> Option<Call> {
+        while !self.open_calls.is_empty() {
+            self.close_call();
+        }
+        self.root
+    }
+
+    fn open_root_call<S: StateInterface>(&mut self, state: &mut S) {
+        let (caller, address, code_address, gas) = {
+            let frame = state.current_frame();
+            (
+                frame.caller(),
+                frame.address(),
+                frame.code_address(),
+                frame.gas(),
+            )
+        };
+        let (calldata, is_pointer) = state.read_register(1);
+        let calldata = if is_pointer {
+            read_heap_slice(state, &HeapPointer::from(&FatPointer::from(calldata)))
+        } else {
+            vec![]
+        };
+        self.push_call(
+            Call::new(
+                CallKind::Far(CallingMode::Normal),
+                caller,
+                address,
+                code_address,
+                gas,
+                calldata,
+            ),
+            0,
+        );
+    }
+
+    fn open_near_call<S: StateInterface>(&mut self, state: &mut S) {
+        let caller_gas = state.callframe(1).gas();
+        let frame = state.current_frame();
+        let call = Call::new(
+            CallKind::Near,
+            frame.caller(),
+            frame.address(),
+            frame.code_address(),
+            frame.gas(),
+            vec![],
+        );
+        self.push_call(call, caller_gas);
+    }
+
+    fn push_call(&mut self, call: Call, caller_gas: u32) {
+        self.open_calls.push(OpenCall { call, caller_gas });
+    }
+
+    fn close_call(&mut self) {
+        let Some(OpenCall { call, .. }) = self.open_calls.pop() else {
+            return;
+        };
+        if let Some(parent) = self.open_calls.last_mut() {
+            parent.call.calls.push(call);
+        } else {
+            self.root = Some(call);
+        }
+    }
+
+    fn on_return<S: StateInterface>(&mut self, state: &mut S, return_type: ReturnType) {
+        let panic_reason = self.panic_reason.take();
+        let exited_call = self.exited_call.take();
+        let Some(open_call) = self.open_calls.last_mut() else {
+            return;
+        };
+        let call = &mut open_call.call;
+
+        if let Some(exit) = exited_call {
+            call.gas_used = call.gas.saturating_sub(exit.gas_left);
+            call.outcome = Some(exit.return_type);
+            call.panic_reason = panic_reason;
+            if let Some(returndata) = exit.returndata {
+                call.returndata = read_heap_slice(state, &returndata);
+            }
+            self.close_call();
+        } else if call.kind == CallKind::Near {
+            let returned_gas = state
+                .current_frame()
+                .gas()
+                .saturating_sub(open_call.caller_gas);
+            call.gas_used = call.gas.saturating_sub(returned_gas);
+            call.outcome = Some(return_type);
+            call.panic_reason = panic_reason;
+            self.close_call();
+        } else {
+            // Returning from the initial frame doesn't pop it; its gas is left as is, and the outcome is set by `finish()`.
+            call.gas_used = call.gas.saturating_sub(state.current_frame().gas());
+            call.panic_reason = panic_reason;
+        }
+    }
+}
+
+impl Tracer for CallTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if self.open_calls.is_empty() && self.root.is_none() {
+            self.open_root_call(state);
+        }
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        match OP::VALUE {
+            // The calldata is still in place after the far call instruction.
+            Opcode::FarCall(_) => {
+                if let (Some(calldata), Some(open_call)) =
+                    (self.entered_calldata.take(), self.open_calls.last_mut())
+                {
+                    open_call.call.calldata = read_heap_slice(state, &calldata);
+                }
+            }
+            Opcode::NearCall => self.open_near_call(state),
+            Opcode::Ret(return_type) => self.on_return(state, return_type),
+            _ => {}
+        }
+        ShouldStop::Continue
+    }
+
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        if !call.is_failed {
+            self.entered_calldata = Some(call.calldata);
+        }
+        self.push_call(
+            Call::new(
+                CallKind::Far(call.calling_mode),
+                call.caller,
+                call.address,
+                call.code_address,
+                call.gas,
+                vec![],
+            ),
+            0,
+        );
+    }
+
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        self.exited_call = Some(call);
+    }
+
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.panic_reason = Some(reason);
+    }
+}
+
+/// Reads the heap slice addressed by a fat pointer.
+fn read_heap_slice<S: StateInterface>(state: &S, pointer: &HeapPointer) -> Vec<u8> {
+    let start = pointer.start.saturating_add(pointer.offset);
+    let end = pointer.start.saturating_add(pointer.length);
+    let mut bytes = Vec::with_capacity(end.saturating_sub(start) as usize);
+    let mut offset = start;
+    while offset < end {
+        let mut word = [0; 32];
+        state
+            .read_heap_u256(pointer.heap, offset)
+            .to_big_endian(&mut word);
+        let len = (end - offset).min(32);
+        bytes.extend_from_slice(&word[..len as usize]);
+        offset += len;
+    }
+    bytes
+}
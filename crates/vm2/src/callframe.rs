@@ -4,9 +4,10 @@ use primitive_types::H160;
 use zkevm_opcode_defs::system_params::{
     NEW_EVM_FRAME_MEMORY_STIPEND, NEW_FRAME_MEMORY_STIPEND, NEW_KERNEL_FRAME_MEMORY_STIPEND,
 };
-use zksync_vm2_interface::{HeapId, Tracer};
+use zksync_vm2_interface::{v2::Tracer, HeapId};
 
 use crate::{
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
     decommit::is_kernel,
     instruction_handlers::invalid_instruction,
     program::Program,
@@ -14,6 +15,8 @@ use crate::{
     world_diff::Snapshot,
     Instruction, World,
 };
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::{instruction_handlers::spontaneous_panic, program::ProgramSource};
 
 #[derive(Debug)]
 pub(crate) struct Callframe<T, W> {
@@ -48,6 +51,16 @@ pub(crate) struct Callframe<T, W> {
     pub(crate) world_before_this_frame: Snapshot,
 }
 
+// SAFETY: `pc` is the only field that isn't `Send` automatically. It points either into the instructions of `program`,
+// which are never mutated and are kept alive by `program` (an `Arc` handle, so they don't move together with the frame),
+// or to a `'static` instruction. Thus, sending `pc` is equivalent to sending `&Instruction<T, W>` along with `program`.
+unsafe impl<T, W> Send for Callframe<T, W>
+where
+    Program<T, W>: Send,
+    Instruction<T, W>: Sync,
+{
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub(crate) struct NearCallFrame {
     pub(crate) exception_handler: u16,
@@ -170,6 +183,14 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
             .unwrap_or_else(invalid_instruction);
     }
 
+    /// Replaces the program of this frame with a version of it that has the same instructions
+    /// (e.g., with breakpoints patched in), keeping the program counter.
+    #[cfg(not(feature = "single_instruction_test"))]
+    pub(crate) fn replace_program(&mut self, program: Program<T, W>) {
+        self.pc = program.translate_pc(&self.program, self.pc);
+        self.program = program;
+    }
+
     /// The total amount of gas in this frame, including gas currently inaccessible because of a near call.
     pub(crate) fn contained_gas(&self) -> u32 {
         self.gas
@@ -228,6 +249,107 @@ impl<T: Tracer, W: World<T>> Callframe<T, W> {
     }
 }
 
+/// Program counter positions not pointing into the frame program, stored in checkpoints.
+#[cfg(not(feature = "single_instruction_test"))]
+const PC_INVALID_INSTRUCTION: u32 = 1 << 16;
+#[cfg(not(feature = "single_instruction_test"))]
+const PC_SPONTANEOUS_PANIC: u32 = (1 << 16) + 1;
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> Callframe<T, W> {
+    pub(crate) fn encode_checkpoint(&self, encoder: &mut Encoder) {
+        encoder.put(&self.program.source());
+        let pc = if ptr::eq(self.pc, invalid_instruction()) {
+            PC_INVALID_INSTRUCTION
+        } else if ptr::eq(self.pc, spontaneous_panic()) {
+            PC_SPONTANEOUS_PANIC
+        } else {
+            // The wraparound instruction after the 16-bit program counter range is truncated to
+            // zero, which is exactly where it jumps to.
+            self.get_pc_as_u16().into()
+        };
+        encoder.put(&pc);
+
+        encoder.put(&self.address);
+        encoder.put(&self.code_address);
+        encoder.put(&self.caller);
+        encoder.put(&self.exception_handler);
+        encoder.put(&self.context_u128);
+        encoder.put(&self.is_static);
+        encoder.put(&self.is_kernel);
+        encoder.put(&self.stack);
+        encoder.put(&self.sp);
+        encoder.put(&self.gas);
+        encoder.put(&self.near_calls);
+        encoder.put(&self.heap);
+        encoder.put(&self.aux_heap);
+        encoder.put(&self.heap_size);
+        encoder.put(&self.aux_heap_size);
+        encoder.put(&self.calldata_heap);
+        encoder.put(&self.heaps_i_am_keeping_alive);
+        encoder.put(&self.world_before_this_frame);
+    }
+
+    /// Decodes a callframe, obtaining its program from the provided closure.
+    pub(crate) fn decode_checkpoint(
+        decoder: &mut Decoder<'_>,
+        get_program: impl FnOnce(ProgramSource) -> Result<Program<T, W>, CheckpointError>,
+    ) -> Result<Self, CheckpointError> {
+        let program = get_program(decoder.get()?)?;
+        let pc: *const Instruction<T, W> = match decoder.get::<u32>()? {
+            PC_INVALID_INSTRUCTION => invalid_instruction(),
+            PC_SPONTANEOUS_PANIC => spontaneous_panic(),
+            pc => u16::try_from(pc)
+                .ok()
+                .and_then(|pc| program.instruction(pc))
+                .ok_or(CheckpointError::Malformed("program counter out of bounds"))?,
+        };
+
+        Ok(Self {
+            pc,
+            program,
+            address: decoder.get()?,
+            code_address: decoder.get()?,
+            caller: decoder.get()?,
+            exception_handler: decoder.get()?,
+            context_u128: decoder.get()?,
+            is_static: decoder.get()?,
+            is_kernel: decoder.get()?,
+            stack: decoder.get()?,
+            sp: decoder.get()?,
+            gas: decoder.get()?,
+            near_calls: decoder.get()?,
+            heap: decoder.get()?,
+            aux_heap: decoder.get()?,
+            heap_size: decoder.get()?,
+            aux_heap_size: decoder.get()?,
+            calldata_heap: decoder.get()?,
+            heaps_i_am_keeping_alive: decoder.get()?,
+            world_before_this_frame: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for NearCallFrame {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.exception_handler);
+        encoder.put(&self.previous_frame_sp);
+        encoder.put(&self.previous_frame_gas);
+        encoder.put(&self.previous_frame_pc);
+        encoder.put(&self.world_before_this_frame);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            exception_handler: decoder.get()?,
+            previous_frame_sp: decoder.get()?,
+            previous_frame_gas: decoder.get()?,
+            previous_frame_pc: decoder.get()?,
+            world_before_this_frame: decoder.get()?,
+        })
+    }
+}
+
 pub(crate) struct FrameRemnant {
     pub(crate) exception_handler: u16,
     pub(crate) snapshot: Snapshot,
@@ -247,6 +369,35 @@ pub(crate) struct CallframeSnapshot {
     heaps_i_was_keeping_alive: usize,
 }
 
+#[cfg(not(feature = "single_instruction_test"))]
+impl Checkpoint for CallframeSnapshot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.stack);
+        encoder.put(&self.context_u128);
+        encoder.put(&self.sp);
+        encoder.put(&self.pc);
+        encoder.put(&self.gas);
+        encoder.put(&self.near_calls);
+        encoder.put(&self.heap_size);
+        encoder.put(&self.aux_heap_size);
+        encoder.put(&self.heaps_i_was_keeping_alive);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            stack: decoder.get()?,
+            context_u128: decoder.get()?,
+            sp: decoder.get()?,
+            pc: decoder.get()?,
+            gas: decoder.get()?,
+            near_calls: decoder.get()?,
+            heap_size: decoder.get()?,
+            aux_heap_size: decoder.get()?,
+            heaps_i_was_keeping_alive: decoder.get()?,
+        })
+    }
+}
+
 impl<T, W> Clone for Callframe<T, W> {
     fn clone(&self) -> Self {
         Self {
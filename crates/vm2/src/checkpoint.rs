@@ -0,0 +1,626 @@
+//! Serializable [`VirtualMachine`] checkpoints.
+//!
+//! A checkpoint captures everything needed to resume a VM in another process: the callstack
+//! (including near calls), stacks, heaps, registers and flags, the [`WorldDiff`](crate::WorldDiff)
+//! together with its rollback history and decommit bookkeeping, and all external snapshots held by
+//! the VM.
+//!
+//! Instruction handlers are function pointers, so programs are not part of the format. Instead, each
+//! far call frame records the hash its program was decommitted under, and restoring asks the
+//! [`World`] for that program again. The initial (bootloader) frame was not decommitted by the VM,
+//! so its program must be supplied explicitly, like in [`VirtualMachine::new()`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error, fmt,
+};
+
+use primitive_types::{H160, U256};
+use zk_evm_abstractions::{aux::Timestamp, queries::LogQuery};
+#[cfg(not(feature = "single_instruction_test"))]
+use zksync_vm2_interface::v2::Tracer;
+use zksync_vm2_interface::{v2::PanicReason, Event, HeapId, L2ToL1Log};
+
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::{
+    breakpoints::Breakpoints, program::ProgramSource, stack::StackPool, state::State,
+    vm::VmSnapshot, watchpoints::Watchpoints, Program, VirtualMachine, World,
+};
+
+/// Magic bytes every checkpoint starts with.
+const MAGIC: [u8; 4] = *b"VM2C";
+/// Version of the checkpoint format. Must be bumped on every incompatible change.
+const VERSION: u16 = 5;
+
+/// Errors that can occur when saving or restoring a [`VirtualMachine`] checkpoint.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    /// A callframe runs a program that was neither decommitted by the VM nor is the initial program,
+    /// so it cannot be re-supplied on restore.
+    UnknownProgram {
+        /// Depth of the offending callframe; the initial frame has depth 0.
+        frame: usize,
+    },
+    /// The data is not a VM checkpoint.
+    BadMagic,
+    /// The checkpoint was written in an unsupported version of the format.
+    UnsupportedVersion(u16),
+    /// The data ended prematurely.
+    UnexpectedEnd,
+    /// The data has bytes after the end of the checkpoint.
+    TrailingBytes,
+    /// The data is structurally invalid.
+    Malformed(&'static str),
+    /// The VM is about to panic for a reason unknown to this VM version (i.e., added in a newer version
+    /// of the interface), which cannot be saved.
+    UnknownPanicReason(PanicReason),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProgram { frame } => write!(
+                formatter,
+                "program of callframe {frame} was not decommitted and cannot be re-supplied"
+            ),
+            Self::BadMagic => formatter.write_str("data is not a VM checkpoint"),
+            Self::UnsupportedVersion(version) => {
+                write!(formatter, "unsupported checkpoint version {version}")
+            }
+            Self::UnexpectedEnd => formatter.write_str("checkpoint data ended prematurely"),
+            Self::TrailingBytes => formatter.write_str("trailing bytes after checkpoint data"),
+            Self::Malformed(reason) => write!(formatter, "malformed checkpoint: {reason}"),
+            Self::UnknownPanicReason(reason) => {
+                write!(formatter, "panic reason {reason:?} cannot be saved")
+            }
+        }
+    }
+}
+
+impl error::Error for CheckpointError {}
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
+    /// Serializes the full state of this VM into a versioned binary checkpoint that can be
+    /// [restored](Self::restore_checkpoint()) later, possibly in another process.
+    ///
+    /// The tracer and the [`World`] are not part of the checkpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a callframe other than the initial one runs a program that was not
+    /// obtained via [`World::decommit()`], or if the VM is about to panic for a reason unknown to this VM version.
+    pub fn save_checkpoint(&self) -> Result<Vec<u8>, CheckpointError> {
+        let states = std::iter::once(&self.state)
+            .chain(self.snapshots.iter().filter_map(VmSnapshot::full_state));
+        for state in states {
+            for (frame, callframe) in state.frames().enumerate().skip(1) {
+                if callframe.program.source() == ProgramSource::Unknown {
+                    return Err(CheckpointError::UnknownProgram { frame });
+                }
+            }
+            if let Some(reason) = state.pending_panic {
+                if panic_reason_tag(reason).is_none() {
+                    return Err(CheckpointError::UnknownPanicReason(reason));
+                }
+            }
+        }
+
+        let mut encoder = Encoder::default();
+        encoder.put(&MAGIC);
+        encoder.put(&VERSION);
+        encoder.put(&self.settings);
+        self.state.encode_checkpoint(&mut encoder);
+        encoder.put(&self.world_diff);
+        encoder.put(&self.snapshots.len());
+        for snapshot in &self.snapshots {
+            snapshot.encode_checkpoint(&mut encoder);
+        }
+        encoder.put(&self.commit_pending);
+        Ok(encoder.finish())
+    }
+
+    /// Restores a VM from a checkpoint produced by [`Self::save_checkpoint()`].
+    ///
+    /// `program` is the program of the initial (bootloader) frame, i.e. the one originally passed to
+    /// [`Self::new()`]. Programs of other frames are re-supplied by `world` via [`World::decommit()`].
+    /// [Breakpoints](Self::add_breakpoint()) and [watchpoints](Self::add_watchpoint()) are not part of a checkpoint
+    /// and must be set again. This includes the breakpoint the VM has stopped on, so if the VM was saved on
+    /// a breakpoint that is set again, the restored VM stops on it once more before executing the instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` is not a valid checkpoint.
+    pub fn restore_checkpoint(
+        bytes: &[u8],
+        program: Program<T, W>,
+        world: &mut W,
+    ) -> Result<Self, CheckpointError> {
+        let mut decoder = Decoder::new(bytes);
+        if decoder.get::<[u8; 4]>()? != MAGIC {
+            return Err(CheckpointError::BadMagic);
+        }
+        let version = decoder.get::<u16>()?;
+        if version != VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+
+        let settings = decoder.get()?;
+        // Programs of fully captured snapshot states are re-supplied in the same way as for the current state.
+        let mut get_program = move |frame: usize, source: ProgramSource| {
+            if frame == 0 {
+                return Ok(program.clone());
+            }
+            match source {
+                ProgramSource::Decommitted(hash) => Ok(world.decommit(hash).with_code_hash(hash)),
+                ProgramSource::Panicking => Ok(Program::new_panicking()),
+                ProgramSource::Unknown => Err(CheckpointError::UnknownProgram { frame }),
+            }
+        };
+        let state = State::decode_checkpoint(&mut decoder, &mut get_program)?;
+        let world_diff = decoder.get()?;
+        let snapshot_count = decoder.get_len()?;
+        let snapshots = (0..snapshot_count)
+            .map(|_| VmSnapshot::decode_checkpoint(&mut decoder, &mut get_program))
+            .collect::<Result<_, _>>()?;
+        let commit_pending = decoder.get()?;
+        decoder.finish()?;
+
+        Ok(Self {
+            world_diff,
+            state,
+            settings,
+            stack_pool: StackPool::default(),
+            snapshots,
+            breakpoints: Breakpoints::default(),
+            watchpoints: Watchpoints::default(),
+            // Checkpoints cannot be saved during `run()`, which is the only time superinstructions are allowed.
+            allow_superinstructions: false,
+            commit_pending,
+        })
+    }
+}
+
+/// Binary checkpoint writer.
+#[derive(Debug, Default)]
+pub(crate) struct Encoder(Vec<u8>);
+
+impl Encoder {
+    pub(crate) fn put<V: Checkpoint>(&mut self, value: &V) {
+        value.encode(self);
+    }
+
+    fn put_bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Binary checkpoint reader.
+#[derive(Debug)]
+pub(crate) struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+
+    pub(crate) fn get<V: Checkpoint>(&mut self) -> Result<V, CheckpointError> {
+        V::decode(self)
+    }
+
+    /// Reads a collection length, rejecting lengths that cannot possibly fit into the remaining data.
+    pub(crate) fn get_len(&mut self) -> Result<usize, CheckpointError> {
+        let len = self.get::<usize>()?;
+        if len > self.0.len() {
+            return Err(CheckpointError::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], CheckpointError> {
+        let (head, tail) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or(CheckpointError::UnexpectedEnd)?;
+        self.0 = tail;
+        Ok(*head)
+    }
+
+    fn finish(self) -> Result<(), CheckpointError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(CheckpointError::TrailingBytes)
+        }
+    }
+}
+
+/// Types that can be written to and read from a VM checkpoint.
+pub(crate) trait Checkpoint: Sized {
+    fn encode(&self, encoder: &mut Encoder);
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError>;
+}
+
+macro_rules! impl_checkpoint_for_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Checkpoint for $ty {
+                fn encode(&self, encoder: &mut Encoder) {
+                    encoder.put_bytes(&self.to_le_bytes());
+                }
+
+                fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+                    decoder.take().map(Self::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_checkpoint_for_int!(u8, u16, u32, u64, u128, i32);
+
+impl Checkpoint for usize {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&(*self as u64));
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Self::try_from(decoder.get::<u64>()?)
+            .map_err(|_| CheckpointError::Malformed("length does not fit into usize"))
+    }
+}
+
+impl Checkpoint for bool {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&u8::from(*self));
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        match decoder.get::<u8>()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CheckpointError::Malformed("invalid boolean")),
+        }
+    }
+}
+
+impl Checkpoint for U256 {
+    fn encode(&self, encoder: &mut Encoder) {
+        for limb in self.0 {
+            encoder.put(&limb);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self(decoder.get()?))
+    }
+}
+
+impl Checkpoint for H160 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put_bytes(&self.0);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        decoder.take().map(Self)
+    }
+}
+
+impl Checkpoint for HeapId {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.as_u32());
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        decoder.get().map(Self::from_u32_unchecked)
+    }
+}
+
+impl<V: Checkpoint + Copy + Default, const N: usize> Checkpoint for [V; N] {
+    fn encode(&self, encoder: &mut Encoder) {
+        for item in self {
+            encoder.put(item);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let mut array = [V::default(); N];
+        for item in &mut array {
+            *item = decoder.get()?;
+        }
+        Ok(array)
+    }
+}
+
+impl<V: Checkpoint> Checkpoint for Vec<V> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.len());
+        for item in self {
+            encoder.put(item);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let len = decoder.get_len()?;
+        (0..len).map(|_| decoder.get()).collect()
+    }
+}
+
+impl<V: Checkpoint> Checkpoint for Box<[V]> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.len());
+        for item in &**self {
+            encoder.put(item);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        decoder.get::<Vec<V>>().map(Vec::into_boxed_slice)
+    }
+}
+
+impl<V: Checkpoint> Checkpoint for Option<V> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.is_some());
+        if let Some(value) = self {
+            encoder.put(value);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(if decoder.get()? {
+            Some(decoder.get()?)
+        } else {
+            None
+        })
+    }
+}
+
+impl<A: Checkpoint, B: Checkpoint> Checkpoint for (A, B) {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.0);
+        encoder.put(&self.1);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok((decoder.get()?, decoder.get()?))
+    }
+}
+
+impl<K: Checkpoint + Ord, V: Checkpoint> Checkpoint for BTreeMap<K, V> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.len());
+        for (key, value) in self {
+            encoder.put(key);
+            encoder.put(value);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let len = decoder.get_len()?;
+        (0..len)
+            .map(|_| Ok((decoder.get()?, decoder.get()?)))
+            .collect()
+    }
+}
+
+impl<K: Checkpoint + Ord> Checkpoint for BTreeSet<K> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.len());
+        for key in self {
+            encoder.put(key);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let len = decoder.get_len()?;
+        (0..len).map(|_| decoder.get()).collect()
+    }
+}
+
+impl Checkpoint for Event {
+    fn encode(&self, encoder: &mut Encoder) {
+        let Self {
+            key,
+            value,
+            is_first,
+            shard_id,
+            tx_number,
+        } = self;
+        encoder.put(key);
+        encoder.put(value);
+        encoder.put(is_first);
+        encoder.put(shard_id);
+        encoder.put(tx_number);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            key: decoder.get()?,
+            value: decoder.get()?,
+            is_first: decoder.get()?,
+            shard_id: decoder.get()?,
+            tx_number: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for L2ToL1Log {
+    fn encode(&self, encoder: &mut Encoder) {
+        let Self {
+            key,
+            value,
+            is_service,
+            address,
+            shard_id,
+            tx_number,
+        } = self;
+        encoder.put(key);
+        encoder.put(value);
+        encoder.put(is_service);
+        encoder.put(address);
+        encoder.put(shard_id);
+        encoder.put(tx_number);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            key: decoder.get()?,
+            value: decoder.get()?,
+            is_service: decoder.get()?,
+            address: decoder.get()?,
+            shard_id: decoder.get()?,
+            tx_number: decoder.get()?,
+        })
+    }
+}
+
+/// Tag of panic reasons unknown to this VM version. [`VirtualMachine::save_checkpoint()`] refuses to save them,
+/// and the tag is rejected on restore.
+const UNKNOWN_PANIC_REASON_TAG: u8 = u8::MAX;
+
+/// Returns the checkpoint tag of `reason`, or `None` if the reason is unknown to this VM version
+/// (`PanicReason` is non-exhaustive).
+fn panic_reason_tag(reason: PanicReason) -> Option<u8> {
+    Some(match reason {
+        PanicReason::OutOfErgs => 0,
+        PanicReason::StaticViolation => 1,
+        PanicReason::KernelViolation => 2,
+        PanicReason::InvalidInstruction => 3,
+        PanicReason::HeapGrowthOverflow => 4,
+        PanicReason::FarCallDecommitFailed => 5,
+        PanicReason::FarCallShardFailure => 6,
+        PanicReason::InvalidFatPointer => 7,
+        PanicReason::ReturnedCalldataPointer => 8,
+        _ => return None,
+    })
+}
+
+impl Checkpoint for PanicReason {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&panic_reason_tag(*self).unwrap_or(UNKNOWN_PANIC_REASON_TAG));
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(match decoder.get::<u8>()? {
+            0 => Self::OutOfErgs,
+            1 => Self::StaticViolation,
+            2 => Self::KernelViolation,
+            3 => Self::InvalidInstruction,
+            4 => Self::HeapGrowthOverflow,
+            5 => Self::FarCallDecommitFailed,
+            6 => Self::FarCallShardFailure,
+            7 => Self::InvalidFatPointer,
+            8 => Self::ReturnedCalldataPointer,
+            _ => return Err(CheckpointError::Malformed("invalid panic reason")),
+        })
+    }
+}
+
+impl Checkpoint for LogQuery {
+    fn encode(&self, encoder: &mut Encoder) {
+        let Self {
+            timestamp,
+            tx_number_in_block,
+            aux_byte,
+            shard_id,
+            address,
+            key,
+            read_value,
+            written_value,
+            rw_flag,
+            rollback,
+            is_service,
+        } = self;
+        encoder.put(&timestamp.0);
+        encoder.put(tx_number_in_block);
+        encoder.put(aux_byte);
+        encoder.put(shard_id);
+        encoder.put(address);
+        encoder.put(key);
+        encoder.put(read_value);
+        encoder.put(written_value);
+        encoder.put(rw_flag);
+        encoder.put(rollback);
+        encoder.put(is_service);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            timestamp: Timestamp(decoder.get()?),
+            tx_number_in_block: decoder.get()?,
+            aux_byte: decoder.get()?,
+            shard_id: decoder.get()?,
+            address: decoder.get()?,
+            key: decoder.get()?,
+            read_value: decoder.get()?,
+            written_value: decoder.get()?,
+            rw_flag: decoder.get()?,
+            rollback: decoder.get()?,
+            is_service: decoder.get()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<V: Checkpoint>(value: &V) -> V {
+        let mut encoder = Encoder::default();
+        encoder.put(value);
+        let bytes = encoder.finish();
+        let mut decoder = Decoder::new(&bytes);
+        let decoded = decoder.get().unwrap();
+        decoder.finish().unwrap();
+        decoded
+    }
+
+    #[test]
+    fn primitives_round_trip() {
+        assert_eq!(round_trip(&0xdead_beef_u32), 0xdead_beef);
+        assert_eq!(round_trip(&-5_i32), -5);
+        assert_eq!(round_trip(&u128::MAX), u128::MAX);
+        assert_eq!(round_trip(&(U256::MAX - 1)), U256::MAX - 1);
+        assert_eq!(
+            round_trip(&H160::repeat_byte(0x23)),
+            H160::repeat_byte(0x23)
+        );
+        assert_eq!(
+            round_trip(&vec![Some(1_u16), None, Some(3)]),
+            [Some(1), None, Some(3)]
+        );
+
+        let map: BTreeMap<(H160, U256), u8> = [((H160::zero(), U256::one()), 3)].into();
+        assert_eq!(round_trip(&map), map);
+    }
+
+    #[test]
+    fn truncated_data_is_rejected() {
+        let mut encoder = Encoder::default();
+        encoder.put(&vec![U256::one(); 3]);
+        let bytes = encoder.finish();
+
+        for len in 0..bytes.len() {
+            let mut decoder = Decoder::new(&bytes[..len]);
+            assert_eq!(
+                decoder.get::<Vec<U256>>(),
+                Err(CheckpointError::UnexpectedEnd)
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_booleans_are_rejected() {
+        let mut decoder = Decoder::new(&[2]);
+        assert!(matches!(
+            decoder.get::<bool>(),
+            Err(CheckpointError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! Static control-flow analysis of programs.
+
+use std::collections::VecDeque;
+
+use crate::{decode::ControlTransfer, Predicate, Program};
+
+/// Kind of an [`Edge`] in a [`ControlFlowGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EdgeKind {
+    /// Execution proceeds to the next instruction; e.g., if the last instruction of the block is skipped
+    /// because of its predicate, or after the callee of a near or far call returns.
+    Fallthrough,
+    /// Jump to an immediate address.
+    Jump,
+    /// Near call to the callee.
+    NearCall,
+    /// Exception handler of a near or far call, which is jumped to if the callee reverts or panics.
+    ExceptionHandler,
+    /// Return to a label (`ret.to_label`).
+    ReturnToLabel,
+}
+
+/// Edge in a [`ControlFlowGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// Kind of the edge.
+    pub kind: EdgeKind,
+    /// Program counter of the target. This is the first instruction of a block, unless the target is past
+    /// the end of the program (in which case, the VM panics when jumping to it).
+    pub target: u16,
+}
+
+/// Basic block in a [`ControlFlowGraph`], i.e., a sequence of instructions that can only be entered
+/// at the first instruction and left after the last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    /// Program counter of the first instruction in the block.
+    pub first_pc: u16,
+    /// Program counter of the last instruction in the block.
+    pub last_pc: u16,
+    /// Statically known successors of the block.
+    pub successors: Vec<Edge>,
+    /// Whether the block ends with a jump to a dynamically computed address, which is not included in `successors`.
+    pub has_dynamic_jump: bool,
+    /// Whether the block is reachable from the program entry point via `successors`.
+    pub is_reachable: bool,
+    /// Sum of static ergs prices of the instructions in the block. Instructions skipped because of their predicate
+    /// are charged as well. Dynamic costs (e.g., for memory growth or storage access) are not included.
+    pub static_ergs: u64,
+}
+
+/// Control-flow graph of a [`Program`] built by static analysis of its bytecode.
+///
+/// Blocks end after jumps, near and far calls, returns, `invalid` instructions and predicated instructions,
+/// and start at every statically known label (jump targets, near call callees, exception handlers and
+/// `ret.to_label` targets).
+///
+/// # Limitations
+///
+/// - Instructions are read from the code page, so the graph is only meaningful for programs created from bytecode
+///   (e.g., with [`Program::new()`]).
+/// - EraVM bytecode doesn't separate instructions from constants, so constants are analyzed as instructions as well.
+///   They usually end up in unreachable blocks.
+/// - Targets of dynamic jumps (e.g., `jump r1`) are unknown, so blocks only reachable via such jumps
+///   are reported as unreachable.
+#[derive(Debug, Clone)]
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+    far_call_sites: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct InstructionInfo {
+    transfer: ControlTransfer,
+    is_predicated: bool,
+    ergs: u32,
+}
+
+impl InstructionInfo {
+    fn ends_block(self) -> bool {
+        self.is_predicated || self.transfer != ControlTransfer::Next
+    }
+}
+
+impl ControlFlowGraph {
+    /// Analyzes the specified program.
+    #[allow(clippy::too_many_lines)]
+    pub fn new<T, W>(program: &Program<T, W>) -> Self {
+        let code_page = program.code_page();
+        let instructions: Vec<_> = (0..=u16::MAX)
+            .map_while(|pc| {
+                let word = code_page.get(usize::from(pc / 4))?;
+                let arguments = &program.instruction(pc)?.arguments;
+                Some(InstructionInfo {
+                    transfer: ControlTransfer::new(word.0[3 - usize::from(pc % 4)]),
+                    is_predicated: !matches!(arguments.predicate(), Predicate::Always),
+                    ergs: arguments.get_static_gas_cost(),
+                })
+            })
+            .collect();
+
+        let mut is_leader = vec![false; instructions.len()];
+        if let Some(first) = is_leader.first_mut() {
+            *first = true;
+        }
+        for (pc, info) in instructions.iter().enumerate() {
+            for label in info.transfer.labels() {
+                if let Some(is_leader) = is_leader.get_mut(usize::from(label)) {
+                    *is_leader = true;
+                }
+            }
+            if info.ends_block() {
+                if let Some(is_leader) = is_leader.get_mut(pc + 1) {
+                    *is_leader = true;
+                }
+            }
+        }
+
+        let mut blocks = vec![];
+        for (pc, info) in (0..=u16::MAX).zip(&instructions) {
+            if is_leader[usize::from(pc)] {
+                blocks.push(BasicBlock {
+                    first_pc: pc,
+                    last_pc: pc,
+                    successors: vec![],
+                    has_dynamic_jump: false,
+                    is_reachable: false,
+                    static_ergs: 0,
+                });
+            }
+            // The first instruction is a leader, so there's always a block to extend.
+            if let Some(block) = blocks.last_mut() {
+                block.last_pc = pc;
+                block.static_ergs += u64::from(info.ergs);
+            }
+        }
+
+        for block in &mut blocks {
+            let info = instructions[usize::from(block.last_pc)];
+            let next = block
+                .last_pc
+                .checked_add(1)
+                .filter(|&next| usize::from(next) < instructions.len());
+            let mut falls_through = info.is_predicated;
+            match info.transfer {
+                ControlTransfer::Next => falls_through = true,
+                ControlTransfer::Jump(Some(target)) => block.successors.push(Edge {
+                    kind: EdgeKind::Jump,
+                    target,
+                }),
+                ControlTransfer::Jump(None) => block.has_dynamic_jump = true,
+                ControlTransfer::NearCall {
+                    callee,
+                    exception_handler,
+                } => {
+                    block.successors.push(Edge {
+                        kind: EdgeKind::NearCall,
+                        target: callee,
+                    });
+                    block.successors.push(Edge {
+                        kind: EdgeKind::ExceptionHandler,
+                        target: exception_handler,
+                    });
+                    falls_through = true;
+                }
+                ControlTransfer::FarCall { exception_handler } => {
+                    block.successors.push(Edge {
+                        kind: EdgeKind::ExceptionHandler,
+                        target: exception_handler,
+                    });
+                    falls_through = true;
+                }
+                ControlTransfer::Ret { label } => {
+                    block.successors.extend(label.map(|target| Edge {
+                        kind: EdgeKind::ReturnToLabel,
+                        target,
+                    }));
+                }
+                ControlTransfer::Invalid => {}
+            }
+            if let Some(next) = next.filter(|_| falls_through) {
+                block.successors.push(Edge {
+                    kind: EdgeKind::Fallthrough,
+                    target: next,
+                });
+            }
+        }
+
+        mark_reachable_blocks(&mut blocks);
+        let far_call_sites = blocks
+            .iter()
+            .filter(|block| block.is_reachable)
+            .flat_map(|block| block.first_pc..=block.last_pc)
+            .filter(|&pc| {
+                matches!(
+                    instructions[usize::from(pc)].transfer,
+                    ControlTransfer::FarCall { .. }
+                )
+            })
+            .collect();
+        Self {
+            blocks,
+            far_call_sites,
+        }
+    }
+
+    /// Returns all basic blocks ordered by their program counters.
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Returns the block containing the instruction with the specified program counter.
+    pub fn block_at(&self, pc: u16) -> Option<&BasicBlock> {
+        Some(&self.blocks[block_index(&self.blocks, pc)?])
+    }
+
+    /// Iterates over blocks that are not reachable from the program entry point.
+    pub fn unreachable_blocks(&self) -> impl Iterator<Item = &BasicBlock> + '_ {
+        self.blocks.iter().filter(|block| !block.is_reachable)
+    }
+
+    /// Returns program counters of far calls in reachable blocks.
+    pub fn far_call_sites(&self) -> &[u16] {
+        &self.far_call_sites
+    }
+
+    /// Lints the program for labels past its end, returning the program counter of each instruction
+    /// in a reachable block referencing such a label together with the corresponding edge.
+    ///
+    /// Such labels don't prevent loading the program: the VM only panics if the edge is actually taken.
+    /// Labels in unreachable blocks (e.g., in constants that happen to decode as jumps) are not reported.
+    pub fn out_of_range_edges(&self) -> impl Iterator<Item = (u16, Edge)> + '_ {
+        let instruction_count = self
+            .blocks
+            .last()
+            .map_or(0, |block| usize::from(block.last_pc) + 1);
+        self.blocks
+            .iter()
+            .filter(|block| block.is_reachable)
+            .flat_map(move |block| {
+                block
+                    .successors
+                    .iter()
+                    .filter(move |edge| usize::from(edge.target) >= instruction_count)
+                    .map(move |&edge| (block.last_pc, edge))
+            })
+    }
+}
+
+fn block_index(blocks: &[BasicBlock], pc: u16) -> Option<usize> {
+    let index = blocks
+        .partition_point(|block| block.first_pc <= pc)
+        .checked_sub(1)?;
+    (pc <= blocks[index].last_pc).then_some(index)
+}
+
+fn mark_reachable_blocks(blocks: &mut [BasicBlock]) {
+    let mut queue = VecDeque::from([0]);
+    while let Some(index) = queue.pop_front() {
+        let Some(block) = blocks.get_mut(index) else {
+            continue;
+        };
+        if block.is_reachable {
+            continue;
+        }
+        block.is_reachable = true;
+
+        let targets: Vec<_> = block.successors.iter().map(|edge| edge.target).collect();
+        queue.extend(
+            targets
+                .into_iter()
+                .filter_map(|target| block_index(blocks, target)),
+        );
+    }
+}
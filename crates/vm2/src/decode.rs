@@ -1,6 +1,8 @@
+use std::{error, fmt};
+
 use zkevm_opcode_defs::{
     decoding::{EncodingModeProduction, VmEncodingMode},
-    ImmMemHandlerFlags, Opcode,
+    Condition, ImmMemHandlerFlags, Opcode,
     Operand::{Full, RegOnly, RegOrImm},
     RegOrImmFlags, FAR_CALL_SHARD_FLAG_IDX, FAR_CALL_STATIC_FLAG_IDX, FIRST_MESSAGE_FLAG_IDX,
     RET_TO_LABEL_BIT_IDX, SET_FLAGS_FLAG_IDX, SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES,
@@ -11,7 +13,7 @@ use zksync_vm2_interface::{
         self, Add, And, Div, Mul, Or, PointerAdd, PointerPack, PointerShrink, PointerSub,
         RotateLeft, RotateRight, ShiftLeft, ShiftRight, Sub, Xor,
     },
-    Tracer,
+    v2::Tracer,
 };
 
 use crate::{
@@ -24,22 +26,183 @@ use crate::{
     Instruction, Predicate, World,
 };
 
-#[allow(clippy::too_many_lines)]
+pub(crate) fn predicate(condition: Condition) -> Predicate {
+    match condition {
+        Condition::Always => Predicate::Always,
+        Condition::Gt => Predicate::IfGT,
+        Condition::Lt => Predicate::IfLT,
+        Condition::Eq => Predicate::IfEQ,
+        Condition::Ge => Predicate::IfGE,
+        Condition::Le => Predicate::IfLE,
+        Condition::Ne => Predicate::IfNotEQ,
+        Condition::GtOrLt => Predicate::IfGTOrLT,
+    }
+}
+
+/// Error returned by [`Program::try_new()`](crate::Program::try_new()) and
+/// [`Program::try_from_words()`](crate::Program::try_from_words()) for malformed bytecode.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ProgramError {
+    /// The bytecode length (in bytes) is not a multiple of 32.
+    UnalignedLength(usize),
+    /// The bytecode is empty or has more than `u16::MAX` words.
+    WordCountOutOfRange(usize),
+    /// An instruction cannot be loaded.
+    InvalidInstruction {
+        /// Index of the instruction in the bytecode, i.e., its program counter.
+        index: usize,
+        /// What is wrong with the instruction.
+        reason: InstructionError,
+    },
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnalignedLength(len) => {
+                write!(
+                    formatter,
+                    "bytecode length {len} is not a multiple of 32 bytes"
+                )
+            }
+            Self::WordCountOutOfRange(count) => write!(
+                formatter,
+                "bytecode has {count} words, while it must have 1 to {} words",
+                u16::MAX
+            ),
+            Self::InvalidInstruction { index, reason } => {
+                write!(formatter, "invalid instruction #{index}: {reason}")
+            }
+        }
+    }
+}
+
+impl error::Error for ProgramError {}
+
+/// Reason an instruction in [`ProgramError::InvalidInstruction`] cannot be loaded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum InstructionError {
+    /// The opcode doesn't support the addressing mode of the first source operand.
+    UnsupportedSource,
+    /// The opcode doesn't support the addressing mode of the first destination operand.
+    UnsupportedDestination,
+    /// The output is written to an immediate.
+    ImmediateDestination,
+    /// The output is written to the code page.
+    CodePageDestination,
+}
+
+impl fmt::Display for InstructionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedSource => {
+                formatter.write_str("source addressing mode is not supported by the opcode")
+            }
+            Self::UnsupportedDestination => {
+                formatter.write_str("destination addressing mode is not supported by the opcode")
+            }
+            Self::ImmediateDestination => formatter.write_str("output is written to an immediate"),
+            Self::CodePageDestination => formatter.write_str("output is written to the code page"),
+        }
+    }
+}
+
+impl error::Error for InstructionError {}
+
+/// Statically known transfer of control performed by an instruction, ignoring its predicate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ControlTransfer {
+    /// Execution proceeds to the next instruction.
+    Next,
+    /// Jump to the specified label, or to a dynamically computed address if `None`.
+    Jump(Option<u16>),
+    /// Near call; execution proceeds to the next instruction after the callee returns.
+    NearCall { callee: u16, exception_handler: u16 },
+    /// Far call; execution proceeds to the next instruction after the callee returns.
+    FarCall { exception_handler: u16 },
+    /// Return, optionally to the specified label.
+    Ret { label: Option<u16> },
+    /// The `invalid` instruction, which always panics.
+    Invalid,
+}
+
+impl ControlTransfer {
+    /// Decodes control transfer of an encoded instruction.
+    pub(crate) fn new(raw: u64) -> Self {
+        let (parsed, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+        let variant = &parsed.variant;
+        match variant.opcode {
+            Opcode::Jump(_) => {
+                let is_immediate = matches!(
+                    variant.src0_operand_type,
+                    RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only)
+                );
+                Self::Jump(is_immediate.then_some(parsed.imm_0))
+            }
+            Opcode::NearCall(_) => Self::NearCall {
+                callee: parsed.imm_0,
+                exception_handler: parsed.imm_1,
+            },
+            Opcode::FarCall(_) => Self::FarCall {
+                exception_handler: parsed.imm_0,
+            },
+            Opcode::Ret(_) => Self::Ret {
+                label: variant.flags[RET_TO_LABEL_BIT_IDX].then_some(parsed.imm_0),
+            },
+            Opcode::Invalid(_) => Self::Invalid,
+            _ => Self::Next,
+        }
+    }
+
+    /// Returns code labels (jump targets and exception handlers) statically referenced by the instruction.
+    pub(crate) fn labels(self) -> Vec<u16> {
+        match self {
+            Self::Jump(Some(label)) | Self::Ret { label: Some(label) } => vec![label],
+            Self::NearCall {
+                callee,
+                exception_handler,
+            } => vec![callee, exception_handler],
+            Self::FarCall { exception_handler } => vec![exception_handler],
+            Self::Next | Self::Jump(None) | Self::Ret { label: None } | Self::Invalid => vec![],
+        }
+    }
+}
+
 pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> Instruction<T, W> {
+    try_decode(raw, is_bootloader)
+        .unwrap_or_else(|reason| panic!("cannot decode instruction {raw:#018x}: {reason}"))
+}
+
+fn source<S>(operand: AnySource) -> Result<S, InstructionError>
+where
+    AnySource: TryInto<S>,
+{
+    operand
+        .try_into()
+        .map_err(|_| InstructionError::UnsupportedSource)
+}
+
+fn destination<D>(operand: AnyDestination) -> Result<D, InstructionError>
+where
+    AnyDestination: TryInto<D>,
+{
+    operand
+        .try_into()
+        .map_err(|_| InstructionError::UnsupportedDestination)
+}
+
+#[allow(clippy::too_many_lines)]
+pub(crate) fn try_decode<T: Tracer, W: World<T>>(
+    raw: u64,
+    is_bootloader: bool,
+) -> Result<Instruction<T, W>, InstructionError> {
     let (parsed, _) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
 
-    let predicate = match parsed.condition {
-        zkevm_opcode_defs::Condition::Always => Predicate::Always,
-        zkevm_opcode_defs::Condition::Gt => Predicate::IfGT,
-        zkevm_opcode_defs::Condition::Lt => Predicate::IfLT,
-        zkevm_opcode_defs::Condition::Eq => Predicate::IfEQ,
-        zkevm_opcode_defs::Condition::Ge => Predicate::IfGE,
-        zkevm_opcode_defs::Condition::Le => Predicate::IfLE,
-        zkevm_opcode_defs::Condition::Ne => Predicate::IfNotEQ,
-        zkevm_opcode_defs::Condition::GtOrLt => Predicate::IfGTOrLT,
-    };
     let arguments = Arguments::new(
-        predicate,
+        predicate(parsed.condition),
         parsed.variant.ergs_price(),
         ModeRequirements::new(
             parsed.variant.requires_kernel_mode(),
@@ -73,12 +236,14 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
             Register1(Register::new(parsed.dst0_reg_idx)).into()
         }
         RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only) => {
-            panic!("Parser wants to output to immediate")
+            return Err(InstructionError::ImmediateDestination);
         }
         Full(ImmMemHandlerFlags::UseAbsoluteOnStack) => AbsoluteStack(stack_out).into(),
         Full(ImmMemHandlerFlags::UseStackWithPushPop) => AdvanceStackPointer(stack_out).into(),
         Full(ImmMemHandlerFlags::UseStackWithOffset) => RelativeStack(stack_out).into(),
-        Full(ImmMemHandlerFlags::UseCodePage) => panic!("Parser wants to write to code page"),
+        Full(ImmMemHandlerFlags::UseCodePage) => {
+            return Err(InstructionError::CodePageDestination);
+        }
     };
 
     let src2 = Register2(Register::new(parsed.src1_reg_idx));
@@ -115,7 +280,7 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
         };
     }
 
-    match parsed.variant.opcode {
+    Ok(match parsed.variant.opcode {
         Opcode::Add(_) => binop!(Add, ()),
         Opcode::Sub(_) => binop!(Sub, ()),
         Opcode::Mul(_) => binop!(Mul, out2),
@@ -131,31 +296,31 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
             zkevm_opcode_defs::ShiftOpcode::Rol => binop!(RotateLeft, ()),
             zkevm_opcode_defs::ShiftOpcode::Ror => binop!(RotateRight, ()),
         },
-        Opcode::Jump(_) => Instruction::from_jump(src1, out.try_into().unwrap(), arguments),
+        Opcode::Jump(_) => Instruction::from_jump(src1, destination(out)?, arguments),
         Opcode::Context(x) => match x {
             zkevm_opcode_defs::ContextOpcode::This => {
-                Instruction::from_this(out.try_into().unwrap(), arguments)
+                Instruction::from_this(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::Caller => {
-                Instruction::from_caller(out.try_into().unwrap(), arguments)
+                Instruction::from_caller(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::CodeAddress => {
-                Instruction::from_code_address(out.try_into().unwrap(), arguments)
+                Instruction::from_code_address(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::ErgsLeft => {
-                Instruction::from_ergs_left(out.try_into().unwrap(), arguments)
+                Instruction::from_ergs_left(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::GetContextU128 => {
-                Instruction::from_context_u128(out.try_into().unwrap(), arguments)
+                Instruction::from_context_u128(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::SetContextU128 => {
-                Instruction::from_set_context_u128(src1.try_into().unwrap(), arguments)
+                Instruction::from_set_context_u128(source(src1)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::Sp => {
-                Instruction::from_context_sp(out.try_into().unwrap(), arguments)
+                Instruction::from_context_sp(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::Meta => {
-                Instruction::from_context_meta(out.try_into().unwrap(), arguments)
+                Instruction::from_context_meta(destination(out)?, arguments)
             }
             zkevm_opcode_defs::ContextOpcode::IncrementTxNumber => {
                 Instruction::from_increment_tx_number(arguments)
@@ -189,7 +354,7 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
                 }
             };
             constructor(
-                src1.try_into().unwrap(),
+                source(src1)?,
                 src2,
                 Immediate1(parsed.imm_0),
                 parsed.variant.flags[FAR_CALL_STATIC_FLAG_IDX],
@@ -206,110 +371,102 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
             };
             match kind {
                 zkevm_opcode_defs::RetOpcode::Ok => {
-                    Instruction::from_ret(src1.try_into().unwrap(), label, arguments)
+                    Instruction::from_ret(source(src1)?, label, arguments)
                 }
                 zkevm_opcode_defs::RetOpcode::Revert => {
-                    Instruction::from_revert(src1.try_into().unwrap(), label, arguments)
+                    Instruction::from_revert(source(src1)?, label, arguments)
                 }
                 zkevm_opcode_defs::RetOpcode::Panic => {
-                    Instruction::from_panic(src1.try_into().unwrap(), label, arguments)
+                    Instruction::from_panic(source(src1)?, label, arguments)
                 }
             }
         }
         Opcode::Log(x) => match x {
-            zkevm_opcode_defs::LogOpcode::StorageRead => Instruction::from_storage_read(
-                src1.try_into().unwrap(),
-                out.try_into().unwrap(),
-                arguments,
-            ),
+            zkevm_opcode_defs::LogOpcode::StorageRead => {
+                Instruction::from_storage_read(source(src1)?, destination(out)?, arguments)
+            }
             zkevm_opcode_defs::LogOpcode::TransientStorageRead => {
                 Instruction::from_transient_storage_read(
-                    src1.try_into().unwrap(),
-                    out.try_into().unwrap(),
+                    source(src1)?,
+                    destination(out)?,
                     arguments,
                 )
             }
 
             zkevm_opcode_defs::LogOpcode::StorageWrite => {
-                Instruction::from_storage_write(src1.try_into().unwrap(), src2, arguments)
+                Instruction::from_storage_write(source(src1)?, src2, arguments)
             }
 
             zkevm_opcode_defs::LogOpcode::TransientStorageWrite => {
-                Instruction::from_transient_storage_write(src1.try_into().unwrap(), src2, arguments)
+                Instruction::from_transient_storage_write(source(src1)?, src2, arguments)
             }
 
             zkevm_opcode_defs::LogOpcode::ToL1Message => Instruction::from_l2_to_l1_message(
-                src1.try_into().unwrap(),
+                source(src1)?,
                 src2,
                 parsed.variant.flags[FIRST_MESSAGE_FLAG_IDX],
                 arguments,
             ),
             zkevm_opcode_defs::LogOpcode::Event => Instruction::from_event(
-                src1.try_into().unwrap(),
+                source(src1)?,
                 src2,
                 parsed.variant.flags[FIRST_MESSAGE_FLAG_IDX],
                 arguments,
             ),
-            zkevm_opcode_defs::LogOpcode::PrecompileCall => Instruction::from_precompile_call(
-                src1.try_into().unwrap(),
-                src2,
-                out.try_into().unwrap(),
-                arguments,
-            ),
-            zkevm_opcode_defs::LogOpcode::Decommit => Instruction::from_decommit(
-                src1.try_into().unwrap(),
-                src2,
-                out.try_into().unwrap(),
-                arguments,
-            ),
+            zkevm_opcode_defs::LogOpcode::PrecompileCall => {
+                Instruction::from_precompile_call(source(src1)?, src2, destination(out)?, arguments)
+            }
+            zkevm_opcode_defs::LogOpcode::Decommit => {
+                Instruction::from_decommit(source(src1)?, src2, destination(out)?, arguments)
+            }
         },
         Opcode::UMA(x) => {
             let increment = parsed.variant.flags[UMA_INCREMENT_FLAG_IDX];
             match x {
                 zkevm_opcode_defs::UMAOpcode::HeapRead => Instruction::from_heap_read(
-                    src1.try_into().unwrap(),
-                    out.try_into().unwrap(),
+                    source(src1)?,
+                    destination(out)?,
                     increment.then_some(out2),
                     arguments,
                 ),
                 zkevm_opcode_defs::UMAOpcode::HeapWrite => Instruction::from_heap_write(
-                    src1.try_into().unwrap(),
+                    source(src1)?,
                     src2,
-                    increment.then_some(out.try_into().unwrap()),
+                    increment.then_some(destination(out)?),
                     arguments,
                     is_bootloader,
                 ),
                 zkevm_opcode_defs::UMAOpcode::AuxHeapRead => Instruction::from_aux_heap_read(
-                    src1.try_into().unwrap(),
-                    out.try_into().unwrap(),
+                    source(src1)?,
+                    destination(out)?,
                     increment.then_some(out2),
                     arguments,
                 ),
                 zkevm_opcode_defs::UMAOpcode::AuxHeapWrite => Instruction::from_aux_heap_store(
-                    src1.try_into().unwrap(),
+                    source(src1)?,
                     src2,
-                    increment.then_some(out.try_into().unwrap()),
+                    increment.then_some(destination(out)?),
                     arguments,
                 ),
                 zkevm_opcode_defs::UMAOpcode::FatPointerRead => Instruction::from_pointer_read(
-                    src1.try_into().unwrap(),
-                    out.try_into().unwrap(),
+                    source(src1)?,
+                    destination(out)?,
                     increment.then_some(out2),
                     arguments,
                 ),
                 zkevm_opcode_defs::UMAOpcode::StaticMemoryRead => {
                     Instruction::from_static_memory_read(
-                        src1.try_into().unwrap(),
-                        out.try_into().unwrap(),
+                        source(src1)?,
+                        destination(out)?,
                         increment.then_some(out2),
                         arguments,
                     )
                 }
                 zkevm_opcode_defs::UMAOpcode::StaticMemoryWrite => {
                     Instruction::from_static_memory_write(
-                        src1.try_into().unwrap(),
+                        source(src1)?,
                         src2,
-                        increment.then_some(out.try_into().unwrap()),
+                        increment.then_some(destination(out)?),
                         arguments,
                     )
                 }
@@ -335,5 +492,5 @@ pub(crate) fn decode<T: Tracer, W: World<T>>(raw: u64, is_bootloader: bool) -> I
                 arguments,
             )
         }
-    }
+    })
 }
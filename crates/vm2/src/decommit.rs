@@ -2,7 +2,7 @@ use primitive_types::{H160, U256};
 use zkevm_opcode_defs::{
     ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
 };
-use zksync_vm2_interface::{CycleStats, HeapId, Tracer};
+use zksync_vm2_interface::{v2::Tracer, CycleStats, HeapId};
 
 use crate::{
     program::Program,
@@ -196,7 +196,9 @@ impl WorldDiff {
         };
         *gas -= decommit.cost;
 
-        let decommit = world.decommit(decommit.code_key);
+        let decommit = world
+            .decommit(decommit.code_key)
+            .with_code_hash(decommit.code_key);
         if is_new {
             let code_len_in_words =
                 u32::try_from(decommit.code_page().len()).expect("bytecode length overflow");
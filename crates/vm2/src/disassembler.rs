@@ -0,0 +1,387 @@
+//! Disassembler producing textual EraVM assembly.
+
+use std::{fmt, ptr};
+
+use zkevm_opcode_defs::{
+    decoding::{EncodingModeProduction, VmEncodingMode},
+    BinopOpcode, ContextOpcode, DecodedOpcode, FarCallOpcode, ImmMemHandlerFlags, LogOpcode,
+    Opcode,
+    Operand::{self, Full, RegOnly, RegOrImm},
+    PtrOpcode, RegOrImmFlags, RetOpcode, ShiftOpcode, UMAOpcode, FAR_CALL_SHARD_FLAG_IDX,
+    FAR_CALL_STATIC_FLAG_IDX, FIRST_MESSAGE_FLAG_IDX, OPCODES_TABLE, RET_TO_LABEL_BIT_IDX,
+    SET_FLAGS_FLAG_IDX, SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES,
+    SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE, UMA_INCREMENT_FLAG_IDX,
+};
+use zksync_vm2_interface::v2::Tracer;
+
+use crate::{
+    assembler::CONDITIONS,
+    decode::{predicate, try_decode},
+    Instruction, World,
+};
+
+/// Encoded EraVM instruction that can be displayed as assembly.
+///
+/// The [`Display`](fmt::Display) implementation outputs a zkasm-like line consisting of the mnemonic with modifiers
+/// (e.g., `.s` for swapped operands, `.inc` for incrementing heap accesses), the predicate (e.g., `.gt`),
+/// `!` if the instruction sets flags, the operands and the ergs price; for example, `sub.s.lt! stack-[r1 + 2], r2, r3 ; ergs: 6`.
+/// Operands use the following syntax:
+///
+/// - `r1` is a register; `5` is an immediate; `@5` is a code label (an instruction index).
+/// - `stack[r1 + 2]` is an absolute stack slot; `stack-[r1 + 2]` is a slot relative to the stack pointer.
+/// - `stack-=[r1 + 2]` pops from the stack; `stack+=[r1 + 2]` pushes to it.
+/// - `code[r1 + 2]` is a code page word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisassembledInstruction(u64);
+
+impl DisassembledInstruction {
+    /// Wraps an encoded instruction.
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the encoded instruction.
+    pub const fn raw(self) -> u64 {
+        self.0
+    }
+
+    /// Recovers the encoding of a decoded instruction. Instructions don't retain their opcode, so this finds
+    /// an opcode that decodes into the same handler, and fills in the operands and the predicate from the instruction
+    /// [`Arguments`](crate::addressing_modes::Arguments).
+    ///
+    /// Returns `None` for instructions that aren't produced by decoding, such as superinstructions
+    /// and instructions with a [breakpoint](crate::Breakpoint).
+    pub(crate) fn of_instruction<T: Tracer, W: World<T>>(
+        instruction: &Instruction<T, W>,
+    ) -> Option<Self> {
+        let arguments = &instruction.arguments;
+        let condition = CONDITIONS
+            .into_iter()
+            .find(|&condition| predicate(condition) as u8 == arguments.predicate() as u8)?;
+        let ([src0_reg_idx, src1_reg_idx, dst0_reg_idx, dst1_reg_idx], [imm_0, imm_1]) =
+            arguments.encoded_operands();
+
+        OPCODES_TABLE.iter().find_map(|&variant| {
+            let raw = DecodedOpcode::<8, EncodingModeProduction> {
+                variant,
+                condition,
+                src0_reg_idx,
+                src1_reg_idx,
+                dst0_reg_idx,
+                dst1_reg_idx,
+                imm_0,
+                imm_1,
+            }
+            .serialize_as_integer();
+            // Heap writes in the bootloader are decoded into different handlers because of hooks.
+            let has_same_handler = [false, true].into_iter().any(|is_bootloader| {
+                try_decode::<T, W>(raw, is_bootloader)
+                    .is_ok_and(|decoded| ptr::fn_addr_eq(decoded.handler, instruction.handler))
+            });
+            has_same_handler.then_some(Self(raw))
+        })
+    }
+}
+
+/// Disassembles EraVM bytecode, i.e., the input of [`Program::new()`](crate::Program::new()).
+///
+/// Like `Program::new()`, this treats each 8-byte chunk of the bytecode as an instruction, so constants
+/// stored in the bytecode after the instructions are disassembled as well. The inverse operation is
+/// [`assemble()`](crate::assemble()).
+pub fn disassemble(bytecode: &[u8]) -> Vec<DisassembledInstruction> {
+    bytecode
+        .chunks_exact(8)
+        .map(|chunk| {
+            let mut raw = [0; 8];
+            raw.copy_from_slice(chunk);
+            DisassembledInstruction(u64::from_be_bytes(raw))
+        })
+        .collect()
+}
+
+impl fmt::Display for DisassembledInstruction {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (parsed, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(self.0);
+        let variant = &parsed.variant;
+
+        formatter.write_str(mnemonic(variant.opcode))?;
+        for &(modifier, index) in modifiers(variant.opcode) {
+            if variant.flags[index] {
+                formatter.write_str(modifier)?;
+            }
+        }
+        formatter.write_str(predicate(parsed.condition).assembly_suffix())?;
+        if can_set_flags(variant.opcode) && variant.flags[SET_FLAGS_FLAG_IDX] {
+            formatter.write_str("!")?;
+        }
+
+        let slots = operand_slots(
+            variant.opcode,
+            &variant.flags,
+            variant.src0_operand_type,
+            variant.dst0_operand_type,
+        );
+        for (i, slot) in slots.into_iter().enumerate() {
+            formatter.write_str(if i == 0 { " " } else { ", " })?;
+            match slot {
+                OperandSlot::Src0 => format_operand(
+                    formatter,
+                    variant.src0_operand_type,
+                    parsed.src0_reg_idx,
+                    parsed.imm_0,
+                    false,
+                )?,
+                OperandSlot::Src1 => write!(formatter, "r{}", parsed.src1_reg_idx)?,
+                OperandSlot::Dst0 => format_operand(
+                    formatter,
+                    variant.dst0_operand_type,
+                    parsed.dst0_reg_idx,
+                    parsed.imm_1,
+                    true,
+                )?,
+                OperandSlot::Dst1 => write!(formatter, "r{}", parsed.dst1_reg_idx)?,
+                OperandSlot::Label0 => write!(formatter, "@{}", parsed.imm_0)?,
+                OperandSlot::Label1 => write!(formatter, "@{}", parsed.imm_1)?,
+            }
+        }
+        write!(formatter, " ; ergs: {}", variant.ergs_price())
+    }
+}
+
+/// Operand of an instruction as written in assembly, identifying the fields of the encoded instruction it uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandSlot {
+    /// First source; uses `src0_reg_idx` and `imm_0`.
+    Src0,
+    /// Second source; always a register.
+    Src1,
+    /// First destination; uses `dst0_reg_idx` and `imm_1`.
+    Dst0,
+    /// Second destination; always a register.
+    Dst1,
+    /// Code label stored in `imm_0`.
+    Label0,
+    /// Code label stored in `imm_1`.
+    Label1,
+}
+
+/// Syntactic form of a full (i.e., `src0` or `dst0`) operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandForm {
+    /// `r1`
+    Register,
+    /// `5`
+    Immediate,
+    /// `stack[r1 + 2]`
+    AbsoluteStack,
+    /// `stack-[r1 + 2]`
+    RelativeStack,
+    /// `stack-=[r1 + 2]` for sources, `stack+=[r1 + 2]` for destinations.
+    StackPushPop,
+    /// `code[r1 + 2]`
+    CodePage,
+}
+
+impl OperandForm {
+    pub(crate) fn of(operand: Operand) -> Self {
+        match operand {
+            RegOnly
+            | RegOrImm(RegOrImmFlags::UseRegOnly)
+            | Full(ImmMemHandlerFlags::UseRegOnly) => Self::Register,
+            RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only) => {
+                Self::Immediate
+            }
+            Full(ImmMemHandlerFlags::UseAbsoluteOnStack) => Self::AbsoluteStack,
+            Full(ImmMemHandlerFlags::UseStackWithOffset) => Self::RelativeStack,
+            Full(ImmMemHandlerFlags::UseStackWithPushPop) => Self::StackPushPop,
+            Full(ImmMemHandlerFlags::UseCodePage) => Self::CodePage,
+        }
+    }
+}
+
+/// Returns the operands of an instruction in the order they are written in assembly.
+pub(crate) fn operand_slots(
+    opcode: Opcode,
+    flags: &[bool],
+    src0: Operand,
+    dst0: Operand,
+) -> Vec<OperandSlot> {
+    use OperandSlot::{Dst0, Dst1, Label0, Label1, Src0, Src1};
+
+    match opcode {
+        Opcode::Invalid(_)
+        | Opcode::Context(ContextOpcode::IncrementTxNumber | ContextOpcode::AuxMutating0) => {
+            vec![]
+        }
+        Opcode::Nop(_) => {
+            // Only stack pointer movements are meaningful for `nop`
+            let mut slots = vec![];
+            if OperandForm::of(src0) == OperandForm::StackPushPop {
+                slots.push(Src0);
+            }
+            if OperandForm::of(dst0) == OperandForm::StackPushPop {
+                slots.push(Dst0);
+            }
+            slots
+        }
+        Opcode::Add(_) | Opcode::Sub(_) | Opcode::Binop(_) | Opcode::Shift(_) | Opcode::Ptr(_) => {
+            vec![Src0, Src1, Dst0]
+        }
+        Opcode::Mul(_) | Opcode::Div(_) => vec![Src0, Src1, Dst0, Dst1],
+        Opcode::Jump(_) => vec![Src0, Dst0],
+        Opcode::Context(ContextOpcode::SetContextU128) => vec![Src0],
+        Opcode::Context(_) => vec![Dst0],
+        Opcode::NearCall(_) => vec![Src0, Label0, Label1],
+        Opcode::FarCall(_) => vec![Src0, Src1, Label0],
+        Opcode::Ret(_) => {
+            if flags[RET_TO_LABEL_BIT_IDX] {
+                vec![Src0, Label0]
+            } else {
+                vec![Src0]
+            }
+        }
+        Opcode::Log(LogOpcode::StorageRead | LogOpcode::TransientStorageRead) => vec![Src0, Dst0],
+        Opcode::Log(
+            LogOpcode::StorageWrite
+            | LogOpcode::TransientStorageWrite
+            | LogOpcode::ToL1Message
+            | LogOpcode::Event,
+        ) => vec![Src0, Src1],
+        Opcode::Log(LogOpcode::PrecompileCall | LogOpcode::Decommit) => vec![Src0, Src1, Dst0],
+        Opcode::UMA(uma) => {
+            let increment = flags[UMA_INCREMENT_FLAG_IDX];
+            let mut slots = match uma {
+                UMAOpcode::HeapWrite | UMAOpcode::AuxHeapWrite | UMAOpcode::StaticMemoryWrite => {
+                    vec![Src0, Src1]
+                }
+                UMAOpcode::HeapRead
+                | UMAOpcode::AuxHeapRead
+                | UMAOpcode::FatPointerRead
+                | UMAOpcode::StaticMemoryRead => vec![Src0, Dst0],
+            };
+            if increment {
+                slots.push(if slots[1] == Src1 { Dst0 } else { Dst1 });
+            }
+            slots
+        }
+    }
+}
+
+/// Returns the modifiers applicable to an opcode together with the indices of the flags they correspond to.
+/// Modifiers are written after the mnemonic in the returned order.
+pub(crate) fn modifiers(opcode: Opcode) -> &'static [(&'static str, usize)] {
+    match opcode {
+        Opcode::Add(_)
+        | Opcode::Sub(_)
+        | Opcode::Mul(_)
+        | Opcode::Div(_)
+        | Opcode::Binop(_)
+        | Opcode::Shift(_) => &[(".s", SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES)],
+        Opcode::Ptr(_) => &[(".s", SWAP_OPERANDS_FLAG_IDX_FOR_PTR_OPCODE)],
+        Opcode::FarCall(_) => &[
+            (".static", FAR_CALL_STATIC_FLAG_IDX),
+            (".shard", FAR_CALL_SHARD_FLAG_IDX),
+        ],
+        Opcode::Ret(_) => &[(".to_label", RET_TO_LABEL_BIT_IDX)],
+        Opcode::Log(LogOpcode::ToL1Message | LogOpcode::Event) => {
+            &[(".first", FIRST_MESSAGE_FLAG_IDX)]
+        }
+        Opcode::UMA(_) => &[(".inc", UMA_INCREMENT_FLAG_IDX)],
+        _ => &[],
+    }
+}
+
+/// Checks whether an opcode can set flags, which is denoted by `!` after the predicate.
+pub(crate) fn can_set_flags(opcode: Opcode) -> bool {
+    matches!(
+        opcode,
+        Opcode::Add(_)
+            | Opcode::Sub(_)
+            | Opcode::Mul(_)
+            | Opcode::Div(_)
+            | Opcode::Binop(_)
+            | Opcode::Shift(_)
+    )
+}
+
+/// Returns the mnemonic of an opcode, e.g. `far_call.delegate`.
+pub(crate) fn mnemonic(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::Invalid(_) => "invalid",
+        Opcode::Nop(_) => "nop",
+        Opcode::Add(_) => "add",
+        Opcode::Sub(_) => "sub",
+        Opcode::Mul(_) => "mul",
+        Opcode::Div(_) => "div",
+        Opcode::Jump(_) => "jump",
+        Opcode::Binop(BinopOpcode::Xor) => "xor",
+        Opcode::Binop(BinopOpcode::And) => "and",
+        Opcode::Binop(BinopOpcode::Or) => "or",
+        Opcode::Shift(ShiftOpcode::Shl) => "shl",
+        Opcode::Shift(ShiftOpcode::Shr) => "shr",
+        Opcode::Shift(ShiftOpcode::Rol) => "rol",
+        Opcode::Shift(ShiftOpcode::Ror) => "ror",
+        Opcode::Context(ContextOpcode::This) => "context.this",
+        Opcode::Context(ContextOpcode::Caller) => "context.caller",
+        Opcode::Context(ContextOpcode::CodeAddress) => "context.code_source",
+        Opcode::Context(ContextOpcode::Meta) => "context.meta",
+        Opcode::Context(ContextOpcode::ErgsLeft) => "context.ergs_left",
+        Opcode::Context(ContextOpcode::Sp) => "context.sp",
+        Opcode::Context(ContextOpcode::GetContextU128) => "context.get_context_u128",
+        Opcode::Context(ContextOpcode::SetContextU128) => "context.set_context_u128",
+        Opcode::Context(ContextOpcode::IncrementTxNumber) => "context.inc_tx_num",
+        Opcode::Context(ContextOpcode::AuxMutating0) => "context.aux_mutating0",
+        Opcode::Ptr(PtrOpcode::Add) => "ptr.add",
+        Opcode::Ptr(PtrOpcode::Sub) => "ptr.sub",
+        Opcode::Ptr(PtrOpcode::Pack) => "ptr.pack",
+        Opcode::Ptr(PtrOpcode::Shrink) => "ptr.shrink",
+        Opcode::NearCall(_) => "near_call",
+        Opcode::FarCall(FarCallOpcode::Normal) => "far_call",
+        Opcode::FarCall(FarCallOpcode::Delegate) => "far_call.delegate",
+        Opcode::FarCall(FarCallOpcode::Mimic) => "far_call.mimic",
+        Opcode::Ret(RetOpcode::Ok) => "ret.ok",
+        Opcode::Ret(RetOpcode::Revert) => "ret.revert",
+        Opcode::Ret(RetOpcode::Panic) => "ret.panic",
+        Opcode::Log(LogOpcode::StorageRead) => "log.sread",
+        Opcode::Log(LogOpcode::StorageWrite) => "log.swrite",
+        Opcode::Log(LogOpcode::TransientStorageRead) => "log.tread",
+        Opcode::Log(LogOpcode::TransientStorageWrite) => "log.twrite",
+        Opcode::Log(LogOpcode::ToL1Message) => "log.to_l1",
+        Opcode::Log(LogOpcode::Event) => "log.event",
+        Opcode::Log(LogOpcode::PrecompileCall) => "log.precompile",
+        Opcode::Log(LogOpcode::Decommit) => "log.decommit",
+        Opcode::UMA(UMAOpcode::HeapRead) => "ld.1",
+        Opcode::UMA(UMAOpcode::HeapWrite) => "st.1",
+        Opcode::UMA(UMAOpcode::AuxHeapRead) => "ld.2",
+        Opcode::UMA(UMAOpcode::AuxHeapWrite) => "st.2",
+        Opcode::UMA(UMAOpcode::FatPointerRead) => "ld",
+        Opcode::UMA(UMAOpcode::StaticMemoryRead) => "ld.static",
+        Opcode::UMA(UMAOpcode::StaticMemoryWrite) => "st.static",
+    }
+}
+
+fn format_operand(
+    formatter: &mut fmt::Formatter<'_>,
+    operand: Operand,
+    register: u8,
+    immediate: u16,
+    is_destination: bool,
+) -> fmt::Result {
+    let prefix = match OperandForm::of(operand) {
+        OperandForm::Register => return write!(formatter, "r{register}"),
+        OperandForm::Immediate => return write!(formatter, "{immediate}"),
+        OperandForm::AbsoluteStack => "stack[",
+        OperandForm::RelativeStack => "stack-[",
+        OperandForm::StackPushPop if is_destination => "stack+=[",
+        OperandForm::StackPushPop => "stack-=[",
+        OperandForm::CodePage => "code[",
+    };
+    formatter.write_str(prefix)?;
+    match (register, immediate) {
+        (0, immediate) => write!(formatter, "{immediate}")?,
+        (register, 0) => write!(formatter, "r{register}")?,
+        (register, immediate) => write!(formatter, "r{register} + {immediate}")?,
+    }
+    formatter.write_str("]")
+}
@@ -1,7 +1,7 @@
 use std::ptr;
 
 use primitive_types::U256;
-use zksync_vm2_interface::HeapId;
+use zksync_vm2_interface::{v2::HeapPointer, HeapId};
 
 /// Fat pointer to a heap location.
 #[derive(Debug)]
@@ -50,3 +50,14 @@ impl FatPointer {
         U256::zero() + unsafe { std::mem::transmute::<FatPointer, u128>(self) }
     }
 }
+
+impl From<&FatPointer> for HeapPointer {
+    fn from(pointer: &FatPointer) -> Self {
+        Self {
+            heap: pointer.memory_page,
+            start: pointer.start,
+            length: pointer.length,
+            offset: pointer.offset,
+        }
+    }
+}
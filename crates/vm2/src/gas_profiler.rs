@@ -0,0 +1,384 @@
+//! Tracer attributing spent ergs to opcodes, program counters, contracts and call frames.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Write as _},
+};
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::{
+    ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
+};
+use zksync_vm2_interface::{
+    v2::Tracer, CallframeInterface, CallingMode, GlobalStateInterface, Opcode, OpcodeType,
+    ShouldStop,
+};
+
+use crate::CallKind;
+
+/// Number of executed instructions and ergs spent by them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ErgsStats {
+    /// Number of executed instructions.
+    pub count: u64,
+    /// Total ergs spent by the instructions.
+    pub ergs: u64,
+}
+
+impl ErgsStats {
+    fn add(&mut self, ergs: u32) {
+        self.count += 1;
+        self.ergs += u64::from(ergs);
+    }
+}
+
+/// Ergs spent in a single far or near call frame, as recorded by [`GasProfiler`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameProfile {
+    /// Kind of the call that created the frame.
+    pub kind: CallKind,
+    /// Index of the calling frame in [`GasProfiler::frames()`], or `None` for the initial frame.
+    pub parent: Option<usize>,
+    /// Address of the contract, whose storage and context are used by the frame.
+    pub address: H160,
+    /// Address of the executed code.
+    pub code_address: H160,
+    /// Versioned hash of the executed code as stored by the deployer system contract
+    /// (zero for addresses without code).
+    pub code_hash: U256,
+    /// Program counter of the first instruction executed in the frame.
+    pub entry_pc: Option<u16>,
+    /// Ergs spent by the instructions executed in the frame, excluding the ergs passed to calls made by the frame.
+    pub exclusive: u64,
+    /// Ergs given to the frame and not returned to the caller, or `None` if the frame hasn't returned.
+    /// Equals `exclusive` plus the inclusive ergs of all calls made by the frame.
+    pub inclusive: Option<u64>,
+}
+
+impl FrameProfile {
+    fn write_name(&self, out: &mut String) {
+        write!(out, "{:?}", self.code_address).unwrap();
+        if let (CallKind::Near, Some(pc)) = (self.kind, self.entry_pc) {
+            write!(out, "@{pc}").unwrap();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OpenFrame {
+    index: usize,
+    gas: u32,
+    /// Gas of the calling frame right after the call. The gas returned by the call is added to it.
+    caller_gas: u32,
+}
+
+#[derive(Debug)]
+struct PendingInstruction {
+    gas: u32,
+    pc: Option<u16>,
+    number_of_callframes: usize,
+}
+
+/// [`Tracer`] attributing spent ergs to opcodes, program counters, contracts, and far and near call frames.
+///
+/// The cost of an instruction is the decrease of the frame gas ([`CallframeInterface::gas()`]) over the instruction,
+/// so it includes dynamic costs like decommitment, memory growth and storage access, with storage refunds subtracted.
+/// Calls are charged without the gas passed to the called frame; returns are charged to the returning frame
+/// without the gas returned to the caller.
+///
+/// The profiler must be used from the start of execution. It can be combined with other tracers using tuples,
+/// and profiles can be exported as [folded stacks](Self::folded_stacks()) for flamegraph tools or as a textual
+/// [report](Self::report()).
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+    frames: Vec<FrameProfile>,
+    open_frames: Vec<OpenFrame>,
+    pending: Option<PendingInstruction>,
+    opcodes: HashMap<Opcode, ErgsStats>,
+    program_counters: HashMap<(U256, u16), ErgsStats>,
+    contracts: HashMap<(H160, U256), ErgsStats>,
+    total_ergs: u64,
+}
+
+impl GasProfiler {
+    /// Creates an empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the total ergs spent by all traced instructions.
+    pub fn total_ergs(&self) -> u64 {
+        self.total_ergs
+    }
+
+    /// Returns ergs spent by each opcode.
+    pub fn opcodes(&self) -> &HashMap<Opcode, ErgsStats> {
+        &self.opcodes
+    }
+
+    /// Returns ergs spent by instructions keyed by code hash and program counter.
+    pub fn program_counters(&self) -> &HashMap<(U256, u16), ErgsStats> {
+        &self.program_counters
+    }
+
+    /// Returns ergs spent by instructions executed in the frames of each contract, keyed by code address
+    /// and code hash. Ergs spent in calls to other contracts are not included.
+    pub fn contracts(&self) -> &HashMap<(H160, U256), ErgsStats> {
+        &self.contracts
+    }
+
+    /// Returns all frames in the order they were entered. The initial frame is the first one.
+    pub fn frames(&self) -> &[FrameProfile] {
+        &self.frames
+    }
+
+    /// Returns exclusive ergs of frames in the folded stack format accepted by flamegraph tools,
+    /// one `frame;frame;...;frame ergs` line per distinct call stack.
+    ///
+    /// Far call frames are named by the code address, and near call frames by the code address
+    /// and the entry program counter (e.g., `0x…@42`).
+    pub fn folded_stacks(&self) -> String {
+        let mut stacks = BTreeMap::<String, u64>::new();
+        for (index, frame) in self.frames.iter().enumerate() {
+            if frame.exclusive > 0 {
+                *stacks.entry(self.stack_path(index)).or_default() += frame.exclusive;
+            }
+        }
+
+        let mut out = String::new();
+        for (stack, ergs) in stacks {
+            writeln!(out, "{stack} {ergs}").unwrap();
+        }
+        out
+    }
+
+    /// Returns a human-readable report listing at most `top_n` of the most expensive opcodes, program counters,
+    /// contracts and frames.
+    pub fn report(&self, top_n: usize) -> String {
+        let mut out = String::new();
+        writeln!(out, "Total ergs: {}", self.total_ergs).unwrap();
+
+        write_top(&mut out, "Opcodes", top_n, &self.opcodes, |out, opcode| {
+            write!(out, "{opcode:?}")
+        });
+        write_top(
+            &mut out,
+            "Program counters",
+            top_n,
+            &self.program_counters,
+            |out, (code_hash, pc)| write!(out, "{code_hash:#x}:{pc}"),
+        );
+        write_top(
+            &mut out,
+            "Contracts",
+            top_n,
+            &self.contracts,
+            |out, (address, code_hash)| write!(out, "{address:?} ({code_hash:#x})"),
+        );
+
+        let mut frames: Vec<_> = (0..self.frames.len()).collect();
+        frames.sort_by_key(|&index| {
+            let frame = &self.frames[index];
+            (
+                std::cmp::Reverse(frame.inclusive.unwrap_or(frame.exclusive)),
+                index,
+            )
+        });
+        writeln!(out, "\nFrames (inclusive / exclusive ergs):").unwrap();
+        for index in frames.into_iter().take(top_n) {
+            let frame = &self.frames[index];
+            let inclusive = frame
+                .inclusive
+                .map_or_else(|| "-".to_owned(), |ergs| ergs.to_string());
+            writeln!(
+                out,
+                "{inclusive:>12} {:>12}  {}",
+                frame.exclusive,
+                self.stack_path(index)
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    fn stack_path(&self, mut index: usize) -> String {
+        let mut path = vec![index];
+        while let Some(parent) = self.frames[index].parent {
+            path.push(parent);
+            index = parent;
+        }
+
+        let mut out = String::new();
+        for (i, &index) in path.iter().rev().enumerate() {
+            if i > 0 {
+                out.push(';');
+            }
+            self.frames[index].write_name(&mut out);
+        }
+        out
+    }
+
+    fn enter_frame<S: GlobalStateInterface>(&mut self, state: &mut S, kind: CallKind) {
+        let caller_gas = if self.open_frames.is_empty() {
+            0
+        } else {
+            state.callframe(1).gas()
+        };
+        let (address, code_address, entry_pc, gas) = {
+            let frame = state.current_frame();
+            (
+                frame.address(),
+                frame.code_address(),
+                frame.program_counter(),
+                frame.gas(),
+            )
+        };
+        let parent = self.open_frames.last().map(|frame| frame.index);
+        let code_hash = match (kind, parent) {
+            (CallKind::Near, Some(parent)) => self.frames[parent].code_hash,
+            _ => code_hash(state, code_address),
+        };
+
+        self.open_frames.push(OpenFrame {
+            index: self.frames.len(),
+            gas,
+            caller_gas,
+        });
+        self.frames.push(FrameProfile {
+            kind,
+            parent,
+            address,
+            code_address,
+            code_hash,
+            entry_pc,
+            exclusive: 0,
+            inclusive: None,
+        });
+    }
+
+    fn exit_frame(&mut self, returned_gas: u32) {
+        if let Some(frame) = self.open_frames.pop() {
+            self.frames[frame.index].inclusive =
+                Some(frame.gas.saturating_sub(returned_gas).into());
+        }
+    }
+
+    fn charge(&mut self, opcode: Opcode, pc: Option<u16>, ergs: u32) {
+        let Some(frame) = self.open_frames.last() else {
+            return;
+        };
+        let frame = &mut self.frames[frame.index];
+        frame.exclusive += u64::from(ergs);
+        self.total_ergs += u64::from(ergs);
+        self.opcodes.entry(opcode).or_default().add(ergs);
+        if let Some(pc) = pc {
+            self.program_counters
+                .entry((frame.code_hash, pc))
+                .or_default()
+                .add(ergs);
+        }
+        self.contracts
+            .entry((frame.code_address, frame.code_hash))
+            .or_default()
+            .add(ergs);
+    }
+}
+
+impl Tracer for GasProfiler {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if self.frames.is_empty() {
+            self.enter_frame(state, CallKind::Far(CallingMode::Normal));
+        }
+        let (gas, pc) = {
+            let frame = state.current_frame();
+            (frame.gas(), frame.program_counter())
+        };
+        self.pending = Some(PendingInstruction {
+            gas,
+            pc,
+            number_of_callframes: state.number_of_callframes(),
+        });
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let Some(pending) = self.pending.take() else {
+            return ShouldStop::Continue;
+        };
+        match OP::VALUE {
+            Opcode::FarCall(_) | Opcode::NearCall => {
+                let caller_gas = state.callframe(1).gas();
+                let passed_gas = state.current_frame().gas();
+                let ergs = pending
+                    .gas
+                    .saturating_sub(caller_gas.saturating_add(passed_gas));
+                self.charge(OP::VALUE, pending.pc, ergs);
+                let kind = match OP::VALUE {
+                    Opcode::FarCall(mode) => CallKind::Far(mode),
+                    _ => CallKind::Near,
+                };
+                self.enter_frame(state, kind);
+            }
+            Opcode::Ret(_) => {
+                let gas = state.current_frame().gas();
+                let returned_gas = if state.number_of_callframes() < pending.number_of_callframes {
+                    let caller_gas = self.open_frames.last().map_or(0, |frame| frame.caller_gas);
+                    gas.saturating_sub(caller_gas)
+                } else {
+                    // The initial frame isn't popped, so its gas is left as is.
+                    gas
+                };
+                self.charge(
+                    OP::VALUE,
+                    pending.pc,
+                    pending.gas.saturating_sub(returned_gas),
+                );
+                self.exit_frame(returned_gas);
+            }
+            opcode => {
+                let ergs = pending.gas.saturating_sub(state.current_frame().gas());
+                self.charge(opcode, pending.pc, ergs);
+            }
+        }
+        ShouldStop::Continue
+    }
+}
+
+/// Reads the code hash of the specified contract the same way as it's done for decommitting.
+fn code_hash<S: GlobalStateInterface>(state: &mut S, address: H160) -> U256 {
+    let deployer_system_contract_address =
+        Address::from_low_u64_be(DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW.into());
+    let code_info = state.get_storage(
+        deployer_system_contract_address,
+        U256::from_big_endian(address.as_bytes()),
+    );
+    let mut code_info_bytes = [0; 32];
+    code_info.to_big_endian(&mut code_info_bytes);
+    code_info_bytes[1] = 0;
+    U256::from_big_endian(&code_info_bytes)
+}
+
+fn write_top<K: Copy>(
+    out: &mut String,
+    title: &str,
+    top_n: usize,
+    stats: &HashMap<K, ErgsStats>,
+    write_key: impl Fn(&mut String, K) -> fmt::Result,
+) {
+    let mut entries: Vec<_> = stats.iter().map(|(&key, &stats)| (key, stats)).collect();
+    // Ties are broken by the key representation, so that reports are deterministic.
+    entries.sort_by_cached_key(|&(key, stats)| {
+        let mut key_repr = String::new();
+        write_key(&mut key_repr, key).unwrap();
+        (std::cmp::Reverse(stats.ergs), key_repr)
+    });
+
+    writeln!(out, "\n{title} (ergs / count):").unwrap();
+    for (key, stats) in entries.into_iter().take(top_n) {
+        write!(out, "{:>12} {:>12}  ", stats.ergs, stats.count).unwrap();
+        write_key(out, key).unwrap();
+        out.push('\n');
+    }
+}
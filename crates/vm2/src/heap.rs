@@ -7,8 +7,12 @@ use primitive_types::U256;
 use zkevm_opcode_defs::{NEW_MEMORY_PAGES_PER_FAR_CALL, STARTING_BASE_PAGE};
 use zksync_vm2_interface::HeapId;
 
-use crate::page_ids::{
-    bootloader_aux_heap_page, bootloader_calldata_page, bootloader_heap_page, static_memory_page,
+use crate::{
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
+    page_ids::{
+        bootloader_aux_heap_page, bootloader_calldata_page, bootloader_heap_page,
+        static_memory_page,
+    },
 };
 
 /// EraVM heap page size in bytes. Storage is now chunk-granular (see
@@ -648,6 +652,89 @@ impl ChunkPool {
     }
 }
 
+// region:Checkpoint implementations
+
+// Only allocated chunks are stored; absent ones read as zero anyway.
+impl Checkpoint for Heap {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.chunks.len());
+        let chunks: Vec<(usize, &Chunk)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, chunk)| Some((idx, chunk.as_ref()?)))
+            .collect();
+        encoder.put(&chunks.len());
+        for (idx, chunk) in chunks {
+            encoder.put(&idx);
+            encoder.put(&**chunk);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        const MAX_CHUNKS: usize = u32::MAX as usize / HEAP_CHUNK_SIZE + 1;
+
+        let len: usize = decoder.get()?;
+        if len > MAX_CHUNKS {
+            return Err(CheckpointError::Malformed("heap is too large"));
+        }
+        let mut heap = Self::default();
+        heap.chunks.resize_with(len, || None);
+        for _ in 0..decoder.get_len()? {
+            let idx: usize = decoder.get()?;
+            let chunk = heap
+                .chunks
+                .get_mut(idx)
+                .ok_or(CheckpointError::Malformed("heap chunk out of bounds"))?;
+            *chunk = Some(Box::new(decoder.get()?));
+        }
+        Ok(heap)
+    }
+}
+
+impl Checkpoint for DynamicPageGroup {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.code);
+        encoder.put(&self.heap);
+        encoder.put(&self.aux);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            code: decoder.get()?,
+            heap: decoder.get()?,
+            aux: decoder.get()?,
+        })
+    }
+}
+
+// The chunk pool is a cache and is not stored.
+impl Checkpoint for Heaps {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.static_memory);
+        encoder.put(&self.bootloader_calldata);
+        encoder.put(&self.bootloader_heap);
+        encoder.put(&self.bootloader_aux_heap);
+        encoder.put(&self.dynamic);
+        encoder.put(&self.bootloader_heap_rollback_info);
+        encoder.put(&self.bootloader_aux_rollback_info);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            static_memory: decoder.get()?,
+            bootloader_calldata: decoder.get()?,
+            bootloader_heap: decoder.get()?,
+            bootloader_aux_heap: decoder.get()?,
+            dynamic: decoder.get()?,
+            chunk_pool: ChunkPool::default(),
+            bootloader_heap_rollback_info: decoder.get()?,
+            bootloader_aux_rollback_info: decoder.get()?,
+        })
+    }
+}
+// endregion
+
 #[cfg(test)]
 #[allow(clippy::cast_possible_truncation)]
 mod tests {
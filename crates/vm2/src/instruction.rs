@@ -1,8 +1,14 @@
 use std::fmt;
 
-use zksync_vm2_interface::ShouldStop;
+use zksync_vm2_interface::{
+    v2::{PanicReason, Tracer},
+    ShouldStop,
+};
 
-use crate::{addressing_modes::Arguments, vm::VirtualMachine};
+use crate::{
+    addressing_modes::Arguments, breakpoints::Breakpoint, disassembler::DisassembledInstruction,
+    revert_reason::RevertReason, vm::VirtualMachine, watchpoints::WatchpointHit, World,
+};
 
 /// Single EraVM instruction (an opcode + [`Arguments`]).
 ///
@@ -22,6 +28,19 @@ impl<T, W> fmt::Debug for Instruction<T, W> {
     }
 }
 
+/// Outputs the instruction in assembler syntax like [`DisassembledInstruction`], e.g. `add.gt r1, r2, r3 ; ergs: 6`.
+/// Instructions don't retain their opcodes, so the opcode is recovered by finding one that decodes into
+/// the same handler. Instructions without such an opcode (e.g., superinstructions) are output with a placeholder
+/// mnemonic and all [`Arguments`] fields, e.g. `instruction.gt src=(r1, r2) dst=(r3, r0) imm=(5, 0) ergs=6`.
+impl<T: Tracer, W: World<T>> fmt::Display for Instruction<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match DisassembledInstruction::of_instruction(self) {
+            Some(disassembled) => fmt::Display::fmt(&disassembled, formatter),
+            None => write!(formatter, "instruction{}", self.arguments),
+        }
+    }
+}
+
 pub(crate) type Handler<T, W> = fn(&mut VirtualMachine<T, W>, &mut W, &mut T) -> ExecutionStatus;
 
 #[derive(Debug)]
@@ -50,17 +69,37 @@ impl From<ShouldStop> for ExecutionStatus {
     }
 }
 
-/// VM stop reason returned from [`VirtualMachine::run()`].
+/// VM stop reason returned from [`VirtualMachine::run()`] and related methods.
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum ExecutionEnd {
     /// The executed program has finished and returned the specified data.
     ProgramFinished(Vec<u8>),
     /// The executed program has reverted returning the specified data.
     Reverted(Vec<u8>),
-    /// The executed program has panicked.
-    Panicked,
+    /// The executed program has panicked for the specified reason. The reason is `None` if the program
+    /// has panicked explicitly using a panicking [`Ret`](zksync_vm2_interface::opcodes::Ret) instruction.
+    ///
+    /// Panics in other frames are reported to tracers via [`Tracer::on_panic()`].
+    Panicked(Option<PanicReason>),
     /// Returned when the bootloader writes to the heap location specified by [`hook_address`](crate::Settings.hook_address).
     SuspendedOnHook(u32),
     /// One of the tracers decided it is time to stop the VM.
     StoppedByTracer,
+    /// The instruction budget passed to [`VirtualMachine::run_for()`] was exhausted before the VM stopped.
+    InstructionLimitReached,
+    /// The VM is about to execute the instruction at the specified [breakpoint](VirtualMachine::add_breakpoint()).
+    Breakpoint(Breakpoint),
+    /// The last executed instruction has triggered a [watchpoint](VirtualMachine::add_watchpoint()).
+    Watchpoint(WatchpointHit),
+}
+
+impl ExecutionEnd {
+    /// Decodes the revert reason if the program has reverted. Returns `None` for all other outcomes.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        match self {
+            Self::Reverted(output) => Some(RevertReason::decode(output)),
+            _ => None,
+        }
+    }
 }
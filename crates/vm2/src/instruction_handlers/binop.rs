@@ -1,7 +1,8 @@
 use primitive_types::U256;
 use zksync_vm2_interface::{
     opcodes::{Add, And, Div, Mul, Or, RotateLeft, RotateRight, ShiftLeft, ShiftRight, Sub, Xor},
-    OpcodeType, Tracer,
+    v2::Tracer,
+    OpcodeType,
 };
 
 use super::{
@@ -21,7 +22,7 @@ use crate::{
     VirtualMachine, World,
 };
 
-fn binop<T, W, Op, In1, Out, const SWAP: bool, const SET_FLAGS: bool>(
+pub(super) fn binop<T, W, Op, In1, Out, const SWAP: bool, const SET_FLAGS: bool>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
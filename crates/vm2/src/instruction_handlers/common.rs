@@ -1,5 +1,9 @@
 use zkevm_opcode_defs::VM_MAX_STACK_DEPTH;
-use zksync_vm2_interface::{opcodes, OpcodeType, Tracer};
+use zksync_vm2_interface::{
+    opcodes,
+    v2::{PanicReason, Tracer},
+    OpcodeType,
+};
 
 use super::ret::free_panic;
 use crate::{
@@ -57,13 +61,13 @@ pub(crate) fn full_boilerplate<Opcode: OpcodeType, T: Tracer, W: World<T>>(
     #[allow(clippy::items_after_statements)] // Invariant for the code below
     const _: () = assert!(VM_MAX_STACK_DEPTH >= 214_748_444);
 
-    if vm.state.use_gas(args.get_static_gas_cost()).is_err()
-        || !args.mode_requirements().met(
-            vm.state.current_frame.is_kernel,
-            vm.state.current_frame.is_static,
-        )
-    {
-        return free_panic(vm, world, tracer);
+    if vm.state.use_gas(args.get_static_gas_cost()).is_err() {
+        return free_panic(vm, world, tracer, PanicReason::OutOfErgs);
+    }
+    let mode_requirements = args.mode_requirements();
+    let is_kernel = vm.state.current_frame.is_kernel;
+    if !mode_requirements.met(is_kernel, vm.state.current_frame.is_static) {
+        return free_panic(vm, world, tracer, mode_requirements.violation(is_kernel));
     }
 
     if args.predicate().satisfied(&vm.state.flags) {
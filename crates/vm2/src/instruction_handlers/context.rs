@@ -2,7 +2,8 @@ use primitive_types::{H160, U256};
 use zkevm_opcode_defs::VmMetaParameters;
 use zksync_vm2_interface::{
     opcodes::{self, Caller, CodeAddress, ContextU128, ErgsLeft, This, SP},
-    OpcodeType, Tracer,
+    v2::Tracer,
+    OpcodeType,
 };
 
 use super::common::boilerplate;
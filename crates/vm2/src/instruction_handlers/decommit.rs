@@ -1,6 +1,6 @@
 use primitive_types::U256;
 use zkevm_opcode_defs::{BlobSha256Format, ContractCodeSha256Format, VersionedHashLen32};
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer};
 
 use super::common::boilerplate_ext;
 use crate::{
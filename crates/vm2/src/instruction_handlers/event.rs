@@ -1,6 +1,6 @@
 use primitive_types::H160;
 use zkevm_opcode_defs::ADDRESS_EVENT_WRITER;
-use zksync_vm2_interface::{opcodes, Event, L2ToL1Log, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer, Event, L2ToL1Log};
 
 use super::common::boilerplate_ext;
 use crate::{
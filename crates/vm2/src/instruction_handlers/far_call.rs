@@ -2,7 +2,7 @@ use primitive_types::U256;
 use zkevm_opcode_defs::{system_params::MSG_VALUE_SIMULATOR_ADDITIVE_COST, ADDRESS_MSG_VALUE};
 use zksync_vm2_interface::{
     opcodes::{FarCall, TypeLevelCallingMode},
-    Tracer,
+    v2::{FarCallEnter, HeapPointer, PanicReason, Tracer},
 };
 
 use super::{
@@ -93,22 +93,26 @@ where
                 // If the gas is insufficient, the rest is burned
                 vm.state.current_frame.gas = 0;
                 mandated_gas = 0;
-                return None;
+                return Err(PanicReason::OutOfErgs);
             }
 
             if shard_call_failed {
-                return None;
+                return Err(PanicReason::FarCallShardFailure);
             }
+            let (unpaid_decommit, is_evm, is_evm_blob_format) =
+                decommit_result.ok_or(PanicReason::FarCallDecommitFailed)?;
             let calldata = maybe_calldata?;
-            let (unpaid_decommit, is_evm, is_evm_blob_format) = decommit_result?;
             let code_hash = unpaid_decommit.code_key();
             let should_materialize = unpaid_decommit.should_materialize();
-            let program = vm.world_diff.pay_for_decommit(
-                world,
-                tracer,
-                unpaid_decommit,
-                &mut vm.state.current_frame.gas,
-            )?;
+            let program = vm
+                .world_diff
+                .pay_for_decommit(
+                    world,
+                    tracer,
+                    unpaid_decommit,
+                    &mut vm.state.current_frame.gas,
+                )
+                .ok_or(PanicReason::OutOfErgs)?;
 
             if should_materialize {
                 // TODO: The interfaces that `World` provide exposes either a parsed program OR bytes,
@@ -119,7 +123,7 @@ where
                 materialize_decommit_page(vm, code_hash, &code, code_page_from_base(new_base_page));
             }
 
-            Some((calldata, program, is_evm, is_evm_blob_format))
+            Ok((calldata, program, is_evm, is_evm_blob_format))
         })();
 
         let maximum_gas = vm.state.current_frame.gas / 64 * 63;
@@ -128,8 +132,9 @@ where
         let new_frame_gas = normally_passed_gas + mandated_gas;
 
         // A far call pushes a new frame and returns from it in the next instruction if it panics.
+        let failure = fallible_part.as_ref().err().copied();
         let (calldata, program, is_evm_interpreter, is_evm_blob_format) = fallible_part
-            .unwrap_or_else(|| (U256::zero().into(), Program::new_panicking(), false, false));
+            .unwrap_or_else(|_| (U256::zero().into(), Program::new_panicking(), false, false));
 
         let new_frame_is_static = IS_STATIC || vm.state.current_frame.is_static;
         vm.push_frame::<M>(
@@ -142,6 +147,20 @@ where
             calldata.memory_page,
             vm.world_diff.snapshot(),
         );
+        // The new frame panics on its first instruction.
+        vm.state.pending_panic = failure;
+
+        let new_frame = &vm.state.current_frame;
+        tracer.on_far_call_enter(FarCallEnter::new(
+            M::VALUE,
+            new_frame.caller,
+            new_frame.address,
+            new_frame.code_address,
+            new_frame_gas,
+            new_frame.is_static,
+            HeapPointer::from(&calldata),
+            failure.is_some(),
+        ));
 
         vm.state.flags = Flags::new(false, false, false);
 
@@ -211,7 +230,7 @@ pub(crate) fn get_calldata<T, W>(
     is_pointer: bool,
     vm: &mut VirtualMachine<T, W>,
     already_failed: bool,
-) -> Option<FatPointer> {
+) -> Result<FatPointer, PanicReason> {
     let mut pointer = FatPointer::from(raw_abi);
     #[allow(clippy::cast_possible_truncation)]
     // intentional: the source is encoded in the lower byte of the extracted value
@@ -220,7 +239,7 @@ pub(crate) fn get_calldata<T, W>(
     match FatPointerSource::from_abi(raw_source) {
         FatPointerSource::ForwardFatPointer => {
             if !is_pointer || pointer.offset > pointer.length {
-                return None;
+                return Err(PanicReason::InvalidFatPointer);
             }
 
             pointer.narrow();
@@ -229,32 +248,35 @@ pub(crate) fn get_calldata<T, W>(
             let mut grow = |size| {
                 match target {
                     FatPointerTarget::ToHeap => {
-                        grow_heap::<_, _, Heap>(&mut vm.state, size).ok()?;
+                        grow_heap::<_, _, Heap>(&mut vm.state, size)
+                            .map_err(|()| PanicReason::OutOfErgs)?;
                         pointer.memory_page = vm.state.current_frame.heap;
                     }
                     FatPointerTarget::ToAuxHeap => {
-                        grow_heap::<_, _, AuxHeap>(&mut vm.state, size).ok()?;
+                        grow_heap::<_, _, AuxHeap>(&mut vm.state, size)
+                            .map_err(|()| PanicReason::OutOfErgs)?;
                         pointer.memory_page = vm.state.current_frame.aux_heap;
                     }
                 }
-                Some(())
+                Ok(())
             };
 
             // A pointer whose start + length > u32::MAX always causes the heap to grow,
             // even if it doesn't fullfill any other validity criteria.
             if let Some(bound) = pointer.start.checked_add(pointer.length) {
+                // If the call has already failed, the returned reason is ignored.
                 if is_pointer || pointer.offset != 0 || already_failed {
-                    return None;
+                    return Err(PanicReason::InvalidFatPointer);
                 }
                 grow(bound)?;
             } else {
-                grow(u32::MAX);
-                return None;
+                let _ = grow(u32::MAX);
+                return Err(PanicReason::HeapGrowthOverflow);
             }
         }
     }
 
-    Some(pointer)
+    Ok(pointer)
 }
 
 #[derive(Debug)]
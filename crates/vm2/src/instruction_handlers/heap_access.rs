@@ -1,10 +1,14 @@
 use primitive_types::U256;
-use zksync_vm2_interface::{opcodes, HeapId, OpcodeType, Tracer};
+use zksync_vm2_interface::{
+    opcodes,
+    v2::{HeapAccess, PanicReason, Tracer},
+    HeapId, OpcodeType,
+};
 
 use super::{
-    common::{boilerplate, full_boilerplate},
+    common::{boilerplate, boilerplate_ext, full_boilerplate},
     monomorphization::{match_boolean, match_reg_imm, monomorphize, parameterize},
-    ret::spontaneous_panic,
+    ret::panic_with,
 };
 use crate::{
     addressing_modes::{
@@ -90,26 +94,27 @@ fn load<T: Tracer, W: World<T>, H: HeapFromState, In: Source, const INCREMENT: b
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate::<H::Read, _, _>(vm, world, tracer, |vm, args| {
+    boilerplate_ext::<H::Read, _, _>(vm, world, tracer, |vm, args, _, tracer| {
         // Pointers need not be masked here even though we do not care about them being pointers.
         // They will panic, though because they are larger than 2^32.
         let (pointer, input_is_pointer) = In::get_with_pointer_flag(args, &mut vm.state);
 
         if bigger_than_last_address(pointer) {
             let _ = vm.state.use_gas(u32::MAX);
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::HeapGrowthOverflow);
             return;
         }
 
         let address = pointer.low_u32();
         let new_bound = address.wrapping_add(32);
         if grow_heap::<_, _, H>(&mut vm.state, new_bound).is_err() {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::OutOfErgs);
             return;
         }
 
         let heap = H::get_heap(&vm.state);
         let value = vm.state.heaps[heap].read_u256(address);
+        tracer.on_heap_access(HeapAccess::read(heap, address, value));
         Register1::set(args, &mut vm.state, value);
 
         if INCREMENT {
@@ -128,14 +133,14 @@ where
     H: HeapFromState,
     In: Source,
 {
-    full_boilerplate::<H::Write, _, _>(vm, world, tracer, |vm, args, _, _| {
+    full_boilerplate::<H::Write, _, _>(vm, world, tracer, |vm, args, _, tracer| {
         // Pointers need not be masked here even though we do not care about them being pointers.
         // They will panic, though because they are larger than 2^32.
         let (pointer, _) = In::get_with_pointer_flag(args, &mut vm.state);
 
         if bigger_than_last_address(pointer) {
             let _ = vm.state.use_gas(u32::MAX);
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::HeapGrowthOverflow);
             return ExecutionStatus::Running;
         }
 
@@ -144,12 +149,13 @@ where
 
         let new_bound = address.wrapping_add(32);
         if grow_heap::<_, _, H>(&mut vm.state, new_bound).is_err() {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::OutOfErgs);
             return ExecutionStatus::Running;
         }
 
         let heap = H::get_heap(&vm.state);
         vm.state.heaps.write_u256(heap, address, value);
+        tracer.on_heap_access(HeapAccess::write(heap, address, value));
 
         if INCREMENT {
             Register1::set(args, &mut vm.state, pointer + 32);
@@ -158,7 +164,7 @@ where
         if HOOKING_ENABLED && address == vm.settings.hook_address {
             ExecutionStatus::Stopped(ExecutionEnd::SuspendedOnHook(value.as_u32()))
         } else {
-            ExecutionStatus::Running
+            vm.watchpoints.heap_write(heap, address, value)
         }
     })
 }
@@ -182,10 +188,10 @@ fn load_pointer<T: Tracer, W: World<T>, const INCREMENT: bool>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate::<opcodes::PointerRead, _, _>(vm, world, tracer, |vm, args| {
+    boilerplate_ext::<opcodes::PointerRead, _, _>(vm, world, tracer, |vm, args, _, tracer| {
         let (input, input_is_pointer) = Register1::get_with_pointer_flag(args, &mut vm.state);
         if !input_is_pointer {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::InvalidFatPointer);
             return;
         }
         let pointer = FatPointer::from(input);
@@ -194,7 +200,7 @@ fn load_pointer<T: Tracer, W: World<T>, const INCREMENT: bool>(
         // but if offset + 32 is not representable, we panic, even if we could've read some bytes.
         // This is not a bug, this is how it must work to be backwards compatible.
         if pointer.offset > LAST_ADDRESS {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::InvalidFatPointer);
             return;
         }
 
@@ -202,6 +208,7 @@ fn load_pointer<T: Tracer, W: World<T>, const INCREMENT: bool>(
         let end = start.saturating_add(32).min(pointer.start + pointer.length);
 
         let value = vm.state.heaps[pointer.memory_page].read_u256_partially(start..end);
+        tracer.on_heap_access(HeapAccess::read(pointer.memory_page, start, value));
         Register1::set(args, &mut vm.state, value);
 
         if INCREMENT {
@@ -216,18 +223,19 @@ fn load_static<T: Tracer, W: World<T>, In: Source, const INCREMENT: bool>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate::<opcodes::StaticMemoryRead, _, _>(vm, world, tracer, |vm, args| {
+    boilerplate_ext::<opcodes::StaticMemoryRead, _, _>(vm, world, tracer, |vm, args, _, tracer| {
         // Static memory uses a plain 32-bit offset in src0, same as heap UMA ops.
         // Pointer-typed values are still accepted as raw words and then validated by range check.
         let (pointer, input_is_pointer) = In::get_with_pointer_flag(args, &mut vm.state);
 
         if bigger_than_last_address(pointer) {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::HeapGrowthOverflow);
             return;
         }
 
         let address = pointer.low_u32();
         let value = vm.state.heaps[STATIC_MEMORY_HEAP].read_u256(address);
+        tracer.on_heap_access(HeapAccess::read(STATIC_MEMORY_HEAP, address, value));
         Register1::set(args, &mut vm.state, value);
 
         if INCREMENT {
@@ -245,26 +253,35 @@ where
     T: Tracer,
     In: Source,
 {
-    boilerplate::<opcodes::StaticMemoryWrite, _, _>(vm, world, tracer, |vm, args| {
-        // Static memory uses a plain 32-bit offset in src0, same as heap UMA ops.
-        // Pointer-typed values are still accepted as raw words and then validated by range check.
-        let (pointer, _) = In::get_with_pointer_flag(args, &mut vm.state);
-
-        if bigger_than_last_address(pointer) {
-            vm.state.current_frame.pc = spontaneous_panic();
-            return;
-        }
-
-        let address = pointer.low_u32();
-        let value = Register2::get(args, &mut vm.state);
-        vm.state
-            .heaps
-            .write_u256(STATIC_MEMORY_HEAP, address, value);
-
-        if INCREMENT {
-            Register1::set(args, &mut vm.state, pointer + 32);
-        }
-    })
+    full_boilerplate::<opcodes::StaticMemoryWrite, _, _>(
+        vm,
+        world,
+        tracer,
+        |vm, args, _, tracer| {
+            // Static memory uses a plain 32-bit offset in src0, same as heap UMA ops.
+            // Pointer-typed values are still accepted as raw words and then validated by range check.
+            let (pointer, _) = In::get_with_pointer_flag(args, &mut vm.state);
+
+            if bigger_than_last_address(pointer) {
+                panic_with(vm, PanicReason::HeapGrowthOverflow);
+                return ExecutionStatus::Running;
+            }
+
+            let address = pointer.low_u32();
+            let value = Register2::get(args, &mut vm.state);
+            vm.state
+                .heaps
+                .write_u256(STATIC_MEMORY_HEAP, address, value);
+            tracer.on_heap_access(HeapAccess::write(STATIC_MEMORY_HEAP, address, value));
+
+            if INCREMENT {
+                Register1::set(args, &mut vm.state, pointer + 32);
+            }
+
+            vm.watchpoints
+                .heap_write(STATIC_MEMORY_HEAP, address, value)
+        },
+    )
 }
 
 impl<T: Tracer, W: World<T>> Instruction<T, W> {
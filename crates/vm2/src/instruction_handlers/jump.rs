@@ -1,4 +1,4 @@
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer};
 
 use super::{
     common::boilerplate,
@@ -13,7 +13,7 @@ use crate::{
     VirtualMachine, World,
 };
 
-fn jump<T: Tracer, W: World<T>, In: Source>(
+pub(super) fn jump<T: Tracer, W: World<T>, In: Source>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
@@ -1,10 +1,9 @@
-#[cfg(feature = "single_instruction_test")]
-pub(crate) use ret::spontaneous_panic;
-
+#[cfg(not(feature = "single_instruction_test"))]
+pub(crate) use self::superinstructions::superinstruction;
 pub(crate) use self::{
     context::address_into_u256,
     heap_access::{AuxHeap, Heap},
-    ret::invalid_instruction,
+    ret::{invalid_instruction, spontaneous_panic},
 };
 
 mod binop;
@@ -22,3 +21,6 @@ mod pointer;
 mod precompiles;
 mod ret;
 mod storage;
+// Superinstructions are installed by the program decoder, which is mocked in single instruction tests
+#[cfg(not(feature = "single_instruction_test"))]
+mod superinstructions;
@@ -1,4 +1,4 @@
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer};
 
 use super::common::boilerplate;
 use crate::{
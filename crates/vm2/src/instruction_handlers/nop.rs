@@ -1,4 +1,4 @@
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer};
 
 use super::common::boilerplate;
 use crate::{
@@ -7,7 +7,7 @@ use crate::{
     Instruction, VirtualMachine, World,
 };
 
-fn nop<T: Tracer, W: World<T>>(
+pub(super) fn nop<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
@@ -1,7 +1,8 @@
 use primitive_types::U256;
 use zksync_vm2_interface::{
     opcodes::{PointerAdd, PointerPack, PointerShrink, PointerSub},
-    OpcodeType, Tracer,
+    v2::{PanicReason, Tracer},
+    OpcodeType,
 };
 
 use super::{
@@ -9,7 +10,7 @@ use super::{
     monomorphization::{
         match_boolean, match_destination, match_source, monomorphize, parameterize,
     },
-    ret::spontaneous_panic,
+    ret::panic_with,
 };
 use crate::{
     addressing_modes::{
@@ -40,12 +41,12 @@ fn ptr<T: Tracer, W: World<T>, Op: PtrOp, In1: Source, Out: Destination, const S
         };
 
         if !a_is_pointer || b_is_pointer {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::InvalidFatPointer);
             return;
         }
 
         let Some(result) = Op::perform(a, b) else {
-            vm.state.current_frame.pc = spontaneous_panic();
+            panic_with(vm, PanicReason::InvalidFatPointer);
             return;
         };
 
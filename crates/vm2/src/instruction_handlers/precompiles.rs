@@ -1,5 +1,5 @@
 use primitive_types::U256;
-use zksync_vm2_interface::{opcodes, HeapId, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer, HeapId};
 
 use super::common::boilerplate_ext;
 use crate::{
@@ -1,7 +1,8 @@
 use primitive_types::U256;
 use zksync_vm2_interface::{
     opcodes::{self, Normal, Panic, Revert, TypeLevelReturnType},
-    ReturnType, Tracer,
+    v2::{FarCallExit, HeapPointer, PanicReason, Tracer},
+    ReturnType,
 };
 
 use super::{
@@ -22,12 +23,17 @@ use crate::{
 
 fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bool>(
     vm: &mut VirtualMachine<T, W>,
+    tracer: &mut T,
     args: &Arguments,
 ) -> ExecutionStatus {
     let mut return_type = RT::VALUE;
+    let mut panic_reason = vm.state.pending_panic.take();
+    if let Some(reason) = panic_reason {
+        tracer.on_panic(reason);
+    }
     let near_call_leftover_gas = vm.state.current_frame.gas;
 
-    let (snapshot, leftover_gas) = if let Some(FrameRemnant {
+    let (snapshot, leftover_gas, returned_heap) = if let Some(FrameRemnant {
         exception_handler,
         snapshot,
     }) = vm.state.current_frame.pop_near_call()
@@ -39,7 +45,7 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
             vm.state.current_frame.set_pc_from_u16(exception_handler);
         }
 
-        (snapshot, near_call_leftover_gas)
+        (snapshot, near_call_leftover_gas, None)
     } else {
         let (raw_abi, is_pointer) = Register1::get_with_pointer_flag(args, &mut vm.state);
         let return_value_or_panic = if return_type == ReturnType::Panic {
@@ -48,28 +54,39 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
             // grows the heap to `u32::MAX`, draining the frame's gas. Passing `already_failed = true`
             // charges exactly that penalty while discarding the (unused) returndata pointer. This
             // mirrors the proving circuit and post-#217 zk_evm; see `get_calldata`.
-            get_calldata(raw_abi, is_pointer, vm, true);
+            let _ = get_calldata(raw_abi, is_pointer, vm, true);
             None
         } else {
-            let result = get_calldata(raw_abi, is_pointer, vm, false).filter(|pointer| {
+            let result = get_calldata(raw_abi, is_pointer, vm, false).and_then(|pointer| {
                 if vm.state.current_frame.is_kernel {
-                    true
+                    return Ok(pointer);
+                }
+                // Non-kernel returndata forwarding must be unidirectional: callers may pass
+                // pointers down the stack, but callees must not forward pointers to older pages.
+                // This mirrors zk_evm's restriction based on base memory page checks.
+                if pointer.memory_page.as_u32() >= base_page_from_heap(vm.state.current_frame.heap)
+                    && pointer.memory_page != vm.state.current_frame.calldata_heap
+                {
+                    Ok(pointer)
                 } else {
-                    // Non-kernel returndata forwarding must be unidirectional: callers may pass
-                    // pointers down the stack, but callees must not forward pointers to older pages.
-                    // This mirrors zk_evm's restriction based on base memory page checks.
-                    pointer.memory_page.as_u32() >= base_page_from_heap(vm.state.current_frame.heap)
-                        && pointer.memory_page != vm.state.current_frame.calldata_heap
+                    Err(PanicReason::ReturnedCalldataPointer)
                 }
             });
 
-            if result.is_none() {
-                return_type = ReturnType::Panic;
+            match result {
+                Ok(pointer) => Some(pointer),
+                Err(reason) => {
+                    return_type = ReturnType::Panic;
+                    panic_reason = Some(reason);
+                    tracer.on_panic(reason);
+                    None
+                }
             }
-            result
         };
 
         let leftover_gas = vm.state.current_frame.gas;
+        let address = vm.state.current_frame.address;
+        let code_address = vm.state.current_frame.code_address;
 
         let Some(FrameRemnant {
             exception_handler,
@@ -104,13 +121,24 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
                     ExecutionStatus::Stopped(ExecutionEnd::ProgramFinished(output))
                 }
             } else {
-                ExecutionStatus::Stopped(ExecutionEnd::Panicked)
+                ExecutionStatus::Stopped(ExecutionEnd::Panicked(panic_reason))
             };
         };
 
+        tracer.on_far_call_exit(FarCallExit::new(
+            return_type,
+            address,
+            code_address,
+            leftover_gas,
+            return_value_or_panic.as_ref().map(HeapPointer::from),
+        ));
+
         vm.state.set_context_u128(0);
         vm.state.registers = [U256::zero(); 16];
 
+        let returned_heap = return_value_or_panic
+            .as_ref()
+            .map(|pointer| pointer.memory_page);
         if let Some(return_value) = return_value_or_panic {
             vm.state.registers[1] = return_value.into_u256();
         }
@@ -120,13 +148,15 @@ fn naked_ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bo
             vm.state.current_frame.set_pc_from_u16(exception_handler);
         }
 
-        (snapshot, leftover_gas)
+        (snapshot, leftover_gas, returned_heap)
     };
 
     if return_type.is_failure() {
         vm.world_diff.append_rollback_logs(&snapshot);
         vm.world_diff.rollback(snapshot);
     }
+    // Only done after the rollback above, which needs the history.
+    vm.finish_pending_commit(returned_heap);
 
     vm.state.flags = Flags::new(return_type == ReturnType::Panic, false, false);
     vm.state.current_frame.gas += leftover_gas;
@@ -139,8 +169,8 @@ fn ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bool>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    full_boilerplate::<opcodes::Ret<RT>, _, _>(vm, world, tracer, |vm, args, _, _| {
-        naked_ret::<T, W, RT, TO_LABEL>(vm, args)
+    full_boilerplate::<opcodes::Ret<RT>, _, _>(vm, world, tracer, |vm, args, _, tracer| {
+        naked_ret::<T, W, RT, TO_LABEL>(vm, tracer, args)
     })
 }
 
@@ -152,18 +182,24 @@ fn ret<T: Tracer, W: World<T>, RT: TypeLevelReturnType, const TO_LABEL: bool>(
 /// - using privileged instructions while not in a system call
 /// - the far call stack overflows
 ///
-/// For all other panics, point the instruction pointer at [PANIC] instead.
+/// For all other panics, use [`panic_with()`] instead.
 pub(crate) fn free_panic<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
+    reason: PanicReason,
 ) -> ExecutionStatus {
+    // If the current instruction is a pending panic that cannot be paid for, the pending reason is more informative.
+    if vm.state.pending_panic.is_none() {
+        vm.state.pending_panic = Some(reason);
+    }
     tracer.before_instruction::<opcodes::Ret<Panic>, _>(&mut VmAndWorld { vm, world });
     // A spontaneous panic has no return ABI: these empty args encode source register r0, so
     // naked_ret's return-ABI resolution reads zero and charges no heap growth. (args are otherwise
     // only consulted for the jump label when TO_LABEL is set, which it isn't here.)
     naked_ret::<T, W, Panic, false>(
         vm,
+        tracer,
         &Arguments::new(Predicate::Always, 0, ModeRequirements::none()),
     )
     .merge_tracer(tracer.after_instruction::<opcodes::Ret<Panic>, _>(&mut VmAndWorld { vm, world }))
@@ -175,7 +211,7 @@ fn invalid<T: Tracer, W: World<T>>(
     tracer: &mut T,
 ) -> ExecutionStatus {
     vm.state.current_frame.gas = 0;
-    free_panic(vm, world, tracer)
+    free_panic(vm, world, tracer, PanicReason::InvalidInstruction)
 }
 
 trait GenericStatics<T, W> {
@@ -196,6 +232,16 @@ pub(crate) fn spontaneous_panic<'a, T: Tracer, W: World<T>>() -> &'a Instruction
     &<()>::PANIC
 }
 
+/// Points the program counter at [`spontaneous_panic()`], so that the next executed instruction panics
+/// for the specified reason.
+pub(crate) fn panic_with<T: Tracer, W: World<T>>(
+    vm: &mut VirtualMachine<T, W>,
+    reason: PanicReason,
+) {
+    vm.state.pending_panic = Some(reason);
+    vm.state.current_frame.pc = spontaneous_panic();
+}
+
 /// Panics, burning all available gas.
 pub(crate) fn invalid_instruction<'a, T: Tracer, W: World<T>>() -> &'a Instruction<T, W> {
     &<()>::INVALID
@@ -1,6 +1,9 @@
-use zksync_vm2_interface::{opcodes, Tracer};
+use zksync_vm2_interface::{
+    opcodes,
+    v2::{StorageAccess, Tracer},
+};
 
-use super::common::{boilerplate, boilerplate_ext};
+use super::common::{boilerplate_ext, full_boilerplate};
 use crate::{
     addressing_modes::{
         Arguments, Destination, Register1, Register2, Source, SLOAD_COST, SSTORE_COST,
@@ -14,14 +17,15 @@ fn sstore<T: Tracer, W: World<T>>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate_ext::<opcodes::StorageWrite, _, _>(vm, world, tracer, |vm, args, world, tracer| {
+    full_boilerplate::<opcodes::StorageWrite, _, _>(vm, world, tracer, |vm, args, world, tracer| {
         let key = Register1::get(args, &mut vm.state);
         let value = Register2::get(args, &mut vm.state);
+        let address = vm.state.current_frame.address;
 
         let refund = vm.world_diff.write_storage(
             world,
             tracer,
-            vm.state.current_frame.address,
+            address,
             key,
             value,
             vm.state.transaction_number,
@@ -29,6 +33,8 @@ fn sstore<T: Tracer, W: World<T>>(
 
         assert!(refund <= SSTORE_COST);
         vm.state.current_frame.gas += refund;
+
+        vm.watchpoints.storage_write(address, key, value)
     })
 }
 
@@ -37,35 +43,45 @@ fn sstore_transient<T: Tracer, W: World<T>>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate::<opcodes::TransientStorageWrite, _, _>(vm, world, tracer, |vm, args| {
-        let key = Register1::get(args, &mut vm.state);
-        let value = Register2::get(args, &mut vm.state);
-
-        vm.world_diff
-            .write_transient_storage(vm.state.current_frame.address, key, value);
-    })
+    boilerplate_ext::<opcodes::TransientStorageWrite, _, _>(
+        vm,
+        world,
+        tracer,
+        |vm, args, _, tracer| {
+            let key = Register1::get(args, &mut vm.state);
+            let value = Register2::get(args, &mut vm.state);
+            let address = vm.state.current_frame.address;
+
+            let read_value = vm.world_diff.write_transient_storage(address, key, value);
+            tracer.on_storage_access(StorageAccess::transient(
+                address,
+                key,
+                read_value,
+                Some(value),
+            ));
+        },
+    )
 }
 
-fn sload<T: Tracer, W: World<T>>(
+pub(super) fn sload<T: Tracer, W: World<T>>(
     vm: &mut VirtualMachine<T, W>,
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate_ext::<opcodes::StorageRead, _, _>(vm, world, tracer, |vm, args, world, tracer| {
+    full_boilerplate::<opcodes::StorageRead, _, _>(vm, world, tracer, |vm, args, world, tracer| {
         let key = Register1::get(args, &mut vm.state);
+        let address = vm.state.current_frame.address;
 
-        let (value, refund) = vm.world_diff.read_storage(
-            world,
-            tracer,
-            vm.state.current_frame.address,
-            key,
-            vm.state.transaction_number,
-        );
+        let (value, refund) =
+            vm.world_diff
+                .read_storage(world, tracer, address, key, vm.state.transaction_number);
 
         assert!(refund <= SLOAD_COST);
         vm.state.current_frame.gas += refund;
 
         Register1::set(args, &mut vm.state, value);
+
+        vm.watchpoints.storage_read(address, key, value)
     })
 }
 
@@ -74,14 +90,19 @@ fn sload_transient<T: Tracer, W: World<T>>(
     world: &mut W,
     tracer: &mut T,
 ) -> ExecutionStatus {
-    boilerplate::<opcodes::TransientStorageRead, _, _>(vm, world, tracer, |vm, args| {
-        let key = Register1::get(args, &mut vm.state);
-        let value = vm
-            .world_diff
-            .read_transient_storage(vm.state.current_frame.address, key);
-
-        Register1::set(args, &mut vm.state, value);
-    })
+    boilerplate_ext::<opcodes::TransientStorageRead, _, _>(
+        vm,
+        world,
+        tracer,
+        |vm, args, _, tracer| {
+            let key = Register1::get(args, &mut vm.state);
+            let address = vm.state.current_frame.address;
+            let value = vm.world_diff.read_transient_storage(address, key);
+            tracer.on_storage_access(StorageAccess::transient(address, key, value, None));
+
+            Register1::set(args, &mut vm.state, value);
+        },
+    )
 }
 
 impl<T: Tracer, W: World<T>> Instruction<T, W> {
@@ -0,0 +1,289 @@
+//! Superinstructions, i.e. handlers executing a frequent pair of instructions in a single dispatch.
+//!
+//! A superinstruction replaces the handler of the first instruction in a pair. It runs the handlers
+//! of both instructions one after another, so gas, predicates, tracer hooks and panics are handled
+//! per instruction exactly like without fusion; the only thing saved is the dispatch between the two.
+
+use std::ptr;
+
+use zkevm_opcode_defs::{
+    decoding::{EncodingModeProduction, VmEncodingMode},
+    ImmMemHandlerFlags, LogOpcode, Opcode, Operand,
+    Operand::{Full, RegOnly, RegOrImm},
+    RegOrImmFlags, SET_FLAGS_FLAG_IDX, SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES,
+};
+use zksync_vm2_interface::{
+    opcodes::{Add, Sub},
+    v2::Tracer,
+};
+
+use super::{
+    binop::{binop, Binop},
+    jump::jump,
+    monomorphization::{match_boolean, monomorphize, parameterize},
+    nop::nop,
+    storage::sload,
+};
+use crate::{
+    addressing_modes::{AdvanceStackPointer, Destination, Immediate1, Register1, Source},
+    instruction::{ExecutionStatus, Handler},
+    VirtualMachine, World,
+};
+
+/// Runs `first`, then `second` if execution proceeds to the next instruction.
+///
+/// The second instruction is only run directly from [`VirtualMachine::run()`]; other execution methods
+/// observe the VM after every instruction. It is also never run in programs with breakpoints,
+/// since the breakpoint could be set on it.
+#[inline(always)]
+fn fused<T, W>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+    first: Handler<T, W>,
+    second: Handler<T, W>,
+) -> ExecutionStatus {
+    let next_instruction = vm.state.current_frame.pc.wrapping_add(1);
+    match first(vm, world, tracer) {
+        ExecutionStatus::Running
+            if vm.allow_superinstructions
+                && ptr::eq(vm.state.current_frame.pc, next_instruction)
+                && !vm.state.current_frame.program.has_breakpoints() =>
+        {
+            second(vm, world, tracer)
+        }
+        status => status,
+    }
+}
+
+/// `add` / `sub` with a register output followed by a jump to an immediate address,
+/// e.g. a loop counter update and a conditional jump.
+fn binop_then_jump<T, W, Op, In1, const SWAP: bool, const SET_FLAGS: bool>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus
+where
+    T: Tracer,
+    W: World<T>,
+    Op: Binop,
+    In1: Source,
+{
+    fused(
+        vm,
+        world,
+        tracer,
+        binop::<T, W, Op, In1, Register1, SWAP, SET_FLAGS>,
+        jump::<T, W, Immediate1>,
+    )
+}
+
+/// Storage read followed by `add` / `sub` with register operands.
+fn sload_then_binop<T, W, Op, const SWAP: bool, const SET_FLAGS: bool>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus
+where
+    T: Tracer,
+    W: World<T>,
+    Op: Binop,
+{
+    fused(
+        vm,
+        world,
+        tracer,
+        sload,
+        binop::<T, W, Op, Register1, Register1, SWAP, SET_FLAGS>,
+    )
+}
+
+/// Two stack pushes or pops performed by `add` without flags.
+fn stack_pair<T, W, In1, Out1, In2, Out2>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus
+where
+    T: Tracer,
+    W: World<T>,
+    In1: Source,
+    Out1: Destination,
+    In2: Source,
+    Out2: Destination,
+{
+    fused(
+        vm,
+        world,
+        tracer,
+        binop::<T, W, Add, In1, Out1, false, false>,
+        binop::<T, W, Add, In2, Out2, false, false>,
+    )
+}
+
+/// Two `nop`s, which are used to move the stack pointer.
+fn nop_pair<T: Tracer, W: World<T>>(
+    vm: &mut VirtualMachine<T, W>,
+    world: &mut W,
+    tracer: &mut T,
+) -> ExecutionStatus {
+    fused(vm, world, tracer, nop, nop)
+}
+
+macro_rules! match_immediate {
+    ([ $($types: tt)* ] $is_immediate: ident $next_matcher: ident $($rest: ident)*) => {
+        if $is_immediate {
+            $next_matcher!([$($types)* Immediate1] $($rest)*)
+        } else {
+            $next_matcher!([$($types)* Register1] $($rest)*)
+        }
+    };
+}
+
+/// Addressing mode of an operand, as far as fusion is concerned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OperandKind {
+    Register,
+    Immediate,
+    StackPushPop,
+    Other,
+}
+
+impl OperandKind {
+    fn new(operand: Operand) -> Self {
+        match operand {
+            RegOnly
+            | RegOrImm(RegOrImmFlags::UseRegOnly)
+            | Full(ImmMemHandlerFlags::UseRegOnly) => Self::Register,
+            RegOrImm(RegOrImmFlags::UseImm16Only) | Full(ImmMemHandlerFlags::UseImm16Only) => {
+                Self::Immediate
+            }
+            Full(ImmMemHandlerFlags::UseStackWithPushPop) => Self::StackPushPop,
+            Full(_) => Self::Other,
+        }
+    }
+}
+
+/// Instruction classified by the handler it is decoded to.
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    Arithmetic {
+        is_sub: bool,
+        source: OperandKind,
+        destination: OperandKind,
+        swap: bool,
+        set_flags: bool,
+    },
+    ImmediateJump,
+    StorageRead,
+    Nop,
+    Other,
+}
+
+impl Shape {
+    fn new(raw: u64) -> Self {
+        let (parsed, _) =
+            EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+        let variant = &parsed.variant;
+        let source = OperandKind::new(variant.src0_operand_type);
+        match variant.opcode {
+            Opcode::Add(_) | Opcode::Sub(_) => Self::Arithmetic {
+                is_sub: matches!(variant.opcode, Opcode::Sub(_)),
+                source,
+                destination: OperandKind::new(variant.dst0_operand_type),
+                swap: variant.flags[SWAP_OPERANDS_FLAG_IDX_FOR_ARITH_OPCODES],
+                set_flags: variant.flags[SET_FLAGS_FLAG_IDX],
+            },
+            Opcode::Jump(_) if source == OperandKind::Immediate => Self::ImmediateJump,
+            Opcode::Log(LogOpcode::StorageRead) => Self::StorageRead,
+            Opcode::Nop(_) => Self::Nop,
+            _ => Self::Other,
+        }
+    }
+
+    /// Returns `Some(true)` for `add` pushing a register to the stack, and `Some(false)` for `add` popping
+    /// the stack into a register.
+    fn stack_push(self) -> Option<bool> {
+        use OperandKind::{Register, StackPushPop};
+
+        match self {
+            Self::Arithmetic {
+                is_sub: false,
+                source,
+                destination,
+                swap: false,
+                set_flags: false,
+            } => match (source, destination) {
+                (Register, StackPushPop) => Some(true),
+                (StackPushPop, Register) => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns a superinstruction handler for the encoded instruction `first` followed by `second`,
+/// or `None` if the pair isn't fused.
+///
+/// Both instructions must decode successfully; the returned handler relies on `second` being decoded
+/// to the handler it calls.
+pub(crate) fn superinstruction<T: Tracer, W: World<T>>(
+    first: u64,
+    second: u64,
+) -> Option<Handler<T, W>> {
+    use OperandKind::{Immediate, Register};
+
+    let (first, second) = (Shape::new(first), Shape::new(second));
+    let handler: Handler<T, W> = match (first, second) {
+        (
+            Shape::Arithmetic {
+                is_sub,
+                source: source @ (Register | Immediate),
+                destination: Register,
+                swap,
+                set_flags,
+            },
+            Shape::ImmediateJump,
+        ) => {
+            let is_immediate = source == Immediate;
+            if is_sub {
+                monomorphize!(binop_then_jump [T W Sub] match_immediate is_immediate match_boolean swap match_boolean set_flags)
+            } else {
+                monomorphize!(binop_then_jump [T W Add] match_immediate is_immediate match_boolean swap match_boolean set_flags)
+            }
+        }
+        (
+            Shape::StorageRead,
+            Shape::Arithmetic {
+                is_sub,
+                source: Register,
+                destination: Register,
+                swap,
+                set_flags,
+            },
+        ) => {
+            if is_sub {
+                monomorphize!(sload_then_binop [T W Sub] match_boolean swap match_boolean set_flags)
+            } else {
+                monomorphize!(sload_then_binop [T W Add] match_boolean swap match_boolean set_flags)
+            }
+        }
+        (Shape::Nop, Shape::Nop) => nop_pair,
+        _ => match (first.stack_push()?, second.stack_push()?) {
+            (true, true) => {
+                stack_pair::<T, W, Register1, AdvanceStackPointer, Register1, AdvanceStackPointer>
+            }
+            (true, false) => {
+                stack_pair::<T, W, Register1, AdvanceStackPointer, AdvanceStackPointer, Register1>
+            }
+            (false, true) => {
+                stack_pair::<T, W, AdvanceStackPointer, Register1, Register1, AdvanceStackPointer>
+            }
+            (false, false) => {
+                stack_pair::<T, W, AdvanceStackPointer, Register1, AdvanceStackPointer, Register1>
+            }
+        },
+    };
+    Some(handler)
+}
@@ -0,0 +1,112 @@
+//! Minimal JSON serialization for tracer outputs, so that the crate doesn't need to depend on `serde`.
+//!
+//! Writing to a `String` never fails, so the results of `write!` are unwrapped.
+
+use std::fmt::Write as _;
+
+use primitive_types::{H160, U256};
+
+/// Writes a JSON object field by field.
+#[derive(Debug)]
+pub(crate) struct JsonObject<'a> {
+    out: &'a mut String,
+    has_fields: bool,
+}
+
+impl<'a> JsonObject<'a> {
+    pub(crate) fn new(out: &'a mut String) -> Self {
+        out.push('{');
+        Self {
+            out,
+            has_fields: false,
+        }
+    }
+
+    /// Writes the field name and returns the output, to which the field value must be written.
+    pub(crate) fn field(&mut self, name: &str) -> &mut String {
+        if self.has_fields {
+            self.out.push(',');
+        }
+        self.has_fields = true;
+        write_string(self.out, name);
+        self.out.push(':');
+        self.out
+    }
+
+    pub(crate) fn finish(self) {
+        self.out.push('}');
+    }
+}
+
+/// Writes a JSON array from the provided items.
+pub(crate) fn write_array<I: IntoIterator>(
+    out: &mut String,
+    items: I,
+    mut write_item: impl FnMut(&mut String, I::Item),
+) {
+    out.push('[');
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_item(out, item);
+    }
+    out.push(']');
+}
+
+pub(crate) fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if u32::from(ch) < 0x20 => {
+                write!(out, "\\u{:04x}", u32::from(ch)).unwrap();
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+pub(crate) fn write_bool(out: &mut String, value: bool) {
+    out.push_str(if value { "true" } else { "false" });
+}
+
+/// Writes bytes as a `0x`-prefixed hex string.
+pub(crate) fn write_bytes(out: &mut String, bytes: &[u8]) {
+    out.push_str("\"0x");
+    for byte in bytes {
+        write!(out, "{byte:02x}").unwrap();
+    }
+    out.push('"');
+}
+
+/// Writes a number as a `0x`-prefixed hex string without leading zeros, as is customary in Ethereum JSON-RPC.
+pub(crate) fn write_quantity(out: &mut String, value: impl Into<U256>) {
+    write!(out, "\"{:#x}\"", value.into()).unwrap();
+}
+
+/// Formats a 256-bit word as a `0x`-prefixed, zero-padded hex string, as is customary for storage keys and values.
+/// Unlike other helpers, this doesn't quote the output, so that it can be used as an object field name.
+pub(crate) fn word_to_hex(value: U256) -> String {
+    let mut bytes = [0; 32];
+    value.to_big_endian(&mut bytes);
+    let mut out = String::with_capacity(66);
+    out.push_str("0x");
+    for byte in bytes {
+        write!(out, "{byte:02x}").unwrap();
+    }
+    out
+}
+
+pub(crate) fn write_word(out: &mut String, value: U256) {
+    write_string(out, &word_to_hex(value));
+}
+
+pub(crate) fn write_address(out: &mut String, address: H160) {
+    write_bytes(out, address.as_bytes());
+}
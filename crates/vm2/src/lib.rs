@@ -6,50 +6,88 @@ use std::hash::{DefaultHasher, Hash, Hasher};
 
 use primitive_types::{H160, U256};
 pub use zksync_vm2_interface as interface;
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 // Re-export missing modules if single instruction testing is enabled
 #[cfg(feature = "single_instruction_test")]
 pub(crate) use self::single_instruction_test::{heap, program, stack};
 pub use self::{
+    assembler::{assemble, AssemblyError},
+    breakpoints::Breakpoint,
+    call_tracer::{Call, CallKind, CallTracer},
+    checkpoint::CheckpointError,
+    control_flow::{BasicBlock, ControlFlowGraph, Edge, EdgeKind},
+    decode::{InstructionError, ProgramError},
+    disassembler::{disassemble, DisassembledInstruction},
     fat_pointer::FatPointer,
+    gas_profiler::{ErgsStats, FrameProfile, GasProfiler},
     instruction::{ExecutionEnd, Instruction},
     mode_requirements::ModeRequirements,
     predication::Predicate,
+    prestate::{AccountState, PrestateDiff, SlotState},
     program::Program,
+    recording::{RecordingTracer, Replay},
+    revert_reason::RevertReason,
+    step_tracer::StepTracer,
     vm::{Settings, VirtualMachine},
+    watchpoints::{Watchpoint, WatchpointHit},
     world_diff::{Snapshot, StorageChange, StorageWriteEntry, WorldDiff},
 };
+#[cfg(not(feature = "single_instruction_test"))]
+pub use self::{
+    batch::{BatchExecutor, BatchWorld, TransactionResult},
+    program_cache::{CacheStats, CachingWorld, ProgramCache},
+};
 use crate::precompiles::{LegacyPrecompiles, Precompiles};
 
 pub mod addressing_modes;
+mod assembler;
+#[cfg(not(feature = "single_instruction_test"))]
+mod batch;
 #[cfg(not(feature = "single_instruction_test"))]
 mod bitset;
+// Breakpoints and checkpoints are unavailable with mocked heaps, stacks and programs
+#[cfg_attr(feature = "single_instruction_test", allow(dead_code))]
+mod breakpoints;
+mod call_tracer;
 mod callframe;
+#[cfg_attr(feature = "single_instruction_test", allow(dead_code))]
+mod checkpoint;
+mod control_flow;
 mod decode;
 mod decommit;
+mod disassembler;
 mod fat_pointer;
+mod gas_profiler;
 #[cfg(not(feature = "single_instruction_test"))]
 mod heap;
 mod instruction;
 mod instruction_handlers;
+mod json;
 mod mode_requirements;
 mod page_ids;
 pub mod precompiles;
 mod predication;
+mod prestate;
 #[cfg(not(feature = "single_instruction_test"))]
 mod program;
+#[cfg(not(feature = "single_instruction_test"))]
+mod program_cache;
+mod recording;
+mod revert_reason;
 mod rollback;
 #[cfg(feature = "single_instruction_test")]
 pub mod single_instruction_test;
 #[cfg(not(feature = "single_instruction_test"))]
 mod stack;
 mod state;
+mod step_tracer;
 pub mod testonly;
 #[cfg(all(test, not(feature = "single_instruction_test")))]
 mod tests;
 mod tracing;
 mod vm;
+mod watchpoints;
 mod world_diff;
 
 /// Storage slot information returned from [`StorageInterface::read_storage()`].
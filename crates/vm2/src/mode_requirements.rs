@@ -1,3 +1,5 @@
+use zksync_vm2_interface::v2::PanicReason;
+
 /// VM execution mode requirements (kernel only, not in static call) that can be placed on instructions.
 #[derive(Debug, Clone, Copy)]
 pub struct ModeRequirements(pub(crate) u8);
@@ -17,4 +19,14 @@ impl ModeRequirements {
         let enabled_modes = u8::from(is_kernel) | (u8::from(!is_static) << 1);
         enabled_modes & self.0 == self.0
     }
+
+    /// Returns the reason for the requirements not being [met](Self::met()). If both requirements are violated,
+    /// the kernel mode violation is reported.
+    pub(crate) fn violation(self, is_kernel: bool) -> PanicReason {
+        if self.0 & 1 != 0 && !is_kernel {
+            PanicReason::KernelViolation
+        } else {
+            PanicReason::StaticViolation
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use crate::checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder};
+
 const LT_BIT: u8 = 1;
 const EQ_BIT: u8 = 1 << 1;
 const GT_BIT: u8 = 1 << 2;
@@ -12,6 +14,20 @@ impl Flags {
     }
 }
 
+impl Checkpoint for Flags {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.0);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let bits = decoder.get::<u8>()?;
+        if bits & !(LT_BIT | EQ_BIT | GT_BIT) != ALWAYS_BIT {
+            return Err(CheckpointError::Malformed("invalid flags"));
+        }
+        Ok(Flags(bits))
+    }
+}
+
 /// Predicate for an instruction. Encoded so that comparing it to flags is efficient.
 #[derive(Copy, Clone, Debug, Default, Hash)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
@@ -42,9 +58,23 @@ impl Predicate {
         let bits = self as u8;
         bits & flags.0 != 0 && (bits >> 4) & flags.0 == 0
     }
+
+    /// Returns the suffix denoting this predicate in assembly mnemonics, e.g. `.gt`. Empty for [`Self::Always`].
+    pub(crate) fn assembly_suffix(self) -> &'static str {
+        match self {
+            Self::Always => "",
+            Self::IfGT => ".gt",
+            Self::IfEQ => ".eq",
+            Self::IfLT => ".lt",
+            Self::IfGE => ".ge",
+            Self::IfLE => ".le",
+            Self::IfNotEQ => ".ne",
+            Self::IfGTOrLT => ".gtlt",
+        }
+    }
 }
 
-#[cfg(feature = "single_instruction_test")]
+#[cfg(any(test, feature = "single_instruction_test"))]
 impl From<&Flags> for zk_evm::flags::Flags {
     fn from(flags: &Flags) -> Self {
         zk_evm::flags::Flags {
@@ -0,0 +1,133 @@
+//! State touched by VM execution, in a form close to the geth `prestateTracer` output.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use primitive_types::{H160, U256};
+
+use crate::json::{word_to_hex, write_array, write_word, JsonObject};
+
+/// Storage slot read or written during execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotState {
+    /// Value of the slot before execution, i.e. as provided by the [`World`](crate::World).
+    pub initial: U256,
+    /// Pending value written to the slot, or `None` if the slot was only read or all writes were rolled back.
+    /// May be equal to `initial`.
+    pub written: Option<U256>,
+}
+
+impl SlotState {
+    /// Returns the value of the slot after execution.
+    pub fn current(&self) -> U256 {
+        self.written.unwrap_or(self.initial)
+    }
+
+    /// Checks whether the value of the slot was changed by execution.
+    pub fn is_changed(&self) -> bool {
+        self.current() != self.initial
+    }
+}
+
+/// State of a single contract touched during execution.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountState {
+    /// Storage slots read or written, keyed by the slot key.
+    pub storage: BTreeMap<U256, SlotState>,
+    /// Non-zero transient storage slots, keyed by the slot key. Transient storage is always zero before execution.
+    pub transient_storage: BTreeMap<U256, U256>,
+}
+
+/// State touched by VM execution, as returned by [`WorldDiff::prestate_diff()`](crate::WorldDiff::prestate_diff()).
+///
+/// Contains enough data to replay the execution on top of a minimal state: every storage slot accessed
+/// (including accesses in reverted frames and the code info reads done by far calls) with its initial value,
+/// and every decommitted bytecode.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrestateDiff {
+    /// Touched contracts keyed by address.
+    pub accounts: BTreeMap<H160, AccountState>,
+    /// Hashes of decommitted bytecodes, including far-call decommits that ran out of gas
+    /// (same as [`WorldDiff::decommitted_hashes()`](crate::WorldDiff::decommitted_hashes())).
+    pub decommitted_hashes: BTreeSet<U256>,
+}
+
+impl PrestateDiff {
+    /// Serializes this diff to JSON, using a format close to the geth `prestateTracer` in the diff mode.
+    ///
+    /// The output is an object with the following fields:
+    ///
+    /// - `pre`: object mapping the address of each touched contract to `{"storage": {key: value}}` with initial values
+    ///   of all touched slots
+    /// - `post`: same for contracts changed by execution, with the values of changed slots after execution.
+    ///   Contracts with non-zero transient storage additionally have a `transientStorage` field.
+    /// - `decommittedHashes`: array of decommitted bytecode hashes
+    ///
+    /// Addresses, keys, values and hashes are 0x-prefixed hex strings, with numbers padded to 32 bytes.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let mut object = JsonObject::new(&mut out);
+
+        let mut pre = JsonObject::new(object.field("pre"));
+        for (address, account) in &self.accounts {
+            if account.storage.is_empty() {
+                continue;
+            }
+            let mut account_object = JsonObject::new(pre.field(&format!("{address:?}")));
+            write_slots(
+                account_object.field("storage"),
+                account
+                    .storage
+                    .iter()
+                    .map(|(&key, slot)| (key, slot.initial)),
+            );
+            account_object.finish();
+        }
+        pre.finish();
+
+        let mut post = JsonObject::new(object.field("post"));
+        for (address, account) in &self.accounts {
+            let has_changes = account.storage.values().any(SlotState::is_changed);
+            if !has_changes && account.transient_storage.is_empty() {
+                continue;
+            }
+            let mut account_object = JsonObject::new(post.field(&format!("{address:?}")));
+            if has_changes {
+                write_slots(
+                    account_object.field("storage"),
+                    account
+                        .storage
+                        .iter()
+                        .filter(|(_, slot)| slot.is_changed())
+                        .map(|(&key, slot)| (key, slot.current())),
+                );
+            }
+            if !account.transient_storage.is_empty() {
+                write_slots(
+                    account_object.field("transientStorage"),
+                    account
+                        .transient_storage
+                        .iter()
+                        .map(|(&key, &value)| (key, value)),
+                );
+            }
+            account_object.finish();
+        }
+        post.finish();
+
+        write_array(
+            object.field("decommittedHashes"),
+            &self.decommitted_hashes,
+            |out, &hash| write_word(out, hash),
+        );
+        object.finish();
+        out
+    }
+}
+
+fn write_slots(out: &mut String, slots: impl Iterator<Item = (U256, U256)>) {
+    let mut object = JsonObject::new(out);
+    for (key, value) in slots {
+        write_word(object.field(&word_to_hex(key)), value);
+    }
+    object.finish();
+}
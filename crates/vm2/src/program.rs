@@ -1,22 +1,73 @@
-use std::{fmt, sync::Arc};
+use std::{collections::BTreeSet, fmt, mem, sync::Arc};
 
 use primitive_types::U256;
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use crate::{
-    addressing_modes::Arguments, decode::decode, hash_for_debugging, instruction::ExecutionStatus,
+    addressing_modes::Arguments,
+    breakpoints::breakpoint,
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
+    decode::{decode, try_decode, ProgramError},
+    hash_for_debugging,
+    instruction::{ExecutionStatus, Handler},
+    instruction_handlers::superinstruction,
     Instruction, ModeRequirements, Predicate, VirtualMachine, World,
 };
 
 /// Compiled EraVM bytecode.
 ///
-/// Cloning this is cheap. It is a handle to memory similar to [`Arc`].
+/// Cloning this is cheap. It is a handle to memory similar to [`Arc`]. Programs are [`Send`] and [`Sync`],
+/// so they can be shared across threads.
 pub struct Program<T, W> {
     // An internal representation that doesn't need two Arcs would be better
     // but it would also require a lot of unsafe, so I made this wrapper to
     // enable changing the internals later.
     code_page: Arc<[U256]>,
     instructions: Arc<[Instruction<T, W>]>,
+    source: ProgramSource,
+    /// Instructions before [breakpoints](crate::Breakpoint) were patched into `instructions`, if any.
+    unpatched_instructions: Option<Arc<[Instruction<T, W>]>>,
+}
+
+const _: () = {
+    const fn assert_send_sync<V: Send + Sync>() {}
+    const fn assert_program_is_send_sync<T, W>() {
+        assert_send_sync::<Program<T, W>>();
+    }
+    assert_program_is_send_sync::<(), ()>();
+};
+
+/// Origin of a [`Program`]. Used to re-supply programs when restoring VM checkpoints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ProgramSource {
+    /// Created by the VM user; e.g., the bootloader program.
+    Unknown,
+    /// Bytecode with the specified hash, which can be obtained from [`World::decommit()`].
+    Decommitted(U256),
+    /// Placeholder program run by a frame whose far call has failed.
+    Panicking,
+}
+
+impl Checkpoint for ProgramSource {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Self::Unknown => encoder.put(&0_u8),
+            Self::Decommitted(code_hash) => {
+                encoder.put(&1_u8);
+                encoder.put(code_hash);
+            }
+            Self::Panicking => encoder.put(&2_u8),
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        match decoder.get::<u8>()? {
+            0 => Ok(Self::Unknown),
+            1 => Ok(Self::Decommitted(decoder.get()?)),
+            2 => Ok(Self::Panicking),
+            _ => Err(CheckpointError::Malformed("invalid program source")),
+        }
+    }
 }
 
 impl<T, W> Clone for Program<T, W> {
@@ -24,6 +75,8 @@ impl<T, W> Clone for Program<T, W> {
         Self {
             code_page: self.code_page.clone(),
             instructions: self.instructions.clone(),
+            source: self.source,
+            unpatched_instructions: self.unpatched_instructions.clone(),
         }
     }
 }
@@ -53,15 +106,27 @@ impl<T, W> fmt::Debug for Program<T, W> {
 
 impl<T: Tracer, W: World<T>> Program<T, W> {
     /// Creates a new program.
-    #[allow(clippy::missing_panics_doc)] // false positive
     pub fn new(bytecode: &[u8], enable_hooks: bool) -> Self {
-        let instructions = decode_program(
-            &bytecode
-                .chunks_exact(8)
-                .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
-                .collect::<Vec<_>>(),
-            enable_hooks,
-        );
+        Self::new_with_fusion(bytecode, enable_hooks, false)
+    }
+
+    /// Creates a new program, optionally fusing frequent instruction pairs (e.g., `add` or `sub` followed by a jump,
+    /// a storage read followed by arithmetic, or adjacent stack pushes and pops) into superinstructions.
+    ///
+    /// A superinstruction executes both instructions in a single dispatch when the program is [run](VirtualMachine::run()).
+    /// Gas, predicates, tracer hooks and panics are still handled for each instruction separately, so execution
+    /// is observably the same as without fusion. Other execution methods (e.g., [`VirtualMachine::step()`]) and programs
+    /// with [breakpoints](crate::Breakpoint) execute superinstructions one instruction at a time.
+    #[allow(clippy::missing_panics_doc)] // false positive
+    pub fn new_with_fusion(bytecode: &[u8], enable_hooks: bool, fuse_instructions: bool) -> Self {
+        let raw = bytecode
+            .chunks_exact(8)
+            .map(|chunk| u64::from_be_bytes(chunk.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        let mut instructions = decode_program(&raw, enable_hooks);
+        if fuse_instructions {
+            fuse_superinstructions(&mut instructions, &raw);
+        }
         let code_page = bytecode
             .chunks_exact(32)
             .map(U256::from_big_endian)
@@ -69,26 +134,117 @@ impl<T: Tracer, W: World<T>> Program<T, W> {
         Self {
             instructions: instructions.into(),
             code_page: code_page.into(),
+            source: ProgramSource::Unknown,
+            unpatched_instructions: None,
         }
     }
 
     /// Creates a new program from `U256` words.
     pub fn from_words(bytecode_words: Vec<U256>, enable_hooks: bool) -> Self {
-        let instructions = decode_program(
-            &bytecode_words
-                .iter()
-                .flat_map(|x| x.0.into_iter().rev())
-                .collect::<Vec<_>>(),
-            enable_hooks,
-        );
+        Self::from_words_with_fusion(bytecode_words, enable_hooks, false)
+    }
+
+    /// Creates a new program from `U256` words, optionally fusing instruction pairs into superinstructions
+    /// as described in [`Self::new_with_fusion()`].
+    pub fn from_words_with_fusion(
+        bytecode_words: Vec<U256>,
+        enable_hooks: bool,
+        fuse_instructions: bool,
+    ) -> Self {
+        let raw = raw_instructions(&bytecode_words);
+        let mut instructions = decode_program(&raw, enable_hooks);
+        if fuse_instructions {
+            fuse_superinstructions(&mut instructions, &raw);
+        }
         Self {
             instructions: instructions.into(),
             code_page: bytecode_words.into(),
+            source: ProgramSource::Unknown,
+            unpatched_instructions: None,
+        }
+    }
+
+    /// Creates a new program, validating the bytecode instead of panicking or silently ignoring malformed data
+    /// like [`Self::new()`] does.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytecode length is not a multiple of 32 bytes, or if the bytecode is invalid
+    /// as described in [`Self::try_from_words()`].
+    pub fn try_new(bytecode: &[u8], enable_hooks: bool) -> Result<Self, ProgramError> {
+        Self::try_new_with_fusion(bytecode, enable_hooks, false)
+    }
+
+    /// Fallible counterpart of [`Self::new_with_fusion()`]; see [`Self::try_new()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::try_new()`].
+    pub fn try_new_with_fusion(
+        bytecode: &[u8],
+        enable_hooks: bool,
+        fuse_instructions: bool,
+    ) -> Result<Self, ProgramError> {
+        if bytecode.len() % 32 != 0 {
+            return Err(ProgramError::UnalignedLength(bytecode.len()));
+        }
+        let bytecode_words = bytecode
+            .chunks_exact(32)
+            .map(U256::from_big_endian)
+            .collect();
+        Self::try_from_words_with_fusion(bytecode_words, enable_hooks, fuse_instructions)
+    }
+
+    /// Creates a new program from `U256` words, validating the bytecode instead of panicking like
+    /// [`Self::from_words()`] does.
+    ///
+    /// Instructions are decoded with the same rules as in `from_words()`, which panics on the instructions rejected
+    /// by this method. In particular, jump targets and exception handlers past the end of the program are allowed
+    /// since the VM panics only if they are jumped to; use
+    /// [`ControlFlowGraph::out_of_range_edges()`](crate::ControlFlowGraph::out_of_range_edges()) to lint for them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytecode is empty or has more than `u16::MAX` words, or if any instruction
+    /// has an unsupported addressing mode (e.g., writes to an immediate or the code page).
+    pub fn try_from_words(
+        bytecode_words: Vec<U256>,
+        enable_hooks: bool,
+    ) -> Result<Self, ProgramError> {
+        Self::try_from_words_with_fusion(bytecode_words, enable_hooks, false)
+    }
+
+    /// Fallible counterpart of [`Self::from_words_with_fusion()`]; see [`Self::try_from_words()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::try_from_words()`].
+    pub fn try_from_words_with_fusion(
+        bytecode_words: Vec<U256>,
+        enable_hooks: bool,
+        fuse_instructions: bool,
+    ) -> Result<Self, ProgramError> {
+        if bytecode_words.is_empty() || bytecode_words.len() > usize::from(u16::MAX) {
+            return Err(ProgramError::WordCountOutOfRange(bytecode_words.len()));
         }
+        let raw = raw_instructions(&bytecode_words);
+        let mut instructions = try_decode_program(&raw, enable_hooks)?;
+        if fuse_instructions {
+            fuse_superinstructions(&mut instructions, &raw);
+        }
+        Ok(Self {
+            instructions: instructions.into(),
+            code_page: bytecode_words.into(),
+            source: ProgramSource::Unknown,
+            unpatched_instructions: None,
+        })
     }
 
     pub(crate) fn new_panicking() -> Self {
-        Self::from_raw(vec![Instruction::from_spontaneous_panic()], vec![])
+        Self {
+            source: ProgramSource::Panicking,
+            ..Self::from_raw(vec![Instruction::from_spontaneous_panic()], vec![])
+        }
     }
 
     #[doc(hidden)] // should only be used in low-level tests / benchmarks
@@ -96,6 +252,46 @@ impl<T: Tracer, W: World<T>> Program<T, W> {
         Self {
             instructions: instructions.into(),
             code_page: code_page.into(),
+            source: ProgramSource::Unknown,
+            unpatched_instructions: None,
+        }
+    }
+
+    /// Returns a copy of this program with [breakpoints](crate::Breakpoint) at the specified instructions,
+    /// replacing previously patched breakpoints.
+    pub(crate) fn with_breakpoints(&self, pcs: &BTreeSet<u16>) -> Self {
+        let unpatched = self
+            .unpatched_instructions
+            .as_ref()
+            .unwrap_or(&self.instructions);
+        if pcs.is_empty() {
+            return Self {
+                instructions: unpatched.clone(),
+                unpatched_instructions: None,
+                ..self.clone()
+            };
+        }
+
+        let instructions = unpatched
+            .iter()
+            .enumerate()
+            .map(|(i, instruction)| {
+                let has_breakpoint = u16::try_from(i).is_ok_and(|pc| pcs.contains(&pc));
+                Instruction {
+                    handler: if has_breakpoint {
+                        breakpoint
+                    } else {
+                        instruction.handler
+                    },
+                    arguments: instruction.arguments,
+                }
+            })
+            .collect::<Vec<_>>();
+        Self {
+            code_page: self.code_page.clone(),
+            instructions: instructions.into(),
+            source: self.source,
+            unpatched_instructions: Some(unpatched.clone()),
         }
     }
 }
@@ -109,6 +305,56 @@ impl<T, W> Program<T, W> {
     pub fn code_page(&self) -> &[U256] {
         &self.code_page
     }
+
+    pub(crate) fn source(&self) -> ProgramSource {
+        self.source
+    }
+
+    /// Marks this program as the bytecode with the specified hash. Programs [decommitted](World::decommit())
+    /// by the VM are marked automatically; marking the initial (bootloader) program allows
+    /// setting [breakpoints](crate::Breakpoint) in it.
+    #[must_use]
+    pub fn with_code_hash(mut self, code_hash: U256) -> Self {
+        self.source = ProgramSource::Decommitted(code_hash);
+        self
+    }
+
+    pub(crate) fn code_hash(&self) -> Option<U256> {
+        match self.source {
+            ProgramSource::Decommitted(code_hash) => Some(code_hash),
+            ProgramSource::Unknown | ProgramSource::Panicking => None,
+        }
+    }
+
+    pub(crate) fn has_breakpoints(&self) -> bool {
+        self.unpatched_instructions.is_some()
+    }
+
+    /// Translates a pointer to an instruction of `other` into a pointer to the corresponding instruction
+    /// of this program, which must have the same instructions. Pointers outside `other` (e.g., to the invalid instruction)
+    /// are returned unchanged.
+    pub(crate) fn translate_pc(
+        &self,
+        other: &Self,
+        pc: *const Instruction<T, W>,
+    ) -> *const Instruction<T, W> {
+        let other_range = other.instructions.as_ptr_range();
+        if !other_range.contains(&pc) {
+            return pc;
+        }
+        let index =
+            (pc as usize - other_range.start as usize) / mem::size_of::<Instruction<T, W>>();
+        &self.instructions[index]
+    }
+
+    /// Returns the handler the instruction at `pc` had before breakpoints were patched in.
+    pub(crate) fn original_handler(&self, pc: u16) -> Handler<T, W> {
+        let instructions = self
+            .unpatched_instructions
+            .as_ref()
+            .unwrap_or(&self.instructions);
+        instructions[usize::from(pc)].handler
+    }
 }
 
 // This implementation compares pointers instead of programs.
@@ -142,6 +388,22 @@ fn jump_to_beginning_handler<T, W>(
     ExecutionStatus::Running
 }
 
+fn raw_instructions(bytecode_words: &[U256]) -> Vec<u64> {
+    bytecode_words
+        .iter()
+        .flat_map(|x| x.0.into_iter().rev())
+        .collect()
+}
+
+/// Instruction placed after the last instruction of a program.
+fn program_end<T: Tracer, W: World<T>>(instruction_count: usize) -> Instruction<T, W> {
+    if instruction_count >= 1 << 16 {
+        jump_to_beginning()
+    } else {
+        Instruction::from_invalid()
+    }
+}
+
 fn decode_program<T: Tracer, W: World<T>>(
     raw: &[u64],
     is_bootloader: bool,
@@ -149,10 +411,34 @@ fn decode_program<T: Tracer, W: World<T>>(
     raw.iter()
         .take(1 << 16)
         .map(|i| decode(*i, is_bootloader))
-        .chain(std::iter::once(if raw.len() >= 1 << 16 {
-            jump_to_beginning()
-        } else {
-            Instruction::from_invalid()
-        }))
+        .chain(std::iter::once(program_end(raw.len())))
+        .collect()
+}
+
+/// Replaces handlers of the first instructions in fusible pairs with superinstructions.
+fn fuse_superinstructions<T: Tracer, W: World<T>>(
+    instructions: &mut [Instruction<T, W>],
+    raw: &[u64],
+) {
+    let raw = &raw[..raw.len().min(1 << 16)];
+    for (instruction, pair) in instructions.iter_mut().zip(raw.windows(2)) {
+        if let Some(handler) = superinstruction(pair[0], pair[1]) {
+            instruction.handler = handler;
+        }
+    }
+}
+
+fn try_decode_program<T: Tracer, W: World<T>>(
+    raw: &[u64],
+    is_bootloader: bool,
+) -> Result<Vec<Instruction<T, W>>, ProgramError> {
+    raw.iter()
+        .take(1 << 16)
+        .enumerate()
+        .map(|(index, &raw)| {
+            try_decode(raw, is_bootloader)
+                .map_err(|reason| ProgramError::InvalidInstruction { index, reason })
+        })
+        .chain(std::iter::once(Ok(program_end(raw.len()))))
         .collect()
 }
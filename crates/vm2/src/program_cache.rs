@@ -0,0 +1,310 @@
+//! Caching of decoded programs for [`World`] implementations.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::v2::Tracer;
+
+use crate::{precompiles::Precompiles, Program, StorageInterface, StorageSlot, World};
+
+/// Statistics of a [`ProgramCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of lookups that returned a cached program.
+    pub hits: u64,
+    /// Number of lookups that had to decode the program.
+    pub misses: u64,
+    /// Number of programs evicted to keep the cache within its capacity.
+    pub evictions: u64,
+}
+
+#[derive(Debug)]
+struct CacheEntry<T, W> {
+    program: Program<T, W>,
+    last_used: u64,
+}
+
+#[derive(Debug)]
+struct CacheState<T, W> {
+    entries: HashMap<U256, CacheEntry<T, W>>,
+    /// Bytecode hashes ordered by the last use of their entries.
+    recency: BTreeMap<u64, U256>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl<T, W> CacheState<T, W> {
+    /// Advances the clock and marks the program with the specified hash as used, returning it if it's cached.
+    fn touch(&mut self, hash: U256) -> Option<Program<T, W>> {
+        self.clock += 1;
+        let now = self.clock;
+        let entry = self.entries.get_mut(&hash)?;
+        let last_used = entry.last_used;
+        entry.last_used = now;
+        let program = entry.program.clone();
+        self.recency.remove(&last_used);
+        self.recency.insert(now, hash);
+        Some(program)
+    }
+}
+
+/// Bounded least-recently-used cache of decoded [`Program`]s keyed by bytecode hash.
+///
+/// The cache can be shared across VM instances and threads (e.g., by wrapping it in an [`Arc`]); programs are cheap
+/// to clone handles, so all users of the cache share the decoded instructions.
+pub struct ProgramCache<T, W> {
+    capacity: usize,
+    state: Mutex<CacheState<T, W>>,
+}
+
+impl<T, W> fmt::Debug for ProgramCache<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ProgramCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, W> ProgramCache<T, W> {
+    /// Creates an empty cache holding at most `capacity` programs.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                recency: BTreeMap::new(),
+                clock: 0,
+                stats: CacheStats::default(),
+            }),
+        }
+    }
+
+    /// Returns the maximum number of cached programs.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached programs.
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    /// Checks whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns hit / miss statistics accumulated since the cache was created.
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats
+    }
+
+    /// Returns the program with the specified bytecode hash, calling `decode` to obtain it if it isn't cached.
+    ///
+    /// `decode` is called without the cache locked, so lookups of other programs aren't blocked while a program
+    /// is decoded. Concurrent lookups of the same uncached hash may thus decode it several times; only
+    /// the first decoded program is cached and returned to all of them.
+    pub fn get_or_decode(
+        &self,
+        hash: U256,
+        decode: impl FnOnce() -> Program<T, W>,
+    ) -> Program<T, W> {
+        {
+            let mut state = self.lock();
+            if let Some(program) = state.touch(hash) {
+                state.stats.hits += 1;
+                return program;
+            }
+        }
+
+        let program = decode();
+        let mut state = self.lock();
+        state.stats.misses += 1;
+        if let Some(program) = state.touch(hash) {
+            // Another lookup has decoded and cached the program in the meantime.
+            return program;
+        }
+        if self.capacity == 0 {
+            return program;
+        }
+        if state.entries.len() >= self.capacity {
+            if let Some((_, evicted_hash)) = state.recency.pop_first() {
+                state.entries.remove(&evicted_hash);
+                state.stats.evictions += 1;
+            }
+        }
+        let now = state.clock;
+        state.entries.insert(
+            hash,
+            CacheEntry {
+                program: program.clone(),
+                last_used: now,
+            },
+        );
+        state.recency.insert(now, hash);
+        program
+    }
+
+    /// Removes all cached programs. Statistics are retained.
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState<T, W>> {
+        // The cache state is consistent between statements, so it's safe to use after a panic while it's locked.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Tracer, W: World<T>> ProgramCache<T, W> {
+    /// Returns the program with the specified bytecode hash, decoding the bytecode returned by `bytecode`
+    /// with [`Program::from_words_with_fusion()`] if it isn't cached. All worlds caching programs decode them
+    /// using this method, so that they execute bytecode the same.
+    pub(crate) fn get_or_decode_bytecode(
+        &self,
+        hash: U256,
+        enable_hooks: bool,
+        fuse_instructions: bool,
+        bytecode: impl FnOnce() -> Vec<u8>,
+    ) -> Program<T, W> {
+        self.get_or_decode(hash, || {
+            let bytecode_words = bytecode()
+                .chunks_exact(32)
+                .map(U256::from_big_endian)
+                .collect();
+            Program::from_words_with_fusion(bytecode_words, enable_hooks, fuse_instructions)
+        })
+    }
+}
+
+/// [`World`] wrapper decoding programs [decommitted](World::decommit()) by the VM at most once per bytecode hash.
+///
+/// Bytecodes are obtained from [`World::decommit_code()`] of the inner world and decoded with
+/// [`Program::from_words_with_fusion()`], so programs execute exactly as if the inner world decoded them
+/// with [`Program::from_words()`]. In particular, decoding panics on bytecode `from_words()` panics on;
+/// such bytecode can be detected beforehand with [`Program::try_from_words()`]. Hooks and superinstructions
+/// are disabled unless enabled with [`Self::with_hooks()`] and [`Self::with_fusion()`] respectively.
+/// All other [`World`] and [`StorageInterface`] methods are delegated to the inner world.
+///
+/// Several worlds (e.g., used by VMs running on different threads) can share a single [`ProgramCache`]
+/// by creating them with [`Self::with_cache()`].
+pub struct CachingWorld<T, W> {
+    inner: W,
+    cache: Arc<ProgramCache<T, Self>>,
+    enable_hooks: bool,
+    fuse_instructions: bool,
+}
+
+impl<T, W: fmt::Debug> fmt::Debug for CachingWorld<T, W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("CachingWorld")
+            .field("inner", &self.inner)
+            .field("cache", &self.cache)
+            .field("enable_hooks", &self.enable_hooks)
+            .field("fuse_instructions", &self.fuse_instructions)
+            .finish()
+    }
+}
+
+impl<T, W> CachingWorld<T, W> {
+    /// Wraps the specified world, caching at most `capacity` programs.
+    pub fn new(inner: W, capacity: usize) -> Self {
+        Self::with_cache(inner, Arc::new(ProgramCache::new(capacity)))
+    }
+
+    /// Wraps the specified world using a (potentially shared) program cache.
+    pub fn with_cache(inner: W, cache: Arc<ProgramCache<T, Self>>) -> Self {
+        Self {
+            inner,
+            cache,
+            enable_hooks: false,
+            fuse_instructions: false,
+        }
+    }
+
+    /// Sets whether hooks are enabled for decoded programs (disabled by default). Hooks only apply to the bootloader,
+    /// so they should only be enabled if the bootloader is decommitted through this world.
+    ///
+    /// Programs already in a shared cache are not affected, so all worlds sharing a cache should use the same setting.
+    #[must_use]
+    pub fn with_hooks(mut self, enable_hooks: bool) -> Self {
+        self.enable_hooks = enable_hooks;
+        self
+    }
+
+    /// Sets whether decoded programs fuse instruction pairs into superinstructions (disabled by default).
+    /// See [`Program::new_with_fusion()`] for details.
+    ///
+    /// Programs already in a shared cache are not affected, so all worlds sharing a cache should use the same setting.
+    #[must_use]
+    pub fn with_fusion(mut self, fuse_instructions: bool) -> Self {
+        self.fuse_instructions = fuse_instructions;
+        self
+    }
+
+    /// Returns the program cache used by this world.
+    pub fn cache(&self) -> &Arc<ProgramCache<T, Self>> {
+        &self.cache
+    }
+
+    /// Returns a reference to the inner world.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner world.
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps the inner world.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<T, W: StorageInterface> StorageInterface for CachingWorld<T, W> {
+    fn read_storage(&mut self, contract: H160, key: U256) -> StorageSlot {
+        self.inner.read_storage(contract, key)
+    }
+
+    fn read_storage_value(&mut self, contract: H160, key: U256) -> U256 {
+        self.inner.read_storage_value(contract, key)
+    }
+
+    fn cost_of_writing_storage(&mut self, initial_slot: StorageSlot, new_value: U256) -> u32 {
+        self.inner.cost_of_writing_storage(initial_slot, new_value)
+    }
+
+    fn is_free_storage_slot(&self, contract: &H160, key: &U256) -> bool {
+        self.inner.is_free_storage_slot(contract, key)
+    }
+}
+
+impl<T: Tracer, W: World<T>> World<T> for CachingWorld<T, W> {
+    fn decommit(&mut self, hash: U256) -> Program<T, Self> {
+        let inner = &mut self.inner;
+        self.cache
+            .get_or_decode_bytecode(hash, self.enable_hooks, self.fuse_instructions, || {
+                inner.decommit_code(hash)
+            })
+    }
+
+    fn decommit_code(&mut self, hash: U256) -> Vec<u8> {
+        self.inner.decommit_code(hash)
+    }
+
+    fn precompiles(&self) -> &impl Precompiles {
+        self.inner.precompiles()
+    }
+}
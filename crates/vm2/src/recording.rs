@@ -0,0 +1,817 @@
+//! Recording of VM execution and its time-travel replay.
+
+use std::{collections::BTreeMap, iter};
+
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::{
+    decoding::{EncodingModeProduction, VmEncodingMode},
+    ImmMemHandlerFlags, Operand,
+};
+use zksync_vm2_interface::{
+    v2::{HeapAccess, Tracer},
+    CallframeInterface, Event, Flags, GlobalStateInterface, HeapId, L2ToL1Log, Opcode, OpcodeType,
+    ShouldStop, StateInterface,
+};
+
+/// [`Tracer`] recording the state changes made by each executed instruction. Once execution is over,
+/// the recording can be turned into a [`Replay`] to move back and forth through it.
+///
+/// For each instruction, the tracer records the minimal state delta: written registers and flags,
+/// changes of the program counter, stack pointer and gas of the current frame, written stack slots,
+/// heap writes, and pushed / popped frames. Deltas are computed by comparing the VM state after the instruction
+/// with the state before it, so the tracer makes execution considerably slower; it is meant for debugging.
+///
+/// # Limitations
+///
+/// - Stack slots above the stack pointer are only compared if they are the destination of an instruction
+///   using absolute stack addressing, which is found by decoding the executed instruction from the contract bytecode.
+///   Thus, such writes are not recorded for programs that aren't created from bytecode (i.e., ones created with
+///   [`Program::from_raw()`](crate::Program::from_raw())) unless they have a code page covering these instructions.
+/// - Heap writes are recorded from [heap access events](Tracer::on_heap_access()), so writes by precompiles
+///   and by the VM itself are not recorded. Heap words are captured when they are first read; a word written
+///   before being read is assumed to be zero before the write, which holds for heaps allocated during the recording.
+/// - Storage, transient storage, events and L2-to-L1 logs are not recorded.
+#[derive(Debug, Default)]
+pub struct RecordingTracer {
+    state: Option<RecordedState>,
+    steps: Vec<Step>,
+    pending_heap_access: Option<HeapAccess>,
+    pending_stack_write: Option<(u16, (U256, bool))>,
+}
+
+impl RecordingTracer {
+    /// Creates a tracer with an empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Converts the recording into a replay positioned after the last recorded instruction.
+    /// Returns `None` if no instructions were executed with this tracer.
+    pub fn into_replay(self) -> Option<Replay> {
+        Some(Replay {
+            position: self.steps.len(),
+            steps: self.steps,
+            state: self.state?,
+        })
+    }
+}
+
+impl Tracer for RecordingTracer {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if self.state.is_none() {
+            self.state = Some(RecordedState::capture(state));
+        }
+        self.pending_stack_write = absolute_stack_destination(state)
+            .map(|slot| (slot, state.current_frame().read_stack(slot)));
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        if let Some(recorded_state) = &mut self.state {
+            let heap_write = self
+                .pending_heap_access
+                .take()
+                .and_then(|access| recorded_state.observe_heap_access(access, &*state));
+            let stack_write = self.pending_stack_write.take();
+            let step = recorded_state.record(OP::VALUE, state, heap_write, stack_write);
+            self.steps.push(step);
+        }
+        ShouldStop::Continue
+    }
+
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        self.pending_heap_access = Some(access);
+    }
+}
+
+/// Returns the stack slot written by the instruction about to be executed, provided that its destination
+/// uses absolute stack addressing. Unlike other stack writes, such a slot can be above the stack pointer.
+#[allow(clippy::cast_possible_truncation)] // stack addresses are computed modulo 2^16
+fn absolute_stack_destination(state: &mut impl StateInterface) -> Option<u16> {
+    let (pc, raw_word) = {
+        let frame = state.current_frame();
+        let pc = frame.program_counter()?;
+        (pc, frame.read_contract_code(pc / 4))
+    };
+
+    // Instructions are packed into code words in the big-endian order
+    let raw = raw_word.0[3 - usize::from(pc % 4)];
+    let (parsed, _) = EncodingModeProduction::parse_preliminary_variant_and_absolute_number(raw);
+    if !matches!(
+        parsed.variant.dst0_operand_type,
+        Operand::Full(ImmMemHandlerFlags::UseAbsoluteOnStack)
+    ) {
+        return None;
+    }
+    let register = state.read_register(parsed.dst0_reg_idx).0;
+    Some((register.low_u32() as u16).wrapping_add(parsed.imm_1))
+}
+
+/// Replay of a [recording](RecordingTracer) that can be moved forward and backward by instructions.
+///
+/// The replay implements [`StateInterface`], which provides the VM state at the current position (i.e., after
+/// the number of instructions returned by [`Self::position()`] were executed). The view is limited to what
+/// the tracer records: heap bytes that are neither read nor written during the recording read as zero, contract code reads
+/// as zero, and storage, transient storage, events and L2-to-L1 logs are always empty. The state can be modified
+/// via the interface; modifications are kept until they are overwritten by the replayed instructions.
+#[derive(Debug)]
+pub struct Replay {
+    steps: Vec<Step>,
+    position: usize,
+    state: RecordedState,
+}
+
+impl Replay {
+    /// Returns the number of recorded instructions.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Checks whether no instructions were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Returns the number of instructions executed to reach the current state.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the opcode of the last executed instruction, or `None` at the start of the recording.
+    pub fn previous_opcode(&self) -> Option<Opcode> {
+        let index = self.position.checked_sub(1)?;
+        Some(self.steps[index].opcode)
+    }
+
+    /// Returns the opcode of the next instruction to be executed, or `None` at the end of the recording.
+    pub fn next_opcode(&self) -> Option<Opcode> {
+        self.steps.get(self.position).map(|step| step.opcode)
+    }
+
+    /// Executes the next instruction. Returns `false` if the replay is at the end of the recording.
+    pub fn step_forward(&mut self) -> bool {
+        let Some(step) = self.steps.get(self.position) else {
+            return false;
+        };
+        self.state.apply(step, true);
+        self.position += 1;
+        true
+    }
+
+    /// Reverts the last executed instruction. Returns `false` if the replay is at the start of the recording.
+    pub fn step_backward(&mut self) -> bool {
+        let Some(index) = self.position.checked_sub(1) else {
+            return false;
+        };
+        self.state.apply(&self.steps[index], false);
+        self.position = index;
+        true
+    }
+
+    /// Moves to the specified position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` exceeds the [number of recorded instructions](Self::len()).
+    pub fn seek(&mut self, position: usize) {
+        assert!(
+            position <= self.steps.len(),
+            "position {position} is out of bounds for a recording of {} instructions",
+            self.steps.len()
+        );
+        while self.position < position {
+            self.step_forward();
+        }
+        while self.position > position {
+            self.step_backward();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Change<V> {
+    before: V,
+    after: V,
+}
+
+impl<V: Copy> Change<V> {
+    fn get(&self, forward: bool) -> V {
+        if forward {
+            self.after
+        } else {
+            self.before
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HeapWrite {
+    heap: HeapId,
+    offset: u32,
+    value: Change<U256>,
+}
+
+/// State delta caused by a single instruction.
+#[derive(Debug)]
+struct Step {
+    opcode: Opcode,
+    registers: Vec<(u8, Change<(U256, bool)>)>,
+    flags: Option<Change<Flags>>,
+    globals: Option<Change<Globals>>,
+    heap_write: Option<HeapWrite>,
+    frames: FramesDelta,
+}
+
+#[derive(Debug)]
+enum FramesDelta {
+    /// Only the program counter, stack pointer, gas and stack slots of the current frame have changed.
+    Current {
+        pc: Change<Option<u16>>,
+        sp: Change<u16>,
+        gas: Change<u32>,
+        stack: Vec<(u16, Change<(U256, bool)>)>,
+    },
+    /// `removed` frames on top of the callstack were replaced with `added` frames.
+    Callstack {
+        removed: Vec<Frame>,
+        added: Vec<Frame>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Globals {
+    transaction_number: u16,
+    context_u128: u128,
+    pubdata: i32,
+}
+
+impl Globals {
+    fn capture(state: &impl StateInterface) -> Self {
+        Self {
+            transaction_number: state.transaction_number(),
+            context_u128: state.context_u128_register(),
+            pubdata: state.pubdata(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FrameView {
+    address: H160,
+    code_address: H160,
+    caller: H160,
+    pc: Option<u16>,
+    exception_handler: u16,
+    is_static: bool,
+    is_kernel: bool,
+    is_near_call: bool,
+    gas: u32,
+    context_u128: u128,
+    sp: u16,
+    heap: HeapId,
+    heap_bound: u32,
+    aux_heap: HeapId,
+    aux_heap_bound: u32,
+}
+
+impl FrameView {
+    fn capture(frame: &impl CallframeInterface) -> Self {
+        Self {
+            address: frame.address(),
+            code_address: frame.code_address(),
+            caller: frame.caller(),
+            pc: frame.program_counter(),
+            exception_handler: frame.exception_handler(),
+            is_static: frame.is_static(),
+            is_kernel: frame.is_kernel(),
+            is_near_call: frame.is_near_call(),
+            gas: frame.gas(),
+            context_u128: frame.context_u128(),
+            sp: frame.stack_pointer(),
+            heap: frame.heap(),
+            heap_bound: frame.heap_bound(),
+            aux_heap: frame.aux_heap(),
+            aux_heap_bound: frame.aux_heap_bound(),
+        }
+    }
+
+    fn differs_only_in_pc_sp_gas(&self, other: &Self) -> bool {
+        *self
+            == Self {
+                pc: self.pc,
+                sp: self.sp,
+                gas: self.gas,
+                ..*other
+            }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    view: FrameView,
+    /// Stack slots visible from the frame. For near calls, the stack is shared with the calling frames;
+    /// only the topmost frame sharing the stack has its up-to-date copy.
+    stack: Vec<(U256, bool)>,
+}
+
+impl Frame {
+    fn capture(frame: &impl CallframeInterface) -> Self {
+        let view = FrameView::capture(frame);
+        Self {
+            stack: (0..view.sp).map(|slot| frame.read_stack(slot)).collect(),
+            view,
+        }
+    }
+
+    fn read_stack(&self, slot: u16) -> (U256, bool) {
+        self.stack
+            .get(usize::from(slot))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn write_stack(&mut self, slot: u16, value: (U256, bool)) {
+        let slot = usize::from(slot);
+        if slot >= self.stack.len() {
+            self.stack.resize(slot + 1, (U256::zero(), false));
+        }
+        self.stack[slot] = value;
+    }
+}
+
+#[derive(Debug)]
+struct RecordedState {
+    registers: [(U256, bool); 16],
+    flags: Flags,
+    globals: Globals,
+    /// Call frames from the outermost to the current one.
+    frames: Vec<Frame>,
+    /// Heap bytes read or written by recorded instructions, keyed by heap ID and offset.
+    heap: BTreeMap<(u32, u32), u8>,
+}
+
+impl RecordedState {
+    fn capture(state: &mut impl StateInterface) -> Self {
+        let mut registers = [(U256::zero(), false); 16];
+        for (register, value) in (0..16).zip(&mut registers) {
+            *value = state.read_register(register);
+        }
+        let frames = (0..state.number_of_callframes())
+            .rev()
+            .map(|n| Frame::capture(&state.callframe(n)))
+            .collect();
+
+        Self {
+            registers,
+            flags: state.flags(),
+            globals: Globals::capture(&*state),
+            frames,
+            heap: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the write performed by a heap access. For reads, captures the read bytes that weren't read
+    /// or written before; since no recorded instruction has changed them, they've had the same value
+    /// since the start of the recording.
+    fn observe_heap_access(
+        &mut self,
+        access: HeapAccess,
+        state: &impl StateInterface,
+    ) -> Option<HeapWrite> {
+        let heap = access.heap;
+        if access.is_write {
+            // The VM state is already updated, so the word is taken from the recording.
+            let before = self.read_heap_u256(heap, access.offset);
+            return (before != access.value).then_some(HeapWrite {
+                heap,
+                offset: access.offset,
+                value: Change {
+                    before,
+                    after: access.value,
+                },
+            });
+        }
+
+        // Pointer reads zero out bytes outside the pointer, so the bytes are read from the heap.
+        for byte_offset in word_offsets(access.offset) {
+            self.heap
+                .entry((heap.as_u32(), byte_offset))
+                .or_insert_with(|| state.read_heap_byte(heap, byte_offset));
+        }
+        None
+    }
+
+    /// Updates this state to the current state of the VM and returns the delta.
+    fn record(
+        &mut self,
+        opcode: Opcode,
+        state: &mut impl StateInterface,
+        heap_write: Option<HeapWrite>,
+        stack_write: Option<(u16, (U256, bool))>,
+    ) -> Step {
+        let mut registers = vec![];
+        for (register, recorded) in (0..16).zip(&mut self.registers) {
+            let value = state.read_register(register);
+            if *recorded != value {
+                registers.push((
+                    register,
+                    Change {
+                        before: *recorded,
+                        after: value,
+                    },
+                ));
+                *recorded = value;
+            }
+        }
+
+        let flags = state.flags();
+        let flags = (self.flags != flags).then(|| Change {
+            before: std::mem::replace(&mut self.flags, flags),
+            after: flags,
+        });
+        let globals = Globals::capture(&*state);
+        let globals = (self.globals != globals).then(|| Change {
+            before: std::mem::replace(&mut self.globals, globals),
+            after: globals,
+        });
+        if let Some(write) = &heap_write {
+            self.write_heap(write.heap, write.offset, write.value.after);
+        }
+
+        Step {
+            opcode,
+            registers,
+            flags,
+            globals,
+            heap_write,
+            frames: self.record_frames(state, stack_write),
+        }
+    }
+
+    /// `stack_write` is the slot written via absolute stack addressing together with its value before the write.
+    fn record_frames(
+        &mut self,
+        state: &mut impl StateInterface,
+        stack_write: Option<(u16, (U256, bool))>,
+    ) -> FramesDelta {
+        let old_len = self.frames.len();
+        let new_len = state.number_of_callframes();
+        let current_view = FrameView::capture(&state.current_frame());
+
+        if let Some(frame) = self.frames.last_mut() {
+            if new_len == old_len && frame.view.differs_only_in_pc_sp_gas(&current_view) {
+                let current_frame = state.current_frame();
+                let compared_slots = frame.view.sp.max(current_view.sp);
+                let mut stack = vec![];
+                for slot in 0..compared_slots {
+                    let before = frame.read_stack(slot);
+                    let after = current_frame.read_stack(slot);
+                    if before != after {
+                        stack.push((slot, Change { before, after }));
+                        frame.write_stack(slot, after);
+                    }
+                }
+                if let Some((slot, before)) =
+                    stack_write.filter(|&(slot, _)| slot >= compared_slots)
+                {
+                    let after = current_frame.read_stack(slot);
+                    if before != after {
+                        stack.push((slot, Change { before, after }));
+                        frame.write_stack(slot, after);
+                    }
+                }
+
+                let before = std::mem::replace(&mut frame.view, current_view);
+                return FramesDelta::Current {
+                    pc: Change {
+                        before: before.pc,
+                        after: current_view.pc,
+                    },
+                    sp: Change {
+                        before: before.sp,
+                        after: current_view.sp,
+                    },
+                    gas: Change {
+                        before: before.gas,
+                        after: current_view.gas,
+                    },
+                    stack,
+                };
+            }
+        }
+
+        // The frame below the current one is updated on calls, so it's re-captured as well
+        let unchanged_len = old_len.min(new_len).saturating_sub(1);
+        let removed = self.frames.split_off(unchanged_len);
+        let added: Vec<_> = (0..new_len - unchanged_len)
+            .rev()
+            .map(|n| Frame::capture(&state.callframe(n)))
+            .collect();
+        self.frames.extend(added.iter().cloned());
+        FramesDelta::Callstack { removed, added }
+    }
+
+    fn apply(&mut self, step: &Step, forward: bool) {
+        for (register, change) in &step.registers {
+            self.registers[usize::from(*register)] = change.get(forward);
+        }
+        if let Some(change) = &step.flags {
+            self.flags = change.get(forward);
+        }
+        if let Some(change) = &step.globals {
+            self.globals = change.get(forward);
+        }
+        if let Some(write) = &step.heap_write {
+            self.write_heap(write.heap, write.offset, write.value.get(forward));
+        }
+
+        match &step.frames {
+            FramesDelta::Current { pc, sp, gas, stack } => {
+                let frame = self.frames.last_mut().expect("no frames in recorded state");
+                frame.view.pc = pc.get(forward);
+                frame.view.sp = sp.get(forward);
+                frame.view.gas = gas.get(forward);
+                for (slot, change) in stack {
+                    frame.write_stack(*slot, change.get(forward));
+                }
+            }
+            FramesDelta::Callstack { removed, added } => {
+                let (old_frames, new_frames) = if forward {
+                    (removed, added)
+                } else {
+                    (added, removed)
+                };
+                self.frames.truncate(self.frames.len() - old_frames.len());
+                self.frames.extend(new_frames.iter().cloned());
+            }
+        }
+    }
+
+    fn write_heap(&mut self, heap: HeapId, offset: u32, value: U256) {
+        let mut bytes = [0; 32];
+        value.to_big_endian(&mut bytes);
+        for (byte_offset, byte) in word_offsets(offset).zip(bytes) {
+            self.heap.insert((heap.as_u32(), byte_offset), byte);
+        }
+    }
+
+    fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8 {
+        self.heap
+            .get(&(heap.as_u32(), offset))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256 {
+        let mut bytes = [0; 32];
+        for (byte_offset, byte) in word_offsets(offset).zip(&mut bytes) {
+            *byte = self.read_heap_byte(heap, byte_offset);
+        }
+        U256::from_big_endian(&bytes)
+    }
+}
+
+/// Returns offsets of the bytes in the heap word starting at `offset`, excluding ones past the end of the heap.
+fn word_offsets(offset: u32) -> impl Iterator<Item = u32> {
+    (0..32).map_while(move |i| offset.checked_add(i))
+}
+
+impl StateInterface for Replay {
+    fn read_register(&self, register: u8) -> (U256, bool) {
+        self.state.registers[usize::from(register)]
+    }
+
+    fn set_register(&mut self, register: u8, value: U256, is_pointer: bool) {
+        self.state.registers[usize::from(register)] = (value, is_pointer);
+    }
+
+    fn current_frame(&mut self) -> impl CallframeInterface + '_ {
+        self.callframe(0)
+    }
+
+    fn number_of_callframes(&self) -> usize {
+        self.state.frames.len()
+    }
+
+    fn callframe(&mut self, n: usize) -> impl CallframeInterface + '_ {
+        let index = self.state.frames.len() - 1 - n;
+        ReplayFrame {
+            frames: &mut self.state.frames,
+            index,
+        }
+    }
+
+    fn read_heap_byte(&self, heap: HeapId, offset: u32) -> u8 {
+        self.state.read_heap_byte(heap, offset)
+    }
+
+    fn read_heap_u256(&self, heap: HeapId, offset: u32) -> U256 {
+        self.state.read_heap_u256(heap, offset)
+    }
+
+    fn write_heap_u256(&mut self, heap: HeapId, offset: u32, value: U256) {
+        self.state.write_heap(heap, offset, value);
+    }
+
+    fn flags(&self) -> Flags {
+        self.state.flags
+    }
+
+    fn set_flags(&mut self, flags: Flags) {
+        self.state.flags = flags;
+    }
+
+    fn transaction_number(&self) -> u16 {
+        self.state.globals.transaction_number
+    }
+
+    fn set_transaction_number(&mut self, value: u16) {
+        self.state.globals.transaction_number = value;
+    }
+
+    fn context_u128_register(&self) -> u128 {
+        self.state.globals.context_u128
+    }
+
+    fn set_context_u128_register(&mut self, value: u128) {
+        self.state.globals.context_u128 = value;
+    }
+
+    fn get_storage_state(&self) -> impl Iterator<Item = ((H160, U256), U256)> {
+        iter::empty()
+    }
+
+    fn get_transient_storage_state(&self) -> impl Iterator<Item = ((H160, U256), U256)> {
+        iter::empty()
+    }
+
+    fn get_transient_storage(&self, _address: H160, _slot: U256) -> U256 {
+        U256::zero()
+    }
+
+    fn write_transient_storage(&mut self, _address: H160, _slot: U256, _value: U256) {
+        // Transient storage isn't recorded
+    }
+
+    fn events(&self) -> impl Iterator<Item = Event> {
+        iter::empty()
+    }
+
+    fn l2_to_l1_logs(&self) -> impl Iterator<Item = L2ToL1Log> {
+        iter::empty()
+    }
+
+    fn pubdata(&self) -> i32 {
+        self.state.globals.pubdata
+    }
+
+    fn set_pubdata(&mut self, value: i32) {
+        self.state.globals.pubdata = value;
+    }
+}
+
+#[derive(Debug)]
+struct ReplayFrame<'a> {
+    frames: &'a mut [Frame],
+    index: usize,
+}
+
+impl ReplayFrame<'_> {
+    fn view(&self) -> &FrameView {
+        &self.frames[self.index].view
+    }
+
+    fn view_mut(&mut self) -> &mut FrameView {
+        &mut self.frames[self.index].view
+    }
+
+    /// Returns the index of the frame holding the up-to-date copy of the stack used by this frame.
+    fn stack_owner(&self) -> usize {
+        let mut owner = self.index;
+        while self
+            .frames
+            .get(owner + 1)
+            .is_some_and(|frame| frame.view.is_near_call)
+        {
+            owner += 1;
+        }
+        owner
+    }
+}
+
+impl CallframeInterface for ReplayFrame<'_> {
+    fn address(&self) -> H160 {
+        self.view().address
+    }
+
+    fn set_address(&mut self, address: H160) {
+        self.view_mut().address = address;
+    }
+
+    fn code_address(&self) -> H160 {
+        self.view().code_address
+    }
+
+    fn set_code_address(&mut self, address: H160) {
+        self.view_mut().code_address = address;
+    }
+
+    fn caller(&self) -> H160 {
+        self.view().caller
+    }
+
+    fn set_caller(&mut self, address: H160) {
+        self.view_mut().caller = address;
+    }
+
+    fn program_counter(&self) -> Option<u16> {
+        self.view().pc
+    }
+
+    fn set_program_counter(&mut self, value: u16) {
+        self.view_mut().pc = Some(value);
+    }
+
+    fn exception_handler(&self) -> u16 {
+        self.view().exception_handler
+    }
+
+    fn set_exception_handler(&mut self, value: u16) {
+        self.view_mut().exception_handler = value;
+    }
+
+    fn is_static(&self) -> bool {
+        self.view().is_static
+    }
+
+    fn is_kernel(&self) -> bool {
+        self.view().is_kernel
+    }
+
+    fn gas(&self) -> u32 {
+        self.view().gas
+    }
+
+    fn set_gas(&mut self, new_gas: u32) {
+        self.view_mut().gas = new_gas;
+    }
+
+    fn context_u128(&self) -> u128 {
+        self.view().context_u128
+    }
+
+    fn set_context_u128(&mut self, value: u128) {
+        self.view_mut().context_u128 = value;
+    }
+
+    fn is_near_call(&self) -> bool {
+        self.view().is_near_call
+    }
+
+    fn read_stack(&self, index: u16) -> (U256, bool) {
+        self.frames[self.stack_owner()].read_stack(index)
+    }
+
+    fn write_stack(&mut self, index: u16, value: U256, is_pointer: bool) {
+        let owner = self.stack_owner();
+        self.frames[owner].write_stack(index, (value, is_pointer));
+    }
+
+    fn stack_pointer(&self) -> u16 {
+        self.view().sp
+    }
+
+    fn set_stack_pointer(&mut self, value: u16) {
+        self.view_mut().sp = value;
+    }
+
+    fn heap(&self) -> HeapId {
+        self.view().heap
+    }
+
+    fn heap_bound(&self) -> u32 {
+        self.view().heap_bound
+    }
+
+    fn set_heap_bound(&mut self, value: u32) {
+        self.view_mut().heap_bound = value;
+    }
+
+    fn aux_heap(&self) -> HeapId {
+        self.view().aux_heap
+    }
+
+    fn aux_heap_bound(&self) -> u32 {
+        self.view().aux_heap_bound
+    }
+
+    fn set_aux_heap_bound(&mut self, value: u32) {
+        self.view_mut().aux_heap_bound = value;
+    }
+
+    fn read_contract_code(&self, _slot: u16) -> U256 {
+        U256::zero()
+    }
+}
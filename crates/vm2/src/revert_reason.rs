@@ -0,0 +1,189 @@
+//! Decoding of Solidity revert reasons from revert data.
+
+use std::fmt;
+
+use primitive_types::U256;
+
+/// Selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Revert reason decoded from the data returned by a reverting call.
+///
+/// Can be obtained from the data directly using [`Self::decode()`], or via [`ExecutionEnd::revert_reason()`](crate::ExecutionEnd::revert_reason())
+/// and [`Call::revert_reason()`](crate::Call::revert_reason()).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// No revert data, e.g. for `revert()` or `require()` without a message.
+    Empty,
+    /// `Error(string)` produced by `revert("...")` or `require()` with a message.
+    /// Invalid UTF-8 sequences in the message are replaced with `U+FFFD`.
+    Error(String),
+    /// `Panic(uint256)` produced by a failed compiler-inserted check, with the panic code (e.g., 0x11 for an arithmetic overflow).
+    Panic(U256),
+    /// Custom error, i.e. revert data starting with a selector other than the ones of `Error` and `Panic`.
+    Custom {
+        /// Error selector.
+        selector: [u8; 4],
+        /// ABI-encoded error arguments following the selector.
+        arguments: Vec<u8>,
+    },
+    /// Data that cannot be an ABI-encoded error: it's shorter than a selector, or has a malformed `Error` / `Panic` payload.
+    Raw(Vec<u8>),
+}
+
+impl RevertReason {
+    /// Decodes the revert reason from the revert data. Never fails; data that cannot be decoded is returned as [`Self::Raw`].
+    pub fn decode(data: &[u8]) -> Self {
+        let (selector, arguments) = match data {
+            [] => return Self::Empty,
+            &[s0, s1, s2, s3, ref arguments @ ..] => ([s0, s1, s2, s3], arguments),
+            _ => return Self::Raw(data.to_vec()),
+        };
+
+        let decoded = match selector {
+            ERROR_SELECTOR => decode_string(arguments).map(Self::Error),
+            PANIC_SELECTOR => {
+                (arguments.len() == 32).then(|| Self::Panic(U256::from_big_endian(arguments)))
+            }
+            _ => Some(Self::Custom {
+                selector,
+                arguments: arguments.to_vec(),
+            }),
+        };
+        decoded.unwrap_or_else(|| Self::Raw(data.to_vec()))
+    }
+}
+
+/// Outputs the error message for `Error`, and a short description for other reasons, e.g. `panic 0x11 (arithmetic overflow or underflow)`.
+impl fmt::Display for RevertReason {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => formatter.write_str("no revert data"),
+            Self::Error(message) => formatter.write_str(message),
+            Self::Panic(code) => {
+                write!(formatter, "panic {code:#x}")?;
+                if let Some(description) = panic_description(*code) {
+                    write!(formatter, " ({description})")?;
+                }
+                Ok(())
+            }
+            Self::Custom { selector, .. } => {
+                formatter.write_str("custom error 0x")?;
+                write_hex(formatter, selector)
+            }
+            Self::Raw(data) => {
+                formatter.write_str("undecodable revert data 0x")?;
+                write_hex(formatter, data)
+            }
+        }
+    }
+}
+
+/// Decodes an ABI-encoded `string` that is the only argument of a function.
+fn decode_string(arguments: &[u8]) -> Option<String> {
+    let offset = read_length(arguments, 0)?;
+    let length = read_length(arguments, offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = arguments.get(start..start.checked_add(length)?)?;
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// Reads a word at the specified position as an offset or length. Words exceeding `u32::MAX` cannot be valid
+/// offsets or lengths since they would point outside of the data.
+fn read_length(data: &[u8], position: usize) -> Option<usize> {
+    let word = data.get(position..position.checked_add(32)?)?;
+    let word = U256::from_big_endian(word);
+    (word <= U256::from(u32::MAX)).then(|| word.as_usize())
+}
+
+/// Describes panic codes defined by the Solidity compiler.
+fn panic_description(code: U256) -> Option<&'static str> {
+    if code > U256::from(u8::MAX) {
+        return None;
+    }
+    Some(match code.as_u32() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop from empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to uninitialized internal function",
+        _ => return None,
+    })
+}
+
+fn write_hex(formatter: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for byte in bytes {
+        write!(formatter, "{byte:02x}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_error(message: &str) -> Vec<u8> {
+        let mut data = ERROR_SELECTOR.to_vec();
+        data.extend_from_slice(&[0; 31]);
+        data.push(0x20);
+        data.extend_from_slice(&[0; 31]);
+        data.push(message.len().try_into().unwrap());
+        data.extend_from_slice(message.as_bytes());
+        data.resize(4 + 32 * 2 + message.len().div_ceil(32) * 32, 0);
+        data
+    }
+
+    #[test]
+    fn decoding_error_message() {
+        let data = encode_error("not enough balance");
+        let reason = RevertReason::decode(&data);
+        assert_eq!(reason, RevertReason::Error("not enough balance".to_owned()));
+        assert_eq!(reason.to_string(), "not enough balance");
+
+        // Truncated string payload
+        let reason = RevertReason::decode(&data[..data.len() - 20]);
+        assert_eq!(reason, RevertReason::Raw(data[..data.len() - 20].to_vec()));
+    }
+
+    #[test]
+    fn decoding_panic() {
+        let mut data = PANIC_SELECTOR.to_vec();
+        data.extend_from_slice(&[0; 31]);
+        data.push(0x11);
+        let reason = RevertReason::decode(&data);
+        assert_eq!(reason, RevertReason::Panic(0x11.into()));
+        assert_eq!(
+            reason.to_string(),
+            "panic 0x11 (arithmetic overflow or underflow)"
+        );
+
+        data.push(0);
+        assert_eq!(RevertReason::decode(&data), RevertReason::Raw(data));
+    }
+
+    #[test]
+    fn decoding_custom_and_raw_data() {
+        assert_eq!(RevertReason::decode(&[]), RevertReason::Empty);
+        assert_eq!(
+            RevertReason::decode(&[1, 2, 3]),
+            RevertReason::Raw(vec![1, 2, 3])
+        );
+
+        let reason = RevertReason::decode(&[0xde, 0xad, 0xbe, 0xef, 1]);
+        assert_eq!(
+            reason,
+            RevertReason::Custom {
+                selector: [0xde, 0xad, 0xbe, 0xef],
+                arguments: vec![1],
+            }
+        );
+        assert_eq!(reason.to_string(), "custom error 0xdeadbeef");
+    }
+}
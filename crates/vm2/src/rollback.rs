@@ -1,5 +1,7 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use crate::checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder};
+
 /// A trait for things that can be rolled back to snapshots
 pub(crate) trait Rollback {
     type Snapshot;
@@ -8,7 +10,7 @@ pub(crate) trait Rollback {
     fn delete_history(&mut self);
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct RollbackableMap<K: Ord, V> {
     map: BTreeMap<K, V>,
     old_entries: Vec<(K, Option<V>)>,
@@ -113,7 +115,7 @@ impl<K: Ord, V> AsRef<BTreeMap<K, V>> for RollbackableMap<K, V> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct RollbackableSet<K: Ord> {
     map: BTreeSet<K>,
     old_entries: Vec<K>,
@@ -154,7 +156,7 @@ impl<K: Ord> AsRef<BTreeSet<K>> for RollbackableSet<K> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct RollbackableLog<T> {
     entries: Vec<T>,
 }
@@ -221,3 +223,56 @@ impl<T: Copy> Rollback for RollbackablePod<T> {
 
     fn delete_history(&mut self) {}
 }
+
+// region:Checkpoint implementations
+
+impl<K: Checkpoint + Ord, V: Checkpoint> Checkpoint for RollbackableMap<K, V> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.map);
+        encoder.put(&self.old_entries);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            map: decoder.get()?,
+            old_entries: decoder.get()?,
+        })
+    }
+}
+
+impl<K: Checkpoint + Ord> Checkpoint for RollbackableSet<K> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.map);
+        encoder.put(&self.old_entries);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            map: decoder.get()?,
+            old_entries: decoder.get()?,
+        })
+    }
+}
+
+impl<T: Checkpoint> Checkpoint for RollbackableLog<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.entries);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            entries: decoder.get()?,
+        })
+    }
+}
+
+impl<T: Checkpoint + Copy> Checkpoint for RollbackablePod<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.0);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        decoder.get().map(Self)
+    }
+}
+// endregion
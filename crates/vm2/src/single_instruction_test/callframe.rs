@@ -1,6 +1,6 @@
 use arbitrary::Arbitrary;
 use primitive_types::H160;
-use zksync_vm2_interface::{HeapId, Tracer};
+use zksync_vm2_interface::{v2::Tracer, HeapId};
 
 use super::stack::{Stack, StackPool};
 use crate::{
@@ -12,7 +12,7 @@ use zk_evm::{
 };
 use zk_evm_abstractions::vm::EventSink;
 use zkevm_opcode_defs::{decoding::EncodingModeProduction, TRANSIENT_STORAGE_AUX_BYTE};
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use super::{stack::Stack, state_to_zk_evm::vm2_state_to_zk_evm_state, MockWorld};
 use crate::{StorageInterface, VirtualMachine, World};
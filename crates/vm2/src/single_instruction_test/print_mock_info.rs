@@ -1,4 +1,4 @@
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use crate::{callframe::Callframe, state::State, VirtualMachine, World};
 
@@ -2,7 +2,7 @@ use std::{rc::Rc, sync::Arc};
 
 use arbitrary::Arbitrary;
 use primitive_types::U256;
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use super::mock_array::MockRead;
 use crate::{decode::decode, Instruction, World};
@@ -65,6 +65,11 @@ impl<T, W> Program<T, W> {
     pub fn code_page(&self) -> &Arc<[U256]> {
         &self.code_page
     }
+
+    #[must_use]
+    pub fn with_code_hash(self, _code_hash: U256) -> Self {
+        self
+    }
 }
 
 impl<T: Tracer, W: World<T>> Program<T, W> {
@@ -1,11 +1,12 @@
 use arbitrary::Arbitrary;
 use primitive_types::U256;
-use zksync_vm2_interface::{HeapId, Tracer};
+use zksync_vm2_interface::{v2::Tracer, HeapId};
 
 use super::{heap::Heaps, stack::StackPool};
 use crate::{
-    callframe::Callframe, fat_pointer::FatPointer, page_ids::first_dynamic_base_page, state::State,
-    Settings, VirtualMachine, World, WorldDiff,
+    breakpoints::Breakpoints, callframe::Callframe, fat_pointer::FatPointer,
+    page_ids::first_dynamic_base_page, state::State, watchpoints::Watchpoints, Settings,
+    VirtualMachine, World, WorldDiff,
 };
 
 impl<T: Tracer, W> VirtualMachine<T, W> {
@@ -77,11 +78,16 @@ impl<'a, T: Tracer, W: World<T>> Arbitrary<'a> for VirtualMachine<T, W> {
                 context_u128: u.arbitrary()?,
                 next_base_page: first_dynamic_base_page(),
                 dst1_was_updated: false,
+                pending_panic: None,
             },
             settings: u.arbitrary()?,
             world_diff: WorldDiff::default(),
             stack_pool: StackPool {},
-            snapshot: None,
+            snapshots: Vec::new(),
+            breakpoints: Breakpoints::default(),
+            watchpoints: Watchpoints::default(),
+            allow_superinstructions: false,
+            commit_pending: false,
         })
     }
 }
@@ -1,6 +1,6 @@
 use arbitrary::Arbitrary;
 use primitive_types::{H160, U256};
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use super::mock_array::MockRead;
 use crate::{Program, StorageInterface, StorageSlot, World};
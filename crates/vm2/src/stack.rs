@@ -5,7 +5,12 @@ use std::{
 
 use primitive_types::U256;
 
-use crate::{bitset::Bitset, fat_pointer::FatPointer, hash_for_debugging};
+use crate::{
+    bitset::Bitset,
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
+    fat_pointer::FatPointer,
+    hash_for_debugging,
+};
 
 const NUMBER_OF_DIRTY_AREAS: usize = 64;
 const DIRTY_AREA_SIZE: usize = (1 << 16) / NUMBER_OF_DIRTY_AREAS;
@@ -213,6 +218,64 @@ impl StackPool {
     }
 }
 
+// region:Checkpoint implementations
+
+// Only allocated sub-chunks are stored; absent ones read as zero anyway.
+impl Checkpoint for Box<Stack> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.pointer_flags);
+        encoder.put(&self.dirty_areas);
+        let chunks: Vec<(u16, [U256; SUBCHUNK_SLOTS])> = (0_u16..)
+            .zip(&self.slots)
+            .filter_map(|(idx, chunk)| Some((idx, **chunk.as_ref()?)))
+            .collect();
+        encoder.put(&chunks);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let mut stack = Stack::new();
+        stack.pointer_flags = decoder.get()?;
+        stack.dirty_areas = decoder.get()?;
+        for (idx, values) in decoder.get::<Vec<(u16, [U256; SUBCHUNK_SLOTS])>>()? {
+            let idx = usize::from(idx);
+            // `Stack::zero()` relies on sub-chunks only being allocated in dirty areas.
+            if idx >= NUM_SUBCHUNKS || stack.dirty_areas & (1 << (idx / SUBCHUNKS_PER_AREA)) == 0 {
+                return Err(CheckpointError::Malformed(
+                    "stack chunk outside of dirty areas",
+                ));
+            }
+            let mut chunk = zeroed_chunk();
+            *chunk = values;
+            stack.slots[idx] = Some(chunk);
+        }
+        Ok(stack)
+    }
+}
+
+impl Checkpoint for StackSnapshot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.pointer_flags);
+        encoder.put(&self.dirty_areas);
+        encoder.put(&self.slots);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        let pointer_flags = decoder.get()?;
+        let dirty_areas: u64 = decoder.get()?;
+        let slots: Box<[U256]> = decoder.get()?;
+        let dirty_prefix_end = NUMBER_OF_DIRTY_AREAS - dirty_areas.leading_zeros() as usize;
+        if slots.len() != DIRTY_AREA_SIZE * dirty_prefix_end {
+            return Err(CheckpointError::Malformed("stack snapshot size mismatch"));
+        }
+        Ok(Self {
+            pointer_flags,
+            dirty_areas,
+            slots,
+        })
+    }
+}
+// endregion
+
 // region:Debug implementations
 
 /// Helper wrapper for debugging [`Stack`] / [`StackSnapshot`] contents.
@@ -1,5 +1,8 @@
 use primitive_types::{H160, U256};
-use zksync_vm2_interface::{HeapId, Tracer};
+use zksync_vm2_interface::{
+    v2::{PanicReason, Tracer},
+    HeapId,
+};
 
 use crate::{
     addressing_modes::Addressable,
@@ -13,6 +16,11 @@ use crate::{
     world_diff::Snapshot,
     World,
 };
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::{
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
+    program::ProgramSource,
+};
 
 /// State of a [`VirtualMachine`](crate::VirtualMachine).
 #[derive(Debug)]
@@ -32,6 +40,9 @@ pub(crate) struct State<T, W> {
     /// Set by every `dst1` write; if still `false` after execution, `dst1` is cleared to zero,
     /// matching `zk_evm`. Transient per-instruction bookkeeping; excluded from equality and snapshots.
     pub(crate) dst1_was_updated: bool,
+    /// Reason of the panic that will be performed by the next instruction, set together with pointing
+    /// the program counter at a panic. Consumed by `naked_ret`; excluded from equality and snapshots.
+    pub(crate) pending_panic: Option<PanicReason>,
 }
 
 impl<T, W> State<T, W> {
@@ -81,6 +92,7 @@ impl<T, W> State<T, W> {
             context_u128: 0,
             next_base_page: first_dynamic_base_page(),
             dst1_was_updated: false,
+            pending_panic: None,
         }
     }
 
@@ -184,6 +196,8 @@ impl<T: Tracer, W: World<T>> State<T, W> {
         // start of the next instruction before being read, so this is a functional no-op; we clear
         // it here to keep the invariant "never survives an instruction boundary" self-evident.
         self.dst1_was_updated = false;
+        // The program counter is restored, so a pending panic is no longer pending.
+        self.pending_panic = None;
     }
 
     pub(crate) fn delete_history(&mut self) {
@@ -191,6 +205,66 @@ impl<T: Tracer, W: World<T>> State<T, W> {
     }
 }
 
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> State<T, W> {
+    /// Iterates over all callframes, starting from the initial one.
+    pub(crate) fn frames(&self) -> impl Iterator<Item = &Callframe<T, W>> {
+        self.previous_frames
+            .iter()
+            .chain(std::iter::once(&self.current_frame))
+    }
+
+    pub(crate) fn encode_checkpoint(&self, encoder: &mut Encoder) {
+        encoder.put(&self.registers);
+        encoder.put(&self.register_pointer_flags);
+        encoder.put(&self.flags);
+        encoder.put(&self.previous_frames.len());
+        for frame in self.frames() {
+            frame.encode_checkpoint(encoder);
+        }
+        encoder.put(&self.heaps);
+        encoder.put(&self.transaction_number);
+        encoder.put(&self.context_u128);
+        encoder.put(&self.next_base_page);
+        encoder.put(&self.pending_panic);
+    }
+
+    /// Decodes the state, obtaining frame programs from the provided closure. The closure receives
+    /// the frame depth (0 for the initial frame) and the recorded program source.
+    pub(crate) fn decode_checkpoint(
+        decoder: &mut Decoder<'_>,
+        mut get_program: impl FnMut(usize, ProgramSource) -> Result<Program<T, W>, CheckpointError>,
+    ) -> Result<Self, CheckpointError> {
+        let registers = decoder.get()?;
+        let register_pointer_flags = decoder.get()?;
+        let flags = decoder.get()?;
+        let previous_frame_count = decoder.get_len()?;
+        let mut previous_frames = Vec::with_capacity(previous_frame_count);
+        for depth in 0..previous_frame_count {
+            previous_frames.push(Callframe::decode_checkpoint(decoder, |source| {
+                get_program(depth, source)
+            })?);
+        }
+        let current_frame = Callframe::decode_checkpoint(decoder, |source| {
+            get_program(previous_frame_count, source)
+        })?;
+
+        Ok(Self {
+            registers,
+            register_pointer_flags,
+            flags,
+            current_frame,
+            previous_frames,
+            heaps: decoder.get()?,
+            transaction_number: decoder.get()?,
+            context_u128: decoder.get()?,
+            next_base_page: decoder.get()?,
+            dst1_was_updated: false,
+            pending_panic: decoder.get()?,
+        })
+    }
+}
+
 impl<T, W> Clone for State<T, W> {
     fn clone(&self) -> Self {
         Self {
@@ -204,6 +278,7 @@ impl<T, W> Clone for State<T, W> {
             context_u128: self.context_u128,
             next_base_page: self.next_base_page,
             dst1_was_updated: self.dst1_was_updated,
+            pending_panic: self.pending_panic,
         }
     }
 }
@@ -282,3 +357,32 @@ pub(crate) struct StateSnapshot {
     context_u128: u128,
     next_base_page: u32,
 }
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl Checkpoint for StateSnapshot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.registers);
+        encoder.put(&self.register_pointer_flags);
+        encoder.put(&self.flags);
+        encoder.put(&self.bootloader_frame);
+        encoder.put(&self.bootloader_heap_snapshot);
+        encoder.put(&self.dynamic_heap_groups);
+        encoder.put(&self.transaction_number);
+        encoder.put(&self.context_u128);
+        encoder.put(&self.next_base_page);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            registers: decoder.get()?,
+            register_pointer_flags: decoder.get()?,
+            flags: decoder.get()?,
+            bootloader_frame: decoder.get()?,
+            bootloader_heap_snapshot: decoder.get()?,
+            dynamic_heap_groups: decoder.get()?,
+            transaction_number: decoder.get()?,
+            context_u128: decoder.get()?,
+            next_base_page: decoder.get()?,
+        })
+    }
+}
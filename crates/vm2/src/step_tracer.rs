@@ -0,0 +1,186 @@
+//! Tracer writing a JSON line per executed instruction.
+
+use std::{fmt::Write as _, io};
+
+use primitive_types::U256;
+use zksync_vm2_interface::{
+    v2::{HeapAccess, Tracer},
+    CallframeInterface, Flags, GlobalStateInterface, Opcode, OpcodeType, ShouldStop,
+};
+
+use crate::json::{write_array, write_bool, write_quantity, write_string, JsonObject};
+
+/// State captured before executing an instruction.
+#[derive(Debug)]
+struct PendingStep {
+    pc: Option<u16>,
+    opcode: Opcode,
+    ergs_after_static_cost: u32,
+    sp: u16,
+    depth: usize,
+    /// Index, value and pointer flag of non-zero registers.
+    registers: Vec<(u8, U256, bool)>,
+    flags: Flags,
+}
+
+/// [`Tracer`] writing a JSON object per executed instruction to an [`io::Write`] sink, one object per line,
+/// similar to EIP-3155 traces for the EVM. Such traces can be diffed line by line, e.g. to compare
+/// different VM implementations.
+///
+/// Each object has the following fields:
+///
+/// - `pc`: program counter of the instruction, or `null` if the instruction is not a part of the program.
+///   The latter happens for panics caused by the preceding instruction, e.g. if it cannot pay for heap growth.
+/// - `op`: [`Opcode`] of the instruction in its `Debug` format, e.g. `Add` or `FarCall(Normal)`
+/// - `ergsAfterStaticCost`: ergs of the current frame after the static cost of the instruction is paid,
+///   but before the instruction is executed
+/// - `ergsAfter`: ergs of the current frame after executing the instruction. For calls and returns,
+///   this is the gas of the called frame or of the frame returned to, respectively.
+/// - `sp`: stack pointer before executing the instruction
+/// - `depth`: number of far and near call frames before executing the instruction; 1 for the initial frame
+/// - `registers`: object mapping non-zero registers before executing the instruction (e.g., `r1`)
+///   to `{"value": hex number, "isPointer": bool}`
+/// - `flags`: `{"lt": bool, "eq": bool, "gt": bool}` before executing the instruction
+/// - `heap`: only present if [enabled](Self::with_heap_accesses()); array of heap words read or written
+///   by the instruction as `{"heap": number, "offset": number, "value": hex number, "isWrite": bool}`
+///
+/// I/O errors don't interrupt execution; the first error is returned from [`Self::finish()`], and nothing
+/// is written after it. Since a line is written per instruction, it is advisable to use a buffered sink.
+#[derive(Debug)]
+pub struct StepTracer<W> {
+    out: W,
+    pending: Option<PendingStep>,
+    heap_accesses: Option<Vec<HeapAccess>>,
+    line: String,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> StepTracer<W> {
+    /// Creates a tracer writing to the specified sink.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            pending: None,
+            heap_accesses: None,
+            line: String::new(),
+            error: None,
+        }
+    }
+
+    /// Includes heap words read or written by each instruction in the output.
+    #[must_use]
+    pub fn with_heap_accesses(mut self) -> Self {
+        self.heap_accesses = Some(vec![]);
+        self
+    }
+
+    /// Flushes the sink and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first I/O error encountered while writing the trace or flushing the sink.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write_step(&mut self, step: &PendingStep, ergs_after: u32) {
+        self.line.clear();
+        let mut object = JsonObject::new(&mut self.line);
+        match step.pc {
+            Some(pc) => write!(object.field("pc"), "{pc}").unwrap(),
+            None => object.field("pc").push_str("null"),
+        }
+        write_string(object.field("op"), &format!("{:?}", step.opcode));
+        write!(
+            object.field("ergsAfterStaticCost"),
+            "{}",
+            step.ergs_after_static_cost
+        )
+        .unwrap();
+        write!(object.field("ergsAfter"), "{ergs_after}").unwrap();
+        write!(object.field("sp"), "{}", step.sp).unwrap();
+        write!(object.field("depth"), "{}", step.depth).unwrap();
+
+        let mut registers = JsonObject::new(object.field("registers"));
+        for &(index, value, is_pointer) in &step.registers {
+            let mut register = JsonObject::new(registers.field(&format!("r{index}")));
+            write_quantity(register.field("value"), value);
+            write_bool(register.field("isPointer"), is_pointer);
+            register.finish();
+        }
+        registers.finish();
+
+        let mut flags = JsonObject::new(object.field("flags"));
+        write_bool(flags.field("lt"), step.flags.less_than);
+        write_bool(flags.field("eq"), step.flags.equal);
+        write_bool(flags.field("gt"), step.flags.greater);
+        flags.finish();
+
+        if let Some(accesses) = &self.heap_accesses {
+            write_array(object.field("heap"), accesses, |out, access| {
+                let mut object = JsonObject::new(out);
+                write!(object.field("heap"), "{}", access.heap.as_u32()).unwrap();
+                write!(object.field("offset"), "{}", access.offset).unwrap();
+                write_quantity(object.field("value"), access.value);
+                write_bool(object.field("isWrite"), access.is_write);
+                object.finish();
+            });
+        }
+        object.finish();
+        self.line.push('\n');
+
+        if let Err(err) = self.out.write_all(self.line.as_bytes()) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl<W: io::Write> Tracer for StepTracer<W> {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        if self.error.is_some() {
+            return;
+        }
+        let (pc, ergs_after_static_cost, sp) = {
+            let frame = state.current_frame();
+            (frame.program_counter(), frame.gas(), frame.stack_pointer())
+        };
+        let registers = (1..16)
+            .filter_map(|index| {
+                let (value, is_pointer) = state.read_register(index);
+                (!value.is_zero() || is_pointer).then_some((index, value, is_pointer))
+            })
+            .collect();
+        self.pending = Some(PendingStep {
+            pc,
+            opcode: OP::VALUE,
+            ergs_after_static_cost,
+            sp,
+            depth: state.number_of_callframes(),
+            registers,
+            flags: state.flags(),
+        });
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        if let Some(step) = self.pending.take() {
+            self.write_step(&step, state.current_frame().gas());
+        }
+        if let Some(accesses) = &mut self.heap_accesses {
+            accesses.clear();
+        }
+        ShouldStop::Continue
+    }
+
+    fn on_heap_access(&mut self, access: HeapAccess) {
+        if let Some(accesses) = &mut self.heap_accesses {
+            accesses.push(access);
+        }
+    }
+}
@@ -9,14 +9,14 @@ use primitive_types::{H160, U256};
 use zkevm_opcode_defs::{
     ethereum_types::Address, system_params::DEPLOYER_SYSTEM_CONTRACT_ADDRESS_LOW,
 };
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 use crate::{
     instruction_handlers::address_into_u256, Program, StorageInterface, StorageSlot, World,
 };
 
 /// Test [`World`] implementation.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TestWorld<T> {
     pub(crate) address_to_hash: BTreeMap<U256, U256>,
     pub(crate) hash_to_contract: BTreeMap<U256, Program<T, Self>>,
@@ -0,0 +1,138 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    assemble, disassemble,
+    testonly::{initial_decommit, TestWorld},
+    AssemblyError, ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const PROGRAM: &str = "
+    ; Counts r1 down from 3, then checks a constant from the code page.
+    add 3, r0, r1
+loop:
+    sub.s! 1, r1, r1
+    jump.ne @loop
+    add code[@answer], r0, r2
+    sub.s! 42, r2, r0
+    jump.ne @fail
+    ret.ok r0
+fail: ret.panic r0
+
+answer:
+    .cell 42
+";
+
+fn run(source: &str) -> ExecutionEnd {
+    let bytecode = assemble(source).unwrap();
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let mut world = TestWorld::new(&[(address, Program::new(&bytecode, false))]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        10_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.run(&mut world, &mut ())
+}
+
+#[test]
+fn assembled_program_runs() {
+    assert!(matches!(run(PROGRAM), ExecutionEnd::ProgramFinished(_)));
+    assert_eq!(
+        run(&PROGRAM.replace(".cell 42", ".cell 0x2b")),
+        ExecutionEnd::Panicked(None)
+    );
+}
+
+#[test]
+fn assembly_round_trips_with_disassembler() {
+    let source = "
+        add 5, r0, r1
+        sub.s.lt! stack-[r1 + 2], r2, r3
+    callee:
+        near_call r1, @callee, @handler
+        st.1.inc r1, r2, r3
+        add code[r1 + @data], r0, r5
+        mul stack-=[1], r2, r3, r4
+        xor.s r1, r2, stack+=[r2]
+        shl r1, r2, stack[r3 + 0x10]
+        far_call.static.shard r1, r2, @handler
+    handler:
+        ret.ok.to_label.gt r1, @callee
+        nop
+    data:
+        .cell 0x1234
+    ";
+    let bytecode = assemble(source).unwrap();
+    assert_eq!(bytecode.len(), 3 * 32 + 32);
+    assert_eq!(bytecode[bytecode.len() - 2..], [0x12, 0x34]);
+
+    let instructions = disassemble(&bytecode[..3 * 32]);
+    let lines: Vec<_> = instructions.iter().map(ToString::to_string).collect();
+    let expected_lines = [
+        "add 5, r0, r1",
+        "sub.s.lt! stack-[r1 + 2], r2, r3",
+        "near_call r1, @2, @9",
+        "st.1.inc r1, r2, r3",
+        "add code[r1 + 3], r0, r5",
+        "mul stack-=[1], r2, r3, r4",
+        "xor.s r1, r2, stack+=[r2]",
+        "shl r1, r2, stack[r3 + 16]",
+        "far_call.static.shard r1, r2, @9",
+        "ret.ok.to_label.gt r1, @2",
+        "nop",
+        "invalid",
+    ];
+    assert_eq!(lines.len(), expected_lines.len());
+    for (line, expected) in lines.iter().zip(expected_lines) {
+        let (code, _) = line.split_once(" ; ").unwrap();
+        assert_eq!(code, expected);
+    }
+
+    let disassembled = lines.join("\n") + "\n.cell 0x1234";
+    assert_eq!(assemble(&disassembled).unwrap(), bytecode);
+}
+
+#[test]
+fn assembly_errors() {
+    assert_eq!(
+        assemble("add r1, r2, r3\nfoo r1").unwrap_err(),
+        AssemblyError::Syntax {
+            line: 2,
+            message: "unknown mnemonic `foo`".to_owned()
+        }
+    );
+    assert_eq!(
+        assemble("add r1, r2, 5").unwrap_err(),
+        AssemblyError::Unencodable { line: 1 }
+    );
+    assert_eq!(
+        assemble("jump.lt @nowhere").unwrap_err(),
+        AssemblyError::UndefinedLabel {
+            line: 1,
+            label: "nowhere".to_owned()
+        }
+    );
+    assert_eq!(
+        assemble("a: nop\na: nop").unwrap_err(),
+        AssemblyError::DuplicateLabel {
+            line: 2,
+            label: "a".to_owned()
+        }
+    );
+    assert!(matches!(
+        assemble("ret.panic.gt.lt r0"),
+        Err(AssemblyError::Syntax { line: 1, .. })
+    ));
+    assert!(matches!(
+        assemble("ptr.add! r1, r2, r3"),
+        Err(AssemblyError::Syntax { line: 1, .. })
+    ));
+}
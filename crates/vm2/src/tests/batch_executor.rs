@@ -0,0 +1,189 @@
+use std::num::NonZeroUsize;
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{v2::Tracer, GlobalStateInterface, OpcodeType};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    BatchExecutor, BatchWorld, ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const COUNTER_ADDRESS: Address = Address::repeat_byte(0x12);
+const SETTER_ADDRESS: Address = Address::repeat_byte(0x23);
+
+/// Increments the value in storage slot 0.
+const COUNTER_PROGRAM: &str = "
+    log.sread r0, r1
+    add 1, r1, r1
+    log.swrite r0, r1
+    ret.ok r0
+";
+
+/// Sets storage slot 0 to 5 without reading it.
+const SETTER_PROGRAM: &str = "
+    add 5, r0, r1
+    log.swrite r0, r1
+    ret.ok r0
+";
+
+fn executor<T: Tracer>(threads: usize) -> BatchExecutor<T, TestWorld<T>> {
+    let world = TestWorld::new(&[
+        (
+            COUNTER_ADDRESS,
+            Program::new(&assemble(COUNTER_PROGRAM).unwrap(), false),
+        ),
+        (
+            SETTER_ADDRESS,
+            Program::new(&assemble(SETTER_PROGRAM).unwrap(), false),
+        ),
+    ]);
+    BatchExecutor::new(world, NonZeroUsize::new(threads).unwrap(), 16)
+}
+
+fn start_transaction<T: Tracer>(
+    &address: &Address,
+    world: &mut BatchWorld<T, TestWorld<T>>,
+) -> VirtualMachine<T, BatchWorld<T, TestWorld<T>>> {
+    let program = initial_decommit(world, address);
+    VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    )
+}
+
+fn slot_value(vm: &VirtualMachine<(), BatchWorld<(), TestWorld<()>>>, address: Address) -> U256 {
+    vm.world_diff().get_storage_state()[&(address, U256::zero())].value
+}
+
+#[test]
+fn independent_transactions_are_not_reexecuted() {
+    let executor = executor(2);
+    let results = executor.execute(
+        &[COUNTER_ADDRESS, SETTER_ADDRESS],
+        start_transaction,
+        |_| (),
+    );
+
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert!(matches!(result.end, ExecutionEnd::ProgramFinished(_)));
+        assert!(!result.was_reexecuted);
+    }
+    assert_eq!(slot_value(&results[0].vm, COUNTER_ADDRESS), 1.into());
+    assert_eq!(slot_value(&results[1].vm, SETTER_ADDRESS), 5.into());
+    // Each program is decoded once, even though the transactions ran on different threads.
+    assert_eq!(executor.program_cache().stats().misses, 2);
+}
+
+#[test]
+fn conflicting_transactions_are_reexecuted_serially() {
+    let transactions = [COUNTER_ADDRESS; 4];
+    for threads in [1, 2, 8] {
+        let results = executor(threads).execute(&transactions, start_transaction, |_| ());
+
+        let was_reexecuted: Vec<_> = results.iter().map(|result| result.was_reexecuted).collect();
+        assert_eq!(
+            was_reexecuted,
+            [false, true, true, true],
+            "threads = {threads}"
+        );
+        for (i, result) in (1_u64..).zip(&results) {
+            assert!(matches!(result.end, ExecutionEnd::ProgramFinished(_)));
+            assert_eq!(slot_value(&result.vm, COUNTER_ADDRESS), i.into());
+        }
+    }
+}
+
+#[test]
+fn reexecution_sees_writes_of_all_preceding_transactions() {
+    let transactions = [
+        COUNTER_ADDRESS,
+        SETTER_ADDRESS,
+        COUNTER_ADDRESS,
+        SETTER_ADDRESS,
+    ];
+    let results = executor(4).execute(&transactions, start_transaction, |_| ());
+
+    let was_reexecuted: Vec<_> = results.iter().map(|result| result.was_reexecuted).collect();
+    // The setter doesn't read its slot, but overwriting a slot written earlier is still a conflict.
+    assert_eq!(was_reexecuted, [false, false, true, true]);
+    assert_eq!(slot_value(&results[2].vm, COUNTER_ADDRESS), 2.into());
+    assert_eq!(slot_value(&results[3].vm, SETTER_ADDRESS), 5.into());
+    assert!(results[3]
+        .vm
+        .world_diff()
+        .get_storage_state()
+        .get(&(COUNTER_ADDRESS, U256::zero()))
+        .is_none());
+}
+
+#[test]
+fn fused_programs_give_same_results() {
+    let transactions = [COUNTER_ADDRESS, SETTER_ADDRESS, COUNTER_ADDRESS];
+    let results = executor(2).execute(&transactions, start_transaction, |_| ());
+    let fused_results =
+        executor(2)
+            .with_fusion(true)
+            .execute(&transactions, start_transaction, |_| ());
+
+    for (result, fused_result) in results.iter().zip(&fused_results) {
+        assert_eq!(result.end, fused_result.end);
+        assert_eq!(result.was_reexecuted, fused_result.was_reexecuted);
+        assert_eq!(
+            result
+                .vm
+                .world_diff()
+                .get_storage_changes()
+                .collect::<Vec<_>>(),
+            fused_result
+                .vm
+                .world_diff()
+                .get_storage_changes()
+                .collect::<Vec<_>>()
+        );
+    }
+}
+
+#[derive(Debug)]
+struct InstructionCounter {
+    address: Address,
+    count: usize,
+}
+
+impl Tracer for InstructionCounter {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, _: &mut S) {
+        self.count += 1;
+    }
+}
+
+#[test]
+fn each_execution_uses_new_tracer() {
+    let transactions = [COUNTER_ADDRESS, SETTER_ADDRESS, COUNTER_ADDRESS];
+    let results = executor(2).execute(&transactions, start_transaction, |&address| {
+        InstructionCounter { address, count: 0 }
+    });
+
+    assert!(results[2].was_reexecuted);
+    let tracers: Vec<_> = results
+        .iter()
+        .map(|result| (result.tracer.address, result.tracer.count))
+        .collect();
+    assert_eq!(
+        tracers,
+        [
+            (COUNTER_ADDRESS, 4),
+            (SETTER_ADDRESS, 3),
+            (COUNTER_ADDRESS, 4)
+        ]
+    );
+}
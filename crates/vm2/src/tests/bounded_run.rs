@@ -0,0 +1,67 @@
+use super::checkpoint::{create_test_world, new_vm};
+use crate::ExecutionEnd;
+
+#[test]
+fn bounded_runs_are_equivalent_to_uninterrupted_run() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    let mut sliced_vm = new_vm(&mut world);
+    let mut stepped_vm = new_vm(&mut world);
+
+    for _ in 0..3 {
+        let end = vm.run(&mut world, &mut ());
+        assert_eq!(end, ExecutionEnd::SuspendedOnHook(0));
+
+        let mut slices = 0;
+        let sliced_end = loop {
+            match sliced_vm.run_for(&mut world, &mut (), 2) {
+                ExecutionEnd::InstructionLimitReached => slices += 1,
+                end => break end,
+            }
+        };
+        assert!(slices > 0);
+        assert_eq!(sliced_end, end);
+        assert_eq!(sliced_vm.dump_state(), vm.dump_state());
+
+        let stepped_end = loop {
+            if let Some(end) = stepped_vm.step(&mut world, &mut ()) {
+                break end;
+            }
+        };
+        assert_eq!(stepped_end, end);
+        assert_eq!(stepped_vm.dump_state(), vm.dump_state());
+
+        for other_vm in [&sliced_vm, &stepped_vm] {
+            assert_eq!(
+                other_vm
+                    .world_diff()
+                    .get_storage_changes()
+                    .collect::<Vec<_>>(),
+                vm.world_diff().get_storage_changes().collect::<Vec<_>>()
+            );
+        }
+    }
+}
+
+#[test]
+fn empty_budget_does_not_execute_anything() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    let initial_state = vm.dump_state();
+
+    assert_eq!(
+        vm.run_for(&mut world, &mut (), 0),
+        ExecutionEnd::InstructionLimitReached
+    );
+    assert_eq!(vm.dump_state(), initial_state);
+
+    // Stops right after entering the far call
+    assert_eq!(
+        vm.run_for(&mut world, &mut (), 3),
+        ExecutionEnd::InstructionLimitReached
+    );
+    assert_eq!(
+        vm.run_for(&mut world, &mut (), 100),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
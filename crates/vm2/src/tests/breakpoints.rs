@@ -0,0 +1,178 @@
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::v2::Tracer;
+
+use super::checkpoint::{
+    create_test_world, new_vm, new_vm_with_program, StopAfterFarCall, CALLED_ADDRESS, MAIN_ADDRESS,
+};
+use crate::{
+    instruction_handlers::address_into_u256,
+    testonly::{initial_decommit, TestWorld},
+    Breakpoint, ExecutionEnd, VirtualMachine,
+};
+
+fn code_hash<T: Tracer>(world: &TestWorld<T>, address: H160) -> U256 {
+    world.address_to_hash[&address_into_u256(address)]
+}
+
+fn storage_changes<T: Tracer>(vm: &VirtualMachine<T, TestWorld<T>>) -> Vec<U256> {
+    vm.world_diff()
+        .get_storage_changes()
+        .map(|((_, key), _)| key)
+        .collect()
+}
+
+#[test]
+fn breakpoint_stops_before_instruction() {
+    let mut world = create_test_world::<()>();
+    let main_hash = code_hash(&world, MAIN_ADDRESS);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS).with_code_hash(main_hash);
+    let mut vm = new_vm_with_program(program);
+    let mut reference_vm = new_vm(&mut world);
+
+    // Stop at the far call
+    let breakpoint = Breakpoint {
+        code_hash: main_hash,
+        pc: 2,
+    };
+    vm.add_breakpoint(breakpoint);
+    assert_eq!(vm.breakpoints().collect::<Vec<_>>(), [breakpoint]);
+
+    for _ in 0..3 {
+        assert_eq!(
+            vm.run(&mut world, &mut ()),
+            ExecutionEnd::Breakpoint(breakpoint)
+        );
+        assert_eq!(vm.state.current_frame.get_pc_as_u16(), 2);
+        assert!(vm.state.previous_frames.is_empty());
+
+        // Resuming executes the far call normally.
+        assert_eq!(
+            vm.run(&mut world, &mut ()),
+            ExecutionEnd::SuspendedOnHook(0)
+        );
+        assert_eq!(
+            reference_vm.run(&mut world, &mut ()),
+            ExecutionEnd::SuspendedOnHook(0)
+        );
+        assert_eq!(vm.state.registers, reference_vm.state.registers);
+        assert_eq!(
+            vm.state.current_frame.gas,
+            reference_vm.state.current_frame.gas
+        );
+        assert_eq!(storage_changes(&vm), storage_changes(&reference_vm));
+    }
+}
+
+#[test]
+fn breakpoints_apply_to_decommitted_programs() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+
+    // Stop at the storage write of the called contract
+    let breakpoint = Breakpoint {
+        code_hash: code_hash(&world, CALLED_ADDRESS),
+        pc: 2,
+    };
+    vm.add_breakpoint(breakpoint);
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    assert_eq!(vm.state.previous_frames.len(), 1);
+    assert!(storage_changes(&vm).is_empty());
+
+    assert_eq!(vm.step(&mut world, &mut ()), None);
+    assert_eq!(storage_changes(&vm), [U256::from(0x_1234_u64)]);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+
+    assert!(vm.remove_breakpoint(breakpoint));
+    assert!(!vm.remove_breakpoint(breakpoint));
+    assert_eq!(vm.breakpoints().count(), 0);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
+
+#[test]
+fn patched_programs_are_reused_across_far_calls() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    let breakpoint = Breakpoint {
+        code_hash: code_hash(&world, CALLED_ADDRESS),
+        pc: 2,
+    };
+    vm.add_breakpoint(breakpoint);
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    let patched_program = vm.state.current_frame.program.clone();
+    assert!(patched_program.has_breakpoints());
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    assert_eq!(vm.state.current_frame.program, patched_program);
+
+    // Changing breakpoints in the program invalidates the patched copy.
+    let other_breakpoint = Breakpoint {
+        pc: 3,
+        ..breakpoint
+    };
+    vm.add_breakpoint(other_breakpoint);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Breakpoint(other_breakpoint)
+    );
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    assert_ne!(vm.state.current_frame.program, patched_program);
+}
+
+#[test]
+fn breakpoints_apply_to_programs_on_callstack() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    assert_eq!(
+        vm.run(&mut world, &mut StopAfterFarCall),
+        ExecutionEnd::StoppedByTracer
+    );
+
+    // Stop at the return of the called contract, which is already executing
+    let breakpoint = Breakpoint {
+        code_hash: code_hash(&world, CALLED_ADDRESS),
+        pc: 3,
+    };
+    vm.add_breakpoint(breakpoint);
+    assert_eq!(
+        vm.run(&mut world, &mut StopAfterFarCall),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    assert_eq!(vm.state.current_frame.get_pc_as_u16(), 3);
+    assert_eq!(storage_changes(&vm), [U256::from(0x_1234_u64)]);
+
+    // Removing the breakpoint unpatches the running program.
+    vm.remove_breakpoint(breakpoint);
+    assert!(!vm.state.current_frame.program.has_breakpoints());
+    assert_eq!(vm.state.current_frame.get_pc_as_u16(), 3);
+    assert_eq!(
+        vm.run(&mut world, &mut StopAfterFarCall),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
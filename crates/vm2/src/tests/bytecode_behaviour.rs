@@ -32,7 +32,7 @@ fn call_to_invalid_address() {
     );
     assert!(matches!(
         vm.run(&mut world, &mut ()),
-        ExecutionEnd::Panicked
+        ExecutionEnd::Panicked(_)
     ));
     assert_eq!(vm.current_frame().gas(), 0);
 }
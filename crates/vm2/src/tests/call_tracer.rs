@@ -0,0 +1,225 @@
+use primitive_types::H160;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    v2::PanicReason, CallframeInterface, CallingMode, ReturnType, StateInterface,
+};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    Call, CallKind, CallTracer, ExecutionEnd, Program, RevertReason, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+const GAS: u32 = 1_000_000;
+
+/// Makes a near call, a far call with 4 bytes of calldata, and a far call that fails because it forwards
+/// a fat pointer from a register that doesn't contain a pointer.
+const MAIN_PROGRAM: &str = "
+    near_call r0, @helper, @fail
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    add code[@forwarding_abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @handler
+handler:
+    ret.ok r0
+helper:
+    ret.ok r0
+fail:
+    ret.panic r0
+
+abi:
+    .cell 0x186a0000000000000000000000004000000000000000000000000
+forwarding_abi:
+    .cell 0x1000186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+/// Returns a 32-byte word ending with `0x1234`.
+const CALLED_PROGRAM: &str = "
+    add 0x1234, r0, r2
+    st.1 r0, r2
+    add code[@abi], r0, r1
+    ret.ok r1
+
+abi:
+    .cell 0x20000000000000000000000000
+";
+
+fn new_vm() -> (
+    VirtualMachine<CallTracer, TestWorld<CallTracer>>,
+    TestWorld<CallTracer>,
+) {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let called_program = Program::new(&assemble(CALLED_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[1, 2, 3],
+        GAS,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    (vm, world)
+}
+
+#[test]
+fn call_tree_is_built() {
+    let (mut vm, mut world) = new_vm();
+    let mut tracer = CallTracer::new();
+    let end = vm.run(&mut world, &mut tracer);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    tracer.finish(&end);
+    let root = tracer.into_call_tree().unwrap();
+
+    assert_eq!(root.kind, CallKind::Far(CallingMode::Normal));
+    assert_eq!(
+        (root.caller, root.address, root.code_address),
+        (Address::zero(), MAIN_ADDRESS, MAIN_ADDRESS)
+    );
+    assert_eq!(root.gas, GAS);
+    assert_eq!(root.gas_used, GAS - vm.current_frame().gas());
+    assert_eq!(root.calldata, [1, 2, 3]);
+    assert_eq!(root.outcome, Some(ReturnType::Normal));
+    assert_eq!(root.calls.len(), 3);
+
+    let near_call = &root.calls[0];
+    assert_eq!(near_call.kind, CallKind::Near);
+    assert_eq!(near_call.address, MAIN_ADDRESS);
+    assert_eq!(near_call.outcome, Some(ReturnType::Normal));
+    assert!(near_call.gas_used > 0 && near_call.gas_used < near_call.gas);
+    assert!(near_call.calldata.is_empty() && near_call.returndata.is_empty());
+
+    let far_call = &root.calls[1];
+    assert_eq!(far_call.kind, CallKind::Far(CallingMode::Normal));
+    assert_eq!(
+        (far_call.caller, far_call.address, far_call.code_address),
+        (MAIN_ADDRESS, CALLED_ADDRESS, CALLED_ADDRESS)
+    );
+    assert_eq!(far_call.gas, 100_000);
+    assert!(far_call.gas_used > 0 && far_call.gas_used < far_call.gas);
+    assert_eq!(far_call.calldata, [0; 4]);
+    let mut expected_returndata = [0; 32];
+    expected_returndata[30..].copy_from_slice(&[0x12, 0x34]);
+    assert_eq!(far_call.returndata, expected_returndata);
+    assert_eq!(far_call.outcome, Some(ReturnType::Normal));
+    assert_eq!(far_call.panic_reason, None);
+    assert!(far_call.calls.is_empty());
+
+    // The failed call pushes a frame, which panics immediately.
+    let failed_call = &root.calls[2];
+    assert_eq!(failed_call.kind, CallKind::Far(CallingMode::Normal));
+    assert_eq!(failed_call.address, CALLED_ADDRESS);
+    assert_eq!(failed_call.outcome, Some(ReturnType::Panic));
+    assert_eq!(
+        failed_call.panic_reason,
+        Some(PanicReason::InvalidFatPointer)
+    );
+    assert!(failed_call.calldata.is_empty() && failed_call.returndata.is_empty());
+    assert!(failed_call.calls.is_empty());
+}
+
+#[test]
+fn unfinished_calls_have_no_outcome() {
+    assert_eq!(CallTracer::new().into_call_tree(), None);
+
+    let (mut vm, mut world) = new_vm();
+    let mut tracer = CallTracer::new();
+    // Stops in the first far call
+    let end = vm.run_for(&mut world, &mut tracer, 5);
+    assert_eq!(end, ExecutionEnd::InstructionLimitReached);
+    tracer.finish(&end);
+    let root = tracer.into_call_tree().unwrap();
+
+    assert_eq!(root.outcome, None);
+    assert_eq!(root.gas_used, 0);
+    assert_eq!(root.calls.len(), 2);
+    assert_eq!(root.calls[0].outcome, Some(ReturnType::Normal));
+    assert_eq!(root.calls[1].address, CALLED_ADDRESS);
+    assert_eq!(root.calls[1].outcome, None);
+}
+
+#[test]
+fn call_is_serialized_to_json() {
+    let call = Call {
+        kind: CallKind::Far(CallingMode::Delegate),
+        caller: H160::repeat_byte(1),
+        address: H160::repeat_byte(2),
+        code_address: H160::repeat_byte(3),
+        gas: 1000,
+        gas_used: 255,
+        calldata: vec![0xab, 0xcd],
+        returndata: vec![],
+        outcome: Some(ReturnType::Revert),
+        panic_reason: None,
+        calls: vec![Call {
+            kind: CallKind::Near,
+            caller: H160::repeat_byte(1),
+            address: H160::repeat_byte(2),
+            code_address: H160::repeat_byte(3),
+            gas: 100,
+            gas_used: 0,
+            calldata: vec![],
+            returndata: vec![],
+            outcome: None,
+            panic_reason: None,
+            calls: vec![],
+        }],
+    };
+
+    let from = format!("0x{}", "01".repeat(20));
+    let to = format!("0x{}", "02".repeat(20));
+    let code_address = format!("0x{}", "03".repeat(20));
+    let near_call = format!(
+        r#"{{"type":"NEARCALL","from":"{from}","to":"{to}","codeAddress":"{code_address}","gas":"0x64","gasUsed":"0x0","input":"0x","output":"0x","outcome":null,"calls":[]}}"#
+    );
+    assert_eq!(
+        call.to_json(),
+        format!(
+            r#"{{"type":"DELEGATECALL","from":"{from}","to":"{to}","codeAddress":"{code_address}","gas":"0x3e8","gasUsed":"0xff","input":"0xabcd","output":"0x","outcome":"revert","calls":[{near_call}]}}"#
+        )
+    );
+}
+
+#[test]
+fn revert_reason_is_decoded() {
+    // `Panic(0x01)`, i.e. a failed assertion
+    let mut returndata = vec![0x4e, 0x48, 0x7b, 0x71];
+    returndata.extend_from_slice(&[0; 31]);
+    returndata.push(1);
+
+    let end = ExecutionEnd::Reverted(returndata.clone());
+    assert_eq!(end.revert_reason(), Some(RevertReason::Panic(1.into())));
+    assert_eq!(ExecutionEnd::ProgramFinished(vec![]).revert_reason(), None);
+
+    let call = Call {
+        kind: CallKind::Far(CallingMode::Normal),
+        caller: H160::repeat_byte(1),
+        address: H160::repeat_byte(2),
+        code_address: H160::repeat_byte(2),
+        gas: 1000,
+        gas_used: 1000,
+        calldata: vec![],
+        returndata,
+        outcome: Some(ReturnType::Revert),
+        panic_reason: None,
+        calls: vec![],
+    };
+    assert_eq!(call.revert_reason(), Some(RevertReason::Panic(1.into())));
+    assert!(call
+        .to_json()
+        .contains(r#""revertReason":"panic 0x1 (assertion failed)""#));
+}
@@ -0,0 +1,339 @@
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    opcodes,
+    v2::{PanicReason, Tracer},
+    GlobalStateInterface, HeapId, Opcode, OpcodeType, ShouldStop,
+};
+
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+        SSTORE_COST,
+    },
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    CheckpointError, ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings,
+    VirtualMachine, World,
+};
+
+pub(super) const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+pub(super) const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+
+/// Stops the VM right after every far call, i.e. at the first instruction of the callee.
+#[derive(Debug, Default)]
+pub(super) struct StopAfterFarCall;
+
+impl Tracer for StopAfterFarCall {
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        _: &mut S,
+    ) -> ShouldStop {
+        if matches!(OP::VALUE, Opcode::FarCall(_)) {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// The main program repeatedly calls a contract that writes to its heap and storage, and suspends on a hook
+/// after each call.
+pub(super) fn create_test_world<T: Tracer>() -> TestWorld<T> {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+
+    let mut abi = U256::zero();
+    abi.0[3] = 100_000;
+
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 1,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r2).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(r1),
+                Register2(r2),
+                Immediate1(0),
+                false,
+                false,
+                args(200),
+            ),
+            // Hook (0)
+            Instruction::from_heap_write(Register1(r0).into(), Register2(r0), None, args(5), true),
+            Instruction::from_jump(Immediate1(0).into(), Register1(r0), args(5)),
+        ],
+        vec![abi, CALLED_ADDRESS.to_low_u64_be().into()],
+    );
+
+    let called_program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_heap_write(Register1(r1).into(), Register2(r1), None, args(5), false),
+            // Need to use the actual cost to not create free gas from refunds
+            Instruction::from_storage_write(Register1(r1), Register2(r1), args(SSTORE_COST)),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+        ],
+        vec![U256::from(0x_1234_u64)],
+    );
+
+    TestWorld::new(&[
+        (CALLED_ADDRESS, called_program),
+        (MAIN_ADDRESS, main_program),
+    ])
+}
+
+pub(super) fn new_vm<T: Tracer>(world: &mut TestWorld<T>) -> VirtualMachine<T, TestWorld<T>> {
+    new_vm_with_program(initial_decommit(world, MAIN_ADDRESS))
+}
+
+pub(super) fn new_vm_with_program<T: Tracer>(
+    program: Program<T, TestWorld<T>>,
+) -> VirtualMachine<T, TestWorld<T>> {
+    VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[1, 2, 3],
+        10_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    )
+}
+
+fn restore<T: Tracer>(
+    bytes: &[u8],
+    world: &mut TestWorld<T>,
+) -> Result<VirtualMachine<T, TestWorld<T>>, CheckpointError> {
+    let program = initial_decommit(world, MAIN_ADDRESS);
+    VirtualMachine::restore_checkpoint(bytes, program, world)
+}
+
+fn assert_same_world_diff<T: Tracer, W: World<T>>(
+    vm: &VirtualMachine<T, W>,
+    restored: &VirtualMachine<T, W>,
+) {
+    assert_eq!(
+        vm.world_diff().get_storage_changes().collect::<Vec<_>>(),
+        restored
+            .world_diff()
+            .get_storage_changes()
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        vm.world_diff().storage_log_queries(),
+        restored.world_diff().storage_log_queries()
+    );
+    assert_eq!(
+        vm.world_diff().storage_refunds(),
+        restored.world_diff().storage_refunds()
+    );
+    assert_eq!(
+        vm.world_diff().pubdata_costs(),
+        restored.world_diff().pubdata_costs()
+    );
+    assert_eq!(
+        vm.world_diff().decommitted_hashes().collect::<Vec<_>>(),
+        restored
+            .world_diff()
+            .decommitted_hashes()
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn checkpoint_at_hook_resumes_identically() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+
+    let checkpoint = vm.save_checkpoint().unwrap();
+    let mut restored = restore(&checkpoint, &mut world).unwrap();
+    assert_eq!(vm.dump_state(), restored.dump_state());
+    assert_same_world_diff(&vm, &restored);
+
+    for _ in 0..3 {
+        let end = vm.run(&mut world, &mut ());
+        assert_eq!(end, restored.run(&mut world, &mut ()));
+        assert_eq!(vm.dump_state(), restored.dump_state());
+        assert_same_world_diff(&vm, &restored);
+    }
+}
+
+#[test]
+fn checkpoint_inside_far_call_resumes_identically() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    let mut tracer = StopAfterFarCall;
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    assert_eq!(vm.state.previous_frames.len(), 1);
+
+    let checkpoint = vm.save_checkpoint().unwrap();
+    let mut restored = restore(&checkpoint, &mut world).unwrap();
+    assert_eq!(vm.dump_state(), restored.dump_state());
+
+    for expected_end in [
+        ExecutionEnd::SuspendedOnHook(0),
+        ExecutionEnd::StoppedByTracer,
+        ExecutionEnd::SuspendedOnHook(0),
+    ] {
+        assert_eq!(vm.run(&mut world, &mut tracer), expected_end);
+        assert_eq!(restored.run(&mut world, &mut tracer), expected_end);
+        assert_eq!(vm.dump_state(), restored.dump_state());
+        assert_same_world_diff(&vm, &restored);
+    }
+}
+
+#[test]
+fn checkpoint_preserves_snapshot() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    vm.make_snapshot();
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+
+    let checkpoint = vm.save_checkpoint().unwrap();
+    let mut restored = restore(&checkpoint, &mut world).unwrap();
+    vm.rollback();
+    restored.rollback();
+    assert_eq!(vm.dump_state(), restored.dump_state());
+    assert_same_world_diff(&vm, &restored);
+    assert_eq!(restored.world_diff().get_storage_changes().count(), 0);
+}
+
+#[test]
+fn checkpoint_preserves_panic_reasons() {
+    let program = "
+        add code[@address], r0, r1
+        st.1 r1, r0
+    address:
+        .cell 0xffffffff
+    ";
+    let program = Program::new(&assemble(program).unwrap(), false);
+    let mut world = TestWorld::new(&[(MAIN_ADDRESS, program)]);
+    let mut vm = new_vm(&mut world);
+    // The heap write cannot pay for heap growth, so the next executed instruction is a panic.
+    for _ in 0..2 {
+        assert_eq!(vm.step(&mut world, &mut ()), None);
+    }
+
+    let checkpoint = vm.save_checkpoint().unwrap();
+    let mut restored = restore(&checkpoint, &mut world).unwrap();
+    assert_eq!(
+        restored.run(&mut world, &mut ()),
+        ExecutionEnd::Panicked(Some(PanicReason::HeapGrowthOverflow))
+    );
+}
+
+#[test]
+fn programs_not_known_to_world_are_rejected() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    let program = Program::from_raw(
+        vec![Instruction::from_ret(
+            Register1(Register::new(0)),
+            None,
+            args(5),
+        )],
+        vec![],
+    );
+    let world_before_this_frame = vm.world_diff.snapshot();
+    vm.push_frame::<opcodes::Normal>(
+        CALLED_ADDRESS,
+        program,
+        1_000,
+        0,
+        false,
+        false,
+        HeapId::FIRST_CALLDATA,
+        world_before_this_frame,
+    );
+
+    assert_eq!(
+        vm.save_checkpoint(),
+        Err(CheckpointError::UnknownProgram { frame: 1 })
+    );
+}
+
+#[test]
+fn invalid_checkpoints_are_rejected() {
+    let mut world = create_test_world::<()>();
+    let vm = new_vm(&mut world);
+    let checkpoint = vm.save_checkpoint().unwrap();
+
+    let mut bad_magic = checkpoint.clone();
+    bad_magic[0] ^= 1;
+    assert_eq!(
+        restore(&bad_magic, &mut world).unwrap_err(),
+        CheckpointError::BadMagic
+    );
+
+    let mut bad_version = checkpoint.clone();
+    bad_version[4] = 0xff;
+    assert!(matches!(
+        restore(&bad_version, &mut world).unwrap_err(),
+        CheckpointError::UnsupportedVersion(_)
+    ));
+
+    assert_eq!(
+        restore(&checkpoint[..checkpoint.len() - 1], &mut world).unwrap_err(),
+        CheckpointError::UnexpectedEnd
+    );
+
+    let mut trailing = checkpoint;
+    trailing.push(0);
+    assert_eq!(
+        restore(&trailing, &mut world).unwrap_err(),
+        CheckpointError::TrailingBytes
+    );
+}
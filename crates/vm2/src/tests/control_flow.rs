@@ -0,0 +1,125 @@
+use crate::{
+    assemble, disassemble, testonly::TestWorld, ControlFlowGraph, Edge, EdgeKind, Program,
+};
+
+const PROGRAM: &str = "
+    add 2, r0, r1
+    near_call r0, @callee, @handler
+loop:
+    sub.s! 1, r1, r1
+    jump.ne @loop
+    far_call r1, r2, @handler
+    ret.ok r0
+callee:
+    ret.ok r0
+handler:
+    ret.panic r0
+    add 1, r0, r1
+    jump r1
+";
+
+fn edge(kind: EdgeKind, target: u16) -> Edge {
+    Edge { kind, target }
+}
+
+#[test]
+fn building_control_flow_graph() {
+    let bytecode = assemble(PROGRAM).unwrap();
+    let program = Program::<(), TestWorld<()>>::new(&bytecode, false);
+    let graph = ControlFlowGraph::new(&program);
+
+    let ranges: Vec<_> = graph
+        .blocks()
+        .iter()
+        .map(|block| (block.first_pc, block.last_pc))
+        .collect();
+    // The last two blocks consist of `invalid` padding.
+    assert_eq!(
+        ranges,
+        [
+            (0, 1),
+            (2, 3),
+            (4, 4),
+            (5, 5),
+            (6, 6),
+            (7, 7),
+            (8, 9),
+            (10, 10),
+            (11, 11)
+        ]
+    );
+
+    let blocks = graph.blocks();
+    assert_eq!(
+        blocks[0].successors,
+        [
+            edge(EdgeKind::NearCall, 6),
+            edge(EdgeKind::ExceptionHandler, 7),
+            edge(EdgeKind::Fallthrough, 2),
+        ]
+    );
+    assert_eq!(
+        blocks[1].successors,
+        [edge(EdgeKind::Jump, 2), edge(EdgeKind::Fallthrough, 4)]
+    );
+    assert_eq!(
+        blocks[2].successors,
+        [
+            edge(EdgeKind::ExceptionHandler, 7),
+            edge(EdgeKind::Fallthrough, 5)
+        ]
+    );
+    assert!(blocks[3].successors.is_empty());
+    assert!(blocks[6].has_dynamic_jump);
+    assert!(!blocks[5].has_dynamic_jump);
+
+    let unreachable: Vec<_> = graph
+        .unreachable_blocks()
+        .map(|block| block.first_pc)
+        .collect();
+    assert_eq!(unreachable, [8, 10, 11]);
+    assert_eq!(graph.far_call_sites(), [4]);
+    assert_eq!(graph.block_at(3), Some(&blocks[1]));
+    assert_eq!(graph.block_at(12), None);
+
+    let ergs: Vec<u64> = disassemble(&bytecode)
+        .iter()
+        .map(|instruction| {
+            let line = instruction.to_string();
+            let (_, ergs) = line.split_once(" ; ergs: ").unwrap();
+            ergs.parse().unwrap()
+        })
+        .collect();
+    for block in blocks {
+        let expected_ergs: u64 = ergs[usize::from(block.first_pc)..=usize::from(block.last_pc)]
+            .iter()
+            .sum();
+        assert_eq!(block.static_ergs, expected_ergs, "{block:?}");
+    }
+}
+
+#[test]
+fn linting_out_of_range_labels() {
+    let bytecode = assemble(
+        "
+        jump.eq 100
+        near_call r0, @end, 10
+    end:
+        ret.ok r0
+        .cell 0x1234
+        ",
+    )
+    .unwrap();
+    let program = Program::<(), TestWorld<()>>::new(&bytecode, false);
+    let graph = ControlFlowGraph::new(&program);
+
+    // Constants decoded as instructions are unreachable, so labels in them aren't reported.
+    let edges: Vec<_> = graph.out_of_range_edges().collect();
+    assert_eq!(
+        edges,
+        [
+            (0, edge(EdgeKind::Jump, 100)),
+            (1, edge(EdgeKind::ExceptionHandler, 10))
+        ]
+    );
+}
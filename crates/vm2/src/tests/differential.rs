@@ -41,23 +41,49 @@
 //! return performs. That is read off the handler. Executing `zk_evm`'s `ret` would need a full
 //! `VmState` with its oracles plus a raw encoding of the instruction, which the real
 //! [`Program`](crate::Program) does not carry.
+//!
+//! # Superinstructions
+//!
+//! The fused-pair tests at the bottom are different: they do execute `zk_evm`. The programs only touch
+//! registers, flags, the stack and storage reads, so a `VmState` with a code page, a stack and a
+//! storage returning zeros (like [`TestWorld`] does for non-system contracts) is enough to run them.
+//! Both VMs start from the same state and stop at the same program counter, and everything those
+//! instructions can change is compared.
+
+use std::collections::BTreeMap;
 
 use primitive_types::{H160, U256};
 use zk_evm::{
-    reference_impls::memory::SimpleMemory, zkevm_opcode_defs::FatPointer as ZkFatPointer,
+    abstractions::{
+        DecommittmentProcessor, MemoryType, PrecompileCyclesWitness, PrecompilesProcessor, Storage,
+        StorageAccessRefund,
+    },
+    aux_structures::{DecommittmentQuery, LogQuery, MemoryQuery, PubdataCost},
+    block_properties::BlockProperties,
+    reference_impls::{event_sink::InMemoryEventSink, memory::SimpleMemory},
+    tracing,
+    vm_state::{
+        execution_stack::CallStackEntry, Callstack, PrimitiveValue, Version, VmLocalState, VmState,
+    },
+    witness_trace::VmWitnessTracer,
+    zkevm_opcode_defs::FatPointer as ZkFatPointer,
 };
 use zk_evm_abstractions::{
     aux::{MemoryPage, Timestamp},
-    vm::Memory,
+    vm::{EventSink, Memory},
+};
+use zkevm_opcode_defs::decoding::EncodingModeProduction;
+use zksync_vm2_interface::{
+    opcodes, v2::Tracer, CallframeInterface, GlobalStateInterface, HeapId, Opcode, OpcodeType,
+    ShouldStop, StateInterface,
 };
-use zksync_vm2_interface::{opcodes, HeapId};
 
 use super::divergence_regressions::{
     execute_one_instruction, kernel_address, load_forward_ret_abi, ret_r1_instruction,
 };
 use crate::{
-    heap::Heaps, page_ids::base_page_from_heap, testonly::TestWorld, FatPointer, Program, Settings,
-    VirtualMachine,
+    assemble, heap::Heaps, instruction_handlers::superinstruction, page_ids::base_page_from_heap,
+    testonly::TestWorld, ExecutionEnd, FatPointer, Program, Settings, VirtualMachine,
 };
 
 /// 32-byte words mirrored out of a vm2 heap page. 8 KiB covers every offset these tests use.
@@ -140,8 +166,8 @@ fn assert_word_eq(heaps: &Heaps, memory: &SimpleMemory, page: HeapId, offset: u3
     );
 }
 
-fn kernel_vm() -> (VirtualMachine<(), TestWorld<()>>, TestWorld<()>) {
-    let program: Program<(), TestWorld<()>> = Program::from_raw(vec![ret_r1_instruction()], vec![]);
+fn kernel_vm<T: Tracer>() -> (VirtualMachine<T, TestWorld<T>>, TestWorld<T>) {
+    let program: Program<T, TestWorld<T>> = Program::from_raw(vec![ret_r1_instruction()], vec![]);
     let vm = VirtualMachine::new(
         kernel_address(),
         program,
@@ -161,7 +187,7 @@ fn kernel_vm() -> (VirtualMachine<(), TestWorld<()>>, TestWorld<()>) {
 /// [`mirror_page`] means anything.
 #[test]
 fn mirroring_a_vm2_heap_into_zk_evm_memory_round_trips() {
-    let (mut vm, _world) = kernel_vm();
+    let (mut vm, _world) = kernel_vm::<()>();
     let page = vm.state.current_frame.heap;
 
     for (i, offset) in [BELOW_WINDOW, IN_WINDOW, ABOVE_WINDOW]
@@ -192,7 +218,7 @@ fn mirroring_a_vm2_heap_into_zk_evm_memory_round_trips() {
 /// `compact_to_window` skips as `is_always_allocated`, so the bug cannot show up there.
 #[test]
 fn kernel_ret_forward_matches_zk_evm_on_a_live_callers_heap() {
-    let (mut vm, mut world) = kernel_vm();
+    let (mut vm, mut world) = kernel_vm::<()>();
     let program: Program<(), TestWorld<()>> = Program::from_raw(vec![ret_r1_instruction()], vec![]);
 
     // E -> A. A is the victim: still live when B returns.
@@ -278,3 +304,604 @@ fn kernel_ret_forward_matches_zk_evm_on_a_live_callers_heap() {
         "byte above the window, in a live frame's heap",
     );
 }
+
+/// Kernel callee for [`kernel_ret_forward_from_decoded_program_matches_zk_evm`]. The `add` and the `jump`
+/// form a fused pair, so with fusion enabled the return is reached through a superinstruction.
+const FORWARDING_CALLEE: &str = "
+    add 1, r0, r2
+    jump @exit
+exit:
+    ret.ok r1
+";
+
+/// Stops the VM after the first return, i.e. once the callee has returned to the victim frame.
+#[derive(Debug, Default)]
+struct StopAfterReturn;
+
+impl Tracer for StopAfterReturn {
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        _: &mut S,
+    ) -> ShouldStop {
+        if matches!(OP::VALUE, Opcode::Ret(_)) {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+/// [`kernel_ret_forward_matches_zk_evm_on_a_live_callers_heap`] with `B` decoded from bytecode and
+/// [run](VirtualMachine::run()) rather than stepped by hand, both with and without superinstructions.
+/// Fusion must not change the memory effect of the far return.
+#[test]
+fn kernel_ret_forward_from_decoded_program_matches_zk_evm() {
+    let callee_words: Vec<_> = assemble(FORWARDING_CALLEE)
+        .unwrap()
+        .chunks_exact(32)
+        .map(U256::from_big_endian)
+        .collect();
+
+    for fuse_instructions in [false, true] {
+        let (mut vm, mut world) = kernel_vm::<StopAfterReturn>();
+        let victim_program = Program::from_raw(vec![ret_r1_instruction()], vec![]);
+        let callee_program =
+            Program::from_words_with_fusion(callee_words.clone(), false, fuse_instructions);
+
+        vm.push_frame::<opcodes::Normal>(
+            kernel_address(),
+            victim_program,
+            400_000,
+            0,
+            false,
+            false,
+            vm.state.current_frame.calldata_heap,
+            vm.world_diff.snapshot(),
+        );
+        let victim_heap = vm.state.current_frame.heap;
+        for offset in [BELOW_WINDOW, IN_WINDOW, ABOVE_WINDOW] {
+            vm.state
+                .heaps
+                .write_u256(victim_heap, offset, U256::from(0xdead_beef_u64));
+        }
+
+        vm.push_frame::<opcodes::Normal>(
+            kernel_address(),
+            callee_program,
+            200_000,
+            0,
+            false,
+            false,
+            victim_heap,
+            vm.world_diff.snapshot(),
+        );
+        let dying_heap = vm.state.current_frame.heap;
+        let dying_address = vm.state.current_frame.address;
+        let mut zk_memory = mirror_page(&vm.state.heaps, victim_heap);
+
+        load_forward_ret_abi(&mut vm, victim_heap, IN_WINDOW, 32);
+        assert_eq!(
+            vm.run(&mut world, &mut StopAfterReturn),
+            ExecutionEnd::StoppedByTracer,
+            "fuse_instructions = {fuse_instructions}"
+        );
+
+        let returned = FatPointer::from(vm.state.registers[1]);
+        assert_eq!(
+            (returned.memory_page, returned.start, returned.length),
+            (victim_heap, IN_WINDOW, 32),
+            "fuse_instructions = {fuse_instructions}"
+        );
+        assert_eq!(vm.state.current_frame.heap, victim_heap);
+
+        zk_evm_far_return(
+            &mut zk_memory,
+            dying_heap,
+            dying_address,
+            &returned,
+            &[victim_heap],
+        );
+        for offset in [BELOW_WINDOW, IN_WINDOW, ABOVE_WINDOW] {
+            assert_word_eq(
+                &vm.state.heaps,
+                &zk_memory,
+                victim_heap,
+                offset,
+                &format!("fuse_instructions = {fuse_instructions}"),
+            );
+        }
+    }
+}
+
+/// Wraps each fused pair so that both VMs stop at `end` (instruction [`END_PC`]) whatever the pair does:
+/// it is the exception handler of the near call containing the pair, the destination of the jumps
+/// in the pairs, and where execution goes after the near call returns normally. `{gas}` is the gas
+/// passed to the near call, so small values make the pair run out of gas. The stack starts with
+/// four (zero) values so that pairs can pop.
+const FUSED_PAIR_TEMPLATE: &str = "
+    nop stack+=[4]
+    add 3, r0, r2
+    add {gas}, r0, r1
+    near_call r1, @body, @end
+    jump @end
+end:
+    ret.ok r0
+body:
+    {first}
+    {second}
+    ret.ok r0
+";
+
+const END_PC: u16 = 5;
+const FIRST_PC: u16 = 6;
+const FUSED_PAIR_ADDRESS: H160 = H160::repeat_byte(0x12);
+
+/// Gas passed to the near call: `0` passes all of it, `5` isn't enough for the first instruction
+/// of any pair, and `6` is enough for the first `add` or `nop`, but not the second one.
+const NEAR_CALL_GAS: [u32; 3] = [0, 5, 6];
+
+/// Page of the code in `zk_evm`. It is different from the (zero) page of the cached code word,
+/// so `zk_evm` starts by reading the code page rather than using the cache.
+const ZK_EVM_CODE_PAGE: u32 = 1;
+
+/// Stack slots compared after execution. The pairs push at most two values.
+const COMPARED_STACK_SLOTS: u16 = 8;
+
+/// Stops the VM as soon as it reaches [`END_PC`].
+#[derive(Debug, Default)]
+struct StopAtEnd;
+
+impl Tracer for StopAtEnd {
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        if state.current_frame().program_counter() == Some(END_PC) {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+/// Everything the fused pairs can change.
+#[derive(Debug, PartialEq)]
+struct PairOutcome {
+    registers: Vec<(U256, bool)>,
+    flags: (bool, bool, bool),
+    pc: u16,
+    sp: u16,
+    gas: u32,
+    contained_gas: u32,
+    near_call_depth: usize,
+    stack: Vec<(U256, bool)>,
+}
+
+type ZkEvmState = VmState<
+    ZeroStorage,
+    CodeAndStack,
+    InMemoryEventSink,
+    NoOracle,
+    NoDecommitter,
+    NoOracle,
+    8,
+    EncodingModeProduction,
+>;
+
+/// Code page and stack of the single frame. Heaps aren't touched by the fused pairs.
+#[derive(Debug)]
+struct CodeAndStack {
+    code_page: Vec<U256>,
+    stack: BTreeMap<u32, (U256, bool)>,
+}
+
+impl Memory for CodeAndStack {
+    fn execute_partial_query(&mut self, _: u32, mut query: MemoryQuery) -> MemoryQuery {
+        assert!(
+            matches!(query.location.memory_type, MemoryType::Stack),
+            "fused pairs only access the stack"
+        );
+        let slot = query.location.index.0;
+        if query.rw_flag {
+            self.stack
+                .insert(slot, (query.value, query.value_is_pointer));
+        } else {
+            (query.value, query.value_is_pointer) =
+                self.stack.get(&slot).copied().unwrap_or_default();
+        }
+        query
+    }
+
+    fn specialized_code_query(&mut self, _: u32, _: MemoryQuery) -> MemoryQuery {
+        unimplemented!("fused pairs don't decommit code")
+    }
+
+    fn read_code_query(&self, _: u32, mut query: MemoryQuery) -> MemoryQuery {
+        query.value = self
+            .code_page
+            .get(query.location.index.0 as usize)
+            .copied()
+            .unwrap_or_default();
+        query
+    }
+}
+
+/// Storage that reads zeros, like [`TestWorld`] for [`FUSED_PAIR_ADDRESS`], and always cold.
+#[derive(Debug)]
+struct ZeroStorage;
+
+impl Storage for ZeroStorage {
+    fn get_access_refund(&mut self, _: u32, _: &LogQuery) -> StorageAccessRefund {
+        StorageAccessRefund::Cold
+    }
+
+    fn execute_partial_query(&mut self, _: u32, mut query: LogQuery) -> (LogQuery, PubdataCost) {
+        assert!(!query.rw_flag, "fused pairs don't write storage");
+        query.read_value = U256::zero();
+        (query, PubdataCost(0))
+    }
+
+    fn start_frame(&mut self, _: Timestamp) {}
+
+    fn finish_frame(&mut self, _: Timestamp, _panicked: bool) {}
+
+    fn start_new_tx(&mut self, _: Timestamp) {}
+}
+
+#[derive(Debug)]
+struct NoDecommitter;
+
+impl DecommittmentProcessor for NoDecommitter {
+    fn prepare_to_decommit(
+        &mut self,
+        _: u32,
+        _: DecommittmentQuery,
+    ) -> anyhow::Result<DecommittmentQuery> {
+        unimplemented!("fused pairs don't decommit code")
+    }
+
+    fn decommit_into_memory<M: Memory>(
+        &mut self,
+        _: u32,
+        _: DecommittmentQuery,
+        _: &mut M,
+    ) -> anyhow::Result<Option<Vec<U256>>> {
+        unimplemented!("fused pairs don't decommit code")
+    }
+}
+
+#[derive(Debug)]
+struct NoTracer;
+
+impl tracing::Tracer for NoTracer {
+    type SupportedMemory = CodeAndStack;
+
+    fn before_decoding(
+        &mut self,
+        _: tracing::VmLocalStateData<'_, 8, EncodingModeProduction>,
+        _: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn after_decoding(
+        &mut self,
+        _: tracing::VmLocalStateData<'_, 8, EncodingModeProduction>,
+        _: tracing::AfterDecodingData<8, EncodingModeProduction>,
+        _: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn before_execution(
+        &mut self,
+        _: tracing::VmLocalStateData<'_, 8, EncodingModeProduction>,
+        _: tracing::BeforeExecutionData<8, EncodingModeProduction>,
+        _: &Self::SupportedMemory,
+    ) {
+    }
+
+    fn after_execution(
+        &mut self,
+        _: tracing::VmLocalStateData<'_, 8, EncodingModeProduction>,
+        _: tracing::AfterExecutionData<8, EncodingModeProduction>,
+        _: &Self::SupportedMemory,
+    ) {
+    }
+}
+
+#[derive(Debug)]
+struct NoOracle;
+
+impl PrecompilesProcessor for NoOracle {
+    fn execute_precompile<M: Memory>(
+        &mut self,
+        _: u32,
+        _: LogQuery,
+        _: &mut M,
+    ) -> Option<(Vec<MemoryQuery>, Vec<MemoryQuery>, PrecompileCyclesWitness)> {
+        unimplemented!("fused pairs don't call precompiles")
+    }
+
+    fn start_frame(&mut self) {}
+
+    fn finish_frame(&mut self, _: bool) {}
+}
+
+impl VmWitnessTracer<8, EncodingModeProduction> for NoOracle {
+    fn start_new_execution_cycle(&mut self, _: &VmLocalState<8, EncodingModeProduction>) {}
+
+    fn end_execution_cycle(&mut self, _: &VmLocalState<8, EncodingModeProduction>) {}
+
+    fn add_memory_query(&mut self, _: u32, _: MemoryQuery) {}
+
+    fn record_refund_for_query(&mut self, _: u32, _: LogQuery, _: StorageAccessRefund) {}
+
+    fn add_log_query(&mut self, _: u32, _: LogQuery) {}
+
+    fn record_pubdata_cost_for_query(&mut self, _: u32, _: LogQuery, _: PubdataCost) {}
+
+    fn prepare_for_decommittment(&mut self, _: u32, _: DecommittmentQuery) {}
+
+    fn execute_decommittment(&mut self, _: u32, _: DecommittmentQuery, _: Vec<U256>) {}
+
+    fn add_precompile_call_result(
+        &mut self,
+        _: u32,
+        _: LogQuery,
+        _: Vec<MemoryQuery>,
+        _: Vec<MemoryQuery>,
+        _: PrecompileCyclesWitness,
+    ) {
+    }
+
+    fn add_revertable_precompile_call(&mut self, _: u32, _: LogQuery) {}
+
+    fn start_new_execution_context(
+        &mut self,
+        _: u32,
+        _: &CallStackEntry<8, EncodingModeProduction>,
+        _: &CallStackEntry<8, EncodingModeProduction>,
+    ) {
+    }
+
+    fn finish_execution_context(&mut self, _: u32, _: bool) {}
+}
+
+fn fused_pair_vm(
+    code_page: &[U256],
+    fuse_instructions: bool,
+) -> VirtualMachine<StopAtEnd, TestWorld<StopAtEnd>> {
+    VirtualMachine::new(
+        FUSED_PAIR_ADDRESS,
+        Program::from_words_with_fusion(code_page.to_vec(), false, fuse_instructions),
+        H160::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    )
+}
+
+/// Builds a `zk_evm` state identical to the initial state of `vm`, which must not have run yet.
+fn zk_evm_state(vm: &VirtualMachine<StopAtEnd, TestWorld<StopAtEnd>>) -> ZkEvmState {
+    let state = &vm.state;
+    let frame = &state.current_frame;
+    assert!(state.previous_frames.is_empty() && frame.near_calls.is_empty());
+
+    let mut event_sink = InMemoryEventSink::new();
+    event_sink.start_frame(Timestamp(0));
+
+    VmState {
+        local_state: VmLocalState {
+            previous_code_word: U256::zero(),
+            previous_code_memory_page: MemoryPage(0),
+            registers: state
+                .registers
+                .into_iter()
+                .enumerate()
+                .skip(1)
+                .map(|(i, value)| PrimitiveValue {
+                    value,
+                    is_pointer: state.register_pointer_flags & (1 << i) != 0,
+                })
+                .collect::<Vec<_>>()
+                .try_into()
+                .unwrap(),
+            flags: (&state.flags).into(),
+            timestamp: 0,
+            monotonic_cycle_counter: 0,
+            spent_pubdata_counter: 0,
+            memory_page_counter: 3000,
+            absolute_execution_step: 0,
+            tx_number_in_block: state.transaction_number,
+            pending_exception: false,
+            previous_super_pc: 0,
+            context_u128_register: state.context_u128,
+            callstack: Callstack {
+                current: CallStackEntry {
+                    this_address: frame.address,
+                    msg_sender: frame.caller,
+                    code_address: frame.code_address,
+                    base_memory_page: MemoryPage(base_page_from_heap(frame.heap)),
+                    code_page: MemoryPage(ZK_EVM_CODE_PAGE),
+                    sp: frame.sp,
+                    pc: frame.get_pc_as_u16(),
+                    exception_handler_location: frame.exception_handler,
+                    ergs_remaining: frame.gas,
+                    this_shard_id: 0,
+                    caller_shard_id: 0,
+                    code_shard_id: 0,
+                    is_static: frame.is_static,
+                    is_local_frame: false,
+                    context_u128_value: frame.context_u128,
+                    heap_bound: frame.heap_size,
+                    aux_heap_bound: frame.aux_heap_size,
+                    total_pubdata_spent: PubdataCost(0),
+                    stipend: 0,
+                },
+                // zk_evm requires an unused bottom frame
+                inner: vec![CallStackEntry::empty_context()],
+            },
+            pubdata_revert_counter: PubdataCost(0),
+        },
+        block_properties: BlockProperties {
+            default_aa_code_hash: U256::from_big_endian(&vm.settings.default_aa_code_hash),
+            evm_emulator_code_hash: U256::from_big_endian(&vm.settings.evm_interpreter_code_hash),
+            zkporter_is_available: false,
+        },
+        storage: ZeroStorage,
+        memory: CodeAndStack {
+            code_page: frame.program.code_page().to_vec(),
+            stack: BTreeMap::new(),
+        },
+        event_sink,
+        precompiles_processor: NoOracle,
+        decommittment_processor: NoDecommitter,
+        witness_tracer: NoOracle,
+        version: Version::Version27,
+        dst1_was_updated_this_cycle: false,
+    }
+}
+
+fn vm2_outcome(vm: &VirtualMachine<StopAtEnd, TestWorld<StopAtEnd>>) -> PairOutcome {
+    let state = &vm.state;
+    let frame = &state.current_frame;
+    let flags = zk_evm::flags::Flags::from(&state.flags);
+    PairOutcome {
+        registers: (1..16)
+            .map(|i| {
+                (
+                    state.registers[i],
+                    state.register_pointer_flags & (1 << i) != 0,
+                )
+            })
+            .collect(),
+        flags: (
+            flags.overflow_or_less_than_flag,
+            flags.equality_flag,
+            flags.greater_than_flag,
+        ),
+        pc: frame.get_pc_as_u16(),
+        sp: frame.sp,
+        gas: frame.gas,
+        contained_gas: frame.contained_gas(),
+        near_call_depth: frame.near_calls.len(),
+        stack: (0..COMPARED_STACK_SLOTS)
+            .map(|slot| (frame.stack.get(slot), frame.stack.get_pointer_flag(slot)))
+            .collect(),
+    }
+}
+
+fn zk_evm_outcome(zk_evm: &ZkEvmState) -> PairOutcome {
+    let state = &zk_evm.local_state;
+    let frame = &state.callstack.current;
+    // `inner[0]` is the unused bottom frame, `inner[1]` the far frame, and the rest are near calls.
+    let outer_frames = &state.callstack.inner[1..];
+    PairOutcome {
+        registers: state
+            .registers
+            .iter()
+            .map(|register| (register.value, register.is_pointer))
+            .collect(),
+        flags: (
+            state.flags.overflow_or_less_than_flag,
+            state.flags.equality_flag,
+            state.flags.greater_than_flag,
+        ),
+        pc: frame.pc,
+        sp: frame.sp,
+        gas: frame.ergs_remaining,
+        contained_gas: frame.ergs_remaining
+            + outer_frames
+                .iter()
+                .map(|frame| frame.ergs_remaining)
+                .sum::<u32>(),
+        near_call_depth: outer_frames.len(),
+        stack: (0..u32::from(COMPARED_STACK_SLOTS))
+            .map(|slot| zk_evm.memory.stack.get(&slot).copied().unwrap_or_default())
+            .collect(),
+    }
+}
+
+/// Runs `first` followed by `second` in vm2 (with and without fusion) and `zk_evm`, with every gas
+/// amount in [`NEAR_CALL_GAS`], and compares the outcomes.
+#[track_caller]
+fn assert_fused_pair_matches_zk_evm(first: &str, second: &str) {
+    for gas in NEAR_CALL_GAS {
+        let source = FUSED_PAIR_TEMPLATE
+            .replace("{gas}", &gas.to_string())
+            .replace("{first}", first)
+            .replace("{second}", second);
+        let code_page: Vec<_> = assemble(&source)
+            .unwrap()
+            .chunks_exact(32)
+            .map(U256::from_big_endian)
+            .collect();
+        let raw_instruction = |pc: u16| {
+            let word = code_page[usize::from(pc / 4)];
+            (word >> (64 * (3 - usize::from(pc % 4)))).low_u64()
+        };
+        assert!(
+            superinstruction::<StopAtEnd, TestWorld<StopAtEnd>>(
+                raw_instruction(FIRST_PC),
+                raw_instruction(FIRST_PC + 1)
+            )
+            .is_some(),
+            "`{first}` followed by `{second}` must be fused"
+        );
+
+        let mut zk_evm = zk_evm_state(&fused_pair_vm(&code_page, false));
+        let mut cycles = 0;
+        while zk_evm.local_state.callstack.current.pc != END_PC {
+            assert!(cycles < 16, "zk_evm didn't reach `end`");
+            zk_evm.cycle(&mut NoTracer).expect("zk_evm cycle failed");
+            cycles += 1;
+        }
+        let expected = zk_evm_outcome(&zk_evm);
+
+        for fuse_instructions in [false, true] {
+            let mut vm = fused_pair_vm(&code_page, fuse_instructions);
+            let mut world = TestWorld::new(&[]);
+            assert_eq!(
+                vm.run(&mut world, &mut StopAtEnd),
+                ExecutionEnd::StoppedByTracer
+            );
+            assert_eq!(
+                vm2_outcome(&vm),
+                expected,
+                "`{first}` followed by `{second}`, gas = {gas}, fuse_instructions = {fuse_instructions}"
+            );
+        }
+    }
+}
+
+#[test]
+fn binop_then_jump_matches_zk_evm() {
+    // Taken jump, skipping the rest of the near call
+    assert_fused_pair_matches_zk_evm("add 1, r2, r4", "jump @end");
+    // Jump not taken because of the flags set by the `sub`
+    assert_fused_pair_matches_zk_evm("sub.s! 3, r2, r4", "jump.ne @end");
+    assert_fused_pair_matches_zk_evm("sub! r2, r2, r4", "jump.eq @end");
+}
+
+#[test]
+fn sload_then_binop_matches_zk_evm() {
+    assert_fused_pair_matches_zk_evm("log.sread r2, r4", "add r4, r2, r5");
+    assert_fused_pair_matches_zk_evm("log.sread r2, r4", "sub.s! r4, r2, r5");
+}
+
+#[test]
+fn stack_pair_matches_zk_evm() {
+    assert_fused_pair_matches_zk_evm("add r2, r0, stack+=[1]", "add r1, r0, stack+=[1]");
+    assert_fused_pair_matches_zk_evm("add r2, r0, stack+=[1]", "add stack-=[1], r0, r4");
+    assert_fused_pair_matches_zk_evm("add stack-=[1], r0, r4", "add r2, r0, stack+=[1]");
+    assert_fused_pair_matches_zk_evm("add stack-=[1], r0, r4", "add stack-=[1], r0, r5");
+}
+
+#[test]
+fn nop_pair_matches_zk_evm() {
+    assert_fused_pair_matches_zk_evm("nop stack+=[2]", "nop stack-=[1]");
+    assert_fused_pair_matches_zk_evm("nop", "nop");
+}
@@ -0,0 +1,60 @@
+use crate::{
+    addressing_modes::{Arguments, Register, Register1, Register2},
+    assemble, disassemble,
+    testonly::TestWorld,
+    Instruction, ModeRequirements, Predicate, Program,
+};
+
+#[test]
+fn disassembling_bytecode() {
+    let bytecode = include_bytes!("bytecodes/call_far");
+    let instructions = disassemble(bytecode);
+    assert_eq!(instructions.len(), bytecode.len() / 8);
+    assert_eq!(
+        instructions[0].raw().to_be_bytes(),
+        bytecode[..8],
+        "instructions must be read in the big-endian order"
+    );
+
+    let lines: Vec<_> = instructions.iter().map(ToString::to_string).collect();
+    assert!(
+        lines.iter().any(|line| line.starts_with("far_call ")),
+        "{lines:#?}"
+    );
+    for line in &lines {
+        assert!(line.contains(" ; ergs: "), "{line}");
+    }
+}
+
+#[test]
+fn displaying_instruction() {
+    let instruction = Instruction::<(), TestWorld<()>>::from_add(
+        Register1(Register::new(2)).into(),
+        Register2(Register::new(3)),
+        Register1(Register::new(4)).into(),
+        Arguments::new(Predicate::IfGT, 6, ModeRequirements::new(true, false)),
+        false,
+        false,
+    );
+    assert_eq!(instruction.to_string(), "add.gt r2, r3, r4 ; ergs: 6");
+}
+
+#[test]
+fn displaying_decoded_instructions() {
+    let source = "
+        sub.s.lt! stack-[r1 + 2], r2, r3
+        st.1.inc r1, r2, r3
+        far_call.static r1, r2, @0
+        jump.eq @1
+        ret.panic.to_label r0, @2
+    ";
+    let program = Program::<(), TestWorld<()>>::new(&assemble(source).unwrap(), false);
+    let lines: Vec<_> = (0..5)
+        .map(|pc| program.instruction(pc).unwrap().to_string())
+        .collect();
+    let expected: Vec<_> = disassemble(&assemble(source).unwrap())[..5]
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(lines, expected);
+}
@@ -6,7 +6,7 @@ use zkevm_opcode_defs::{
     Condition, DecodedOpcode, ImmMemHandlerFlags, Opcode, Operand, RegOrImmFlags, UMAOpcode,
     OPCODES_TABLE, UMA_INCREMENT_FLAG_IDX,
 };
-use zksync_vm2_interface::{opcodes, HeapId, Tracer};
+use zksync_vm2_interface::{opcodes, v2::Tracer, HeapId};
 
 use crate::{
     addressing_modes::{
@@ -0,0 +1,141 @@
+use std::{cell::RefCell, rc::Rc};
+
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    v2::Tracer, CallframeInterface, DynStateInterface, DynTracer, GlobalStateInterface, Opcode,
+    OpcodeType, ReturnType, ShouldStop, TracerAdapter,
+};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+
+const MAIN_PROGRAM: &str = "
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    add 1, r0, r3
+    ret.ok r0
+fail:
+    ret.panic r0
+
+abi:
+    .cell 0x186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+const CALLED_PROGRAM: &str = "
+    add 0x1234, r0, r2
+    st.1 r0, r2
+    ret.ok r0
+";
+
+/// Executed opcode together with the gas and the number of frames before the instruction.
+type LogEntry = (Opcode, u32, usize);
+
+/// Dynamic tracer logging executed instructions.
+struct DynLog(Rc<RefCell<Vec<LogEntry>>>);
+
+impl DynTracer for DynLog {
+    fn before_instruction(&mut self, opcode: Opcode, state: &mut dyn DynStateInterface) {
+        let gas = state.current_frame().gas();
+        let entry = (opcode, gas, state.number_of_callframes());
+        self.0.borrow_mut().push(entry);
+    }
+}
+
+/// Static counterpart of `DynLog`.
+#[derive(Debug, Default)]
+struct StaticLog(Rc<RefCell<Vec<LogEntry>>>);
+
+impl Tracer for StaticLog {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let gas = state.current_frame().gas();
+        let entry = (OP::VALUE, gas, state.number_of_callframes());
+        self.0.borrow_mut().push(entry);
+    }
+}
+
+/// Stops the VM once it returns from the far call.
+struct StopAfterFarCall;
+
+impl DynTracer for StopAfterFarCall {
+    fn after_instruction(
+        &mut self,
+        opcode: Opcode,
+        state: &mut dyn DynStateInterface,
+    ) -> ShouldStop {
+        if matches!(opcode, Opcode::Ret(_)) && state.current_frame().address() == MAIN_ADDRESS {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+fn run<T: Tracer>(tracer: &mut T) -> ExecutionEnd {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let called_program = Program::new(&assemble(CALLED_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    vm.run(&mut world, tracer)
+}
+
+#[test]
+fn dynamic_tracers_observe_same_execution_as_static_ones() {
+    let static_log = Rc::default();
+    let end = run(&mut StaticLog(Rc::clone(&static_log)));
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    let static_log = static_log.take();
+    assert_eq!(static_log.len(), 8);
+    let (opcode, gas, number_of_callframes) = static_log[4];
+    assert_eq!((opcode, number_of_callframes), (Opcode::HeapWrite, 2));
+    assert!(gas < 100_000);
+
+    let dyn_log = Rc::default();
+    let adapted_log = Rc::default();
+    let mut tracers: Vec<Box<dyn DynTracer>> = vec![
+        Box::new(DynLog(Rc::clone(&dyn_log))),
+        Box::new(TracerAdapter(StaticLog(Rc::clone(&adapted_log)))),
+    ];
+    let end = run(&mut tracers);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(dyn_log.take(), static_log);
+    assert_eq!(adapted_log.take(), static_log);
+}
+
+#[test]
+fn dynamic_tracer_can_stop_execution() {
+    let log = Rc::default();
+    let mut tracers: Vec<Box<dyn DynTracer>> = vec![
+        Box::new(DynLog(Rc::clone(&log))),
+        Box::new(StopAfterFarCall),
+    ];
+    let end = run(&mut tracers);
+    assert_eq!(end, ExecutionEnd::StoppedByTracer);
+
+    let opcodes: Vec<_> = log.take().into_iter().map(|(opcode, ..)| opcode).collect();
+    assert_eq!(opcodes.len(), 6);
+    assert_eq!(opcodes.last(), Some(&Opcode::Ret(ReturnType::Normal)));
+}
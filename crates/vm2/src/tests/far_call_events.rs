@@ -0,0 +1,129 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    v2::{FarCallEnter, FarCallExit, HeapPointer, Tracer},
+    CallingMode, HeapId, ReturnType,
+};
+
+use self::FarCallEvent::{Enter, Exit};
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+
+/// Far calls `CALLED_ADDRESS`, then far calls the zero address, which fails because it has no code.
+const MAIN_PROGRAM: &str = "
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    far_call r0, r0, @failed_call
+failed_call:
+    ret.ok r0
+fail:
+    ret.panic r0
+
+abi:
+    .cell 0x186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+/// Returns a 32-byte word from its heap.
+const CALLED_PROGRAM: &str = "
+    add 0x1234, r0, r2
+    st.1 r0, r2
+    add code[@returndata], r0, r1
+    ret.ok r1
+
+returndata:
+    .cell 0x20000000000000000000000000
+";
+
+#[derive(Debug)]
+enum FarCallEvent {
+    Enter(FarCallEnter),
+    Exit(FarCallExit),
+}
+
+#[derive(Debug, Default)]
+struct FarCallLog(Vec<FarCallEvent>);
+
+impl Tracer for FarCallLog {
+    fn on_far_call_enter(&mut self, call: FarCallEnter) {
+        self.0.push(Enter(call));
+    }
+
+    fn on_far_call_exit(&mut self, call: FarCallExit) {
+        self.0.push(Exit(call));
+    }
+}
+
+#[test]
+fn far_calls_emit_enter_and_exit_events() {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let called_program = Program::new(&assemble(CALLED_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+
+    let mut tracer = FarCallLog::default();
+    let end = vm.run(&mut world, &mut tracer);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+
+    let [Enter(call), Exit(call_exit), Enter(failed_call), Exit(failed_call_exit)] =
+        tracer.0.as_slice()
+    else {
+        panic!("unexpected events: {:?}", tracer.0);
+    };
+
+    assert_eq!(call.calling_mode, CallingMode::Normal);
+    assert_eq!(call.caller, MAIN_ADDRESS);
+    assert_eq!(call.address, CALLED_ADDRESS);
+    assert_eq!(call.code_address, CALLED_ADDRESS);
+    assert_eq!(call.gas, 100_000);
+    assert!(!call.is_static);
+    assert!(!call.is_failed);
+    assert_eq!(call.calldata.length, 0);
+
+    assert_eq!(call_exit.return_type, ReturnType::Normal);
+    assert_eq!(call_exit.address, CALLED_ADDRESS);
+    assert_eq!(call_exit.code_address, CALLED_ADDRESS);
+    assert!(call_exit.gas_left > 0 && call_exit.gas_left < call.gas);
+    let returndata = call_exit.returndata.expect("no returndata");
+    assert_eq!((returndata.start, returndata.length), (0, 32));
+    assert_ne!(returndata.heap, call.calldata.heap);
+
+    assert!(failed_call.is_failed);
+    assert_eq!(failed_call.address, Address::zero());
+    assert_eq!(failed_call.gas, 0);
+    assert_eq!(
+        failed_call.calldata,
+        HeapPointer {
+            heap: HeapId::from_u32_unchecked(0),
+            start: 0,
+            length: 0,
+            offset: 0,
+        }
+    );
+    assert_eq!(failed_call_exit.return_type, ReturnType::Panic);
+    assert_eq!(failed_call_exit.address, Address::zero());
+    assert_eq!(failed_call_exit.gas_left, 0);
+    assert_eq!(failed_call_exit.returndata, None);
+}
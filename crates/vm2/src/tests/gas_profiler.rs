@@ -0,0 +1,127 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{v2::Tracer, CallingMode, Opcode, ReturnType};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    CallKind, CallTracer, ExecutionEnd, GasProfiler, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+
+/// Calls a helper with a near call, which far calls `CALLED_ADDRESS`.
+const MAIN_PROGRAM: &str = "
+    near_call r0, @helper, @fail
+    ret.ok r0
+helper:
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    ret.ok r0
+fail:
+    ret.panic r0
+
+abi:
+    .cell 0x186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+/// Grows the heap, which is charged to the called frame.
+const CALLED_PROGRAM: &str = "
+    add 0x1000, r0, r1
+    st.1 r1, r0
+    ret.ok r0
+";
+
+fn new_vm<T: Tracer>() -> (VirtualMachine<T, TestWorld<T>>, TestWorld<T>) {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let called_program = Program::new(&assemble(CALLED_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    (vm, world)
+}
+
+#[test]
+fn ergs_are_attributed_to_frames() {
+    let (mut vm, mut world) = new_vm();
+    let mut tracer = (GasProfiler::new(), CallTracer::new());
+    let end = vm.run(&mut world, &mut tracer);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+
+    let (profiler, mut call_tracer) = tracer;
+    call_tracer.finish(&end);
+    let root_call = call_tracer.into_call_tree().unwrap();
+    assert_eq!(root_call.outcome, Some(ReturnType::Normal));
+
+    let frames = profiler.frames();
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].kind, CallKind::Far(CallingMode::Normal));
+    assert_eq!(frames[0].parent, None);
+    assert_eq!(frames[1].kind, CallKind::Near);
+    assert_eq!(frames[1].parent, Some(0));
+    assert_eq!(frames[1].entry_pc, Some(2));
+    assert_eq!(frames[2].kind, CallKind::Far(CallingMode::Normal));
+    assert_eq!(frames[2].parent, Some(1));
+    assert_eq!(frames[2].address, CALLED_ADDRESS);
+    assert_ne!(frames[0].code_hash, frames[2].code_hash);
+
+    // Inclusive ergs match the gas used reported by the call tracer and include nested frames.
+    let near_call = &root_call.calls[0];
+    let far_call = &near_call.calls[0];
+    assert_eq!(frames[0].inclusive, Some(root_call.gas_used.into()));
+    assert_eq!(frames[1].inclusive, Some(near_call.gas_used.into()));
+    assert_eq!(frames[2].inclusive, Some(far_call.gas_used.into()));
+    assert_eq!(frames[2].inclusive, Some(frames[2].exclusive));
+    assert_eq!(
+        frames[1].inclusive,
+        Some(frames[1].exclusive + frames[2].exclusive)
+    );
+    assert_eq!(profiler.total_ergs(), frames[0].inclusive.unwrap());
+    // Memory growth is included into the cost of the heap write.
+    let heap_write = profiler.opcodes()[&Opcode::HeapWrite];
+    assert_eq!(heap_write.count, 1);
+    assert!(heap_write.ergs > 10);
+
+    let contract_ergs: u64 = profiler.contracts().values().map(|stats| stats.ergs).sum();
+    assert_eq!(contract_ergs, profiler.total_ergs());
+    let called_contract = profiler.contracts()[&(CALLED_ADDRESS, frames[2].code_hash)];
+    assert_eq!(called_contract.count, 3);
+    assert_eq!(called_contract.ergs, frames[2].exclusive);
+    let pc_ergs: u64 = profiler
+        .program_counters()
+        .values()
+        .map(|stats| stats.ergs)
+        .sum();
+    assert_eq!(pc_ergs, profiler.total_ergs());
+
+    let main = format!("{MAIN_ADDRESS:?}");
+    let called = format!("{CALLED_ADDRESS:?}");
+    assert_eq!(
+        profiler.folded_stacks(),
+        format!(
+            "{main} {}\n{main};{main}@2 {}\n{main};{main}@2;{called} {}\n",
+            frames[0].exclusive, frames[1].exclusive, frames[2].exclusive
+        )
+    );
+
+    let report = profiler.report(2);
+    assert!(report.starts_with(&format!("Total ergs: {}\n", profiler.total_ergs())));
+    assert!(report.contains(&format!("{main};{main}@2;{called}")));
+}
@@ -1,8 +1,28 @@
 //! Low-level VM tests.
 
+mod assembler;
+mod batch_executor;
+mod bounded_run;
+mod breakpoints;
 mod bytecode_behaviour;
+mod call_tracer;
+mod checkpoint;
+mod control_flow;
 mod differential;
+mod disassembler;
 mod divergence_regressions;
+mod dyn_tracer;
 mod far_call_decommitment;
+mod far_call_events;
+mod gas_profiler;
+mod nested_snapshots;
 mod panic;
+mod panic_reasons;
+mod program_cache;
+mod program_validation;
+mod recording;
+mod step_tracer;
+mod storage_access_events;
+mod superinstructions;
 mod trace_failing_far_call;
+mod watchpoints;
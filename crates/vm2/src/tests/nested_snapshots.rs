@@ -0,0 +1,351 @@
+use primitive_types::{H160, U256};
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{opcodes, v2::Tracer};
+
+use super::checkpoint::StopAfterFarCall;
+use crate::{
+    addressing_modes::{
+        Arguments, CodePage, Immediate1, Register, Register1, Register2, RegisterAndImmediate,
+        SSTORE_COST,
+    },
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xee, 0xee, 0xee, 0xee,
+]);
+const COUNTER_ADDRESS: u16 = 32;
+
+fn args(gas: u32) -> Arguments {
+    Arguments::new(Predicate::Always, gas, ModeRequirements::none())
+}
+
+/// Each "transaction" of the bootloader increments a counter on its heap, writes the counter to storage,
+/// calls a contract (which writes to its storage and leaves its returndata heap on the bootloader frame)
+/// and suspends on a hook.
+fn create_test_world<T: Tracer>() -> TestWorld<T> {
+    let r0 = Register::new(0);
+    let r1 = Register::new(1);
+    let r2 = Register::new(2);
+    let r3 = Register::new(3);
+
+    let mut abi = U256::zero();
+    abi.0[3] = 100_000;
+
+    let main_program = Program::from_raw(
+        vec![
+            Instruction::from_heap_read(
+                Immediate1(COUNTER_ADDRESS).into(),
+                Register1(r3),
+                None,
+                args(5),
+            ),
+            Instruction::from_add(
+                Immediate1(1).into(),
+                Register2(r3),
+                Register1(r3).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_heap_write(
+                Immediate1(COUNTER_ADDRESS).into(),
+                Register2(r3),
+                None,
+                args(5),
+                false,
+            ),
+            // Need to use the actual cost to not create free gas from refunds
+            Instruction::from_storage_write(Register1(r3), Register2(r3), args(SSTORE_COST)),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 1,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r2).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_far_call::<opcodes::Normal>(
+                Register1(r1),
+                Register2(r2),
+                Immediate1(0),
+                false,
+                false,
+                args(200),
+            ),
+            // Hook (0)
+            Instruction::from_heap_write(Register1(r0).into(), Register2(r0), None, args(5), true),
+            Instruction::from_jump(Immediate1(0).into(), Register1(r0), args(5)),
+        ],
+        vec![abi, CALLED_ADDRESS.to_low_u64_be().into()],
+    );
+
+    let called_program = Program::from_raw(
+        vec![
+            Instruction::from_add(
+                CodePage(RegisterAndImmediate {
+                    immediate: 0,
+                    register: r0,
+                })
+                .into(),
+                Register2(r0),
+                Register1(r1).into(),
+                args(6),
+                false,
+                false,
+            ),
+            Instruction::from_storage_write(Register1(r1), Register2(r1), args(SSTORE_COST)),
+            Instruction::from_ret(Register1(r0), None, args(5)),
+        ],
+        vec![U256::from(0x_1234_u64)],
+    );
+
+    TestWorld::new(&[
+        (CALLED_ADDRESS, called_program),
+        (MAIN_ADDRESS, main_program),
+    ])
+}
+
+fn new_vm<T: Tracer>(world: &mut TestWorld<T>) -> VirtualMachine<T, TestWorld<T>> {
+    let program = initial_decommit(world, MAIN_ADDRESS);
+    VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        10_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    )
+}
+
+fn run_tx<T: Tracer + Default>(vm: &mut VirtualMachine<T, TestWorld<T>>, world: &mut TestWorld<T>) {
+    assert_eq!(
+        vm.run(world, &mut T::default()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
+
+/// Returns storage keys changed in the bootloader storage.
+fn changed_keys<T: Tracer>(vm: &VirtualMachine<T, TestWorld<T>>) -> Vec<u64> {
+    changed_keys_of(vm, MAIN_ADDRESS)
+}
+
+fn changed_keys_of<T: Tracer>(vm: &VirtualMachine<T, TestWorld<T>>, address: H160) -> Vec<u64> {
+    vm.world_diff()
+        .get_storage_changes()
+        .filter(|((changed_address, _), _)| *changed_address == address)
+        .map(|((_, key), _)| key.as_u64())
+        .collect()
+}
+
+#[test]
+fn snapshots_are_rolled_back_in_lifo_order() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+
+    vm.make_snapshot();
+    let state_before_batch = vm.dump_state();
+    run_tx(&mut vm, &mut world);
+
+    vm.make_snapshot();
+    let state_before_tx = vm.dump_state();
+    run_tx(&mut vm, &mut world);
+    assert_eq!(changed_keys(&vm), [1, 2]);
+
+    vm.rollback();
+    assert_eq!(vm.dump_state(), state_before_tx);
+    assert_eq!(changed_keys(&vm), [1]);
+
+    // Committing the inner snapshot keeps its changes rollbackable via the outer one.
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    vm.pop_snapshot();
+    assert_eq!(changed_keys(&vm), [1, 2]);
+
+    vm.rollback();
+    assert_eq!(vm.dump_state(), state_before_batch);
+    assert!(changed_keys(&vm).is_empty());
+
+    run_tx(&mut vm, &mut world);
+    assert_eq!(changed_keys(&vm), [1]);
+}
+
+#[test]
+fn returndata_heaps_are_reclaimed_when_outermost_snapshot_is_popped() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    assert_eq!(vm.state.current_frame.heaps_i_am_keeping_alive.len(), 2);
+
+    vm.pop_snapshot();
+    assert_eq!(vm.state.current_frame.heaps_i_am_keeping_alive.len(), 2);
+
+    vm.rollback();
+    assert!(vm.state.current_frame.heaps_i_am_keeping_alive.is_empty());
+    assert!(changed_keys(&vm).is_empty());
+
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    vm.pop_snapshot();
+    assert!(vm.state.current_frame.heaps_i_am_keeping_alive.is_empty());
+    assert_eq!(changed_keys(&vm), [1]);
+}
+
+#[test]
+#[should_panic(expected = "`rollback()` called without a snapshot")]
+fn rollback_past_outermost_snapshot_panics() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+
+    vm.make_snapshot();
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    vm.rollback();
+    vm.rollback();
+    vm.rollback();
+}
+
+#[test]
+fn snapshot_inside_far_call_restores_callstack() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    let mut tracer = StopAfterFarCall;
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    assert_eq!(vm.state.previous_frames.len(), 1);
+
+    vm.make_snapshot();
+    let state_in_call = vm.dump_state();
+    run_tx(&mut vm, &mut world);
+    assert_eq!(changed_keys_of(&vm, CALLED_ADDRESS), [0x1234]);
+    assert!(vm.state.previous_frames.is_empty());
+
+    vm.rollback();
+    assert_eq!(vm.dump_state(), state_in_call);
+    assert_eq!(vm.state.previous_frames.len(), 1);
+    assert!(changed_keys_of(&vm, CALLED_ADDRESS).is_empty());
+    assert_eq!(changed_keys(&vm), [1]);
+
+    // Execution resumes from the restored far call.
+    run_tx(&mut vm, &mut world);
+    assert_eq!(changed_keys_of(&vm, CALLED_ADDRESS), [0x1234]);
+    assert_eq!(changed_keys(&vm), [1]);
+}
+
+#[test]
+fn bootloader_snapshot_can_be_rolled_back_inside_far_call() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    let mut tracer = StopAfterFarCall;
+
+    vm.make_snapshot();
+    let initial_state = vm.dump_state();
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+
+    // A nested snapshot committed inside the far call is rolled back together with the enclosing one.
+    vm.make_snapshot();
+    run_tx(&mut vm, &mut world);
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    vm.pop_snapshot();
+    assert_eq!(changed_keys(&vm), [1, 2]);
+
+    vm.rollback();
+    assert_eq!(vm.dump_state(), initial_state);
+    assert!(vm.state.previous_frames.is_empty());
+    assert!(vm.world_diff().get_storage_changes().next().is_none());
+
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    run_tx(&mut vm, &mut world);
+    assert_eq!(changed_keys(&vm), [1]);
+    assert_eq!(changed_keys_of(&vm, CALLED_ADDRESS), [0x1234]);
+}
+
+#[test]
+fn returndata_heaps_are_reclaimed_on_return_to_bootloader_after_popping_inside_far_call() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    let mut tracer = StopAfterFarCall;
+
+    vm.make_snapshot();
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    run_tx(&mut vm, &mut world);
+    let [first_returndata_heap] = vm.state.current_frame.heaps_i_am_keeping_alive[..] else {
+        panic!("expected a single returndata heap");
+    };
+
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    vm.pop_snapshot();
+    // The far call can still revert, so nothing is freed until it returns.
+    assert!(vm.state.heaps.contains(first_returndata_heap));
+
+    run_tx(&mut vm, &mut world);
+    let [second_returndata_heap] = vm.state.current_frame.heaps_i_am_keeping_alive[..] else {
+        panic!("expected a single returndata heap");
+    };
+    assert_ne!(second_returndata_heap, first_returndata_heap);
+    assert!(!vm.state.heaps.contains(first_returndata_heap));
+    assert!(vm.state.heaps.contains(second_returndata_heap));
+    assert_eq!(changed_keys(&vm), [1, 2]);
+
+    // The commit is not repeated on later returns.
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    run_tx(&mut vm, &mut world);
+    assert_eq!(vm.state.current_frame.heaps_i_am_keeping_alive.len(), 2);
+}
+
+#[test]
+#[should_panic(expected = "`pop_snapshot()` called without a snapshot")]
+fn popping_without_snapshot_panics() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+
+    vm.make_snapshot();
+    vm.pop_snapshot();
+    vm.pop_snapshot();
+}
@@ -46,11 +46,11 @@ proptest! {
             },
         );
 
-        assert_eq!(vm.run(&mut world, &mut ()),
-            if 1 < label && label < 100 {
-                ExecutionEnd::ProgramFinished(vec![])
-            } else {
-                ExecutionEnd::Panicked
-            });
+        let end = vm.run(&mut world, &mut ());
+        if 1 < label && label < 100 {
+            assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+        } else {
+            assert!(matches!(end, ExecutionEnd::Panicked(_)), "{end:?}");
+        }
     }
 }
@@ -0,0 +1,111 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::v2::{PanicReason, Tracer};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+
+/// Writes to storage, which is forbidden in the static call made by `FAR_CALLS_PROGRAM`.
+const CALLED_PROGRAM: &str = "
+    log.swrite r0, r0
+    ret.ok r0
+";
+
+#[derive(Debug, Default)]
+struct PanicLog(Vec<PanicReason>);
+
+impl Tracer for PanicLog {
+    fn on_panic(&mut self, reason: PanicReason) {
+        self.0.push(reason);
+    }
+}
+
+fn run(main_program: &str, gas: u32) -> (ExecutionEnd, Vec<PanicReason>) {
+    let main_program = Program::new(&assemble(main_program).unwrap(), false);
+    let called_program = Program::new(&assemble(CALLED_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+
+    let mut tracer = PanicLog::default();
+    let end = vm.run(&mut world, &mut tracer);
+    (end, tracer.0)
+}
+
+#[test]
+fn spontaneous_panics_report_their_reason() {
+    let cases = [
+        ("invalid", PanicReason::InvalidInstruction),
+        ("loop:\n jump @loop", PanicReason::OutOfErgs),
+        ("context.inc_tx_num", PanicReason::KernelViolation),
+        ("ptr.add r0, r0, r1", PanicReason::InvalidFatPointer),
+        (
+            "add code[@address], r0, r1\n st.1 r1, r0\naddress:\n .cell 0xffffffff",
+            PanicReason::HeapGrowthOverflow,
+        ),
+    ];
+
+    for (program, reason) in cases {
+        let (end, log) = run(program, 10_000);
+        assert_eq!(end, ExecutionEnd::Panicked(Some(reason)), "{program}");
+        assert_eq!(log, [reason], "{program}");
+    }
+}
+
+#[test]
+fn explicit_panic_has_no_reason() {
+    let (end, log) = run("ret.panic r0", 10_000);
+    assert_eq!(end, ExecutionEnd::Panicked(None));
+    assert_eq!(log, []);
+}
+
+/// Makes a static call that panics in the callee, then a far call to the zero address that fails
+/// because it has no code.
+const FAR_CALLS_PROGRAM: &str = "
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call.static r1, r2, @static_failed
+    ret.panic r0
+static_failed:
+    far_call r0, r0, @call_failed
+    ret.panic r0
+call_failed:
+    ret.ok r0
+
+abi:
+    .cell 0x186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+#[test]
+fn panics_in_far_calls_are_reported() {
+    let (end, log) = run(FAR_CALLS_PROGRAM, 1_000_000);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+    assert_eq!(
+        log,
+        [
+            PanicReason::StaticViolation,
+            PanicReason::FarCallDecommitFailed
+        ]
+    );
+}
@@ -0,0 +1,168 @@
+use std::{sync::Arc, thread};
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{CallframeInterface, StateInterface};
+
+use crate::{
+    assemble,
+    instruction_handlers::address_into_u256,
+    testonly::{initial_decommit, TestWorld},
+    CacheStats, CachingWorld, ExecutionEnd, Program, ProgramCache, Settings, VirtualMachine, World,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+const CALLED_ADDRESS: Address = Address::repeat_byte(0x34);
+
+/// Calls the contract at `CALLED_ADDRESS` twice.
+const MAIN_PROGRAM: &str = "
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    add code[@abi], r0, r1
+    add code[@callee], r0, r2
+    far_call r1, r2, @fail
+    ret.ok r0
+fail:
+    ret.panic r0
+
+abi:
+    .cell 0x186a0000000000000000000000000000000000000000000000000
+callee:
+    .cell 0x3434343434343434343434343434343434343434
+";
+
+fn test_world() -> TestWorld<()> {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let called_program = Program::new(&assemble("ret.ok r0").unwrap(), false);
+    TestWorld::new(&[
+        (MAIN_ADDRESS, main_program),
+        (CALLED_ADDRESS, called_program),
+    ])
+}
+
+fn code_hash(world: &TestWorld<()>, address: Address) -> U256 {
+    world.address_to_hash[&address_into_u256(address)]
+}
+
+#[test]
+fn vm_decommits_are_cached() {
+    let mut world = CachingWorld::new(test_world(), 16);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    assert!(matches!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::ProgramFinished(_)
+    ));
+
+    // The main program and the first far call miss; the second far call hits.
+    assert_eq!(
+        world.cache().stats(),
+        CacheStats {
+            hits: 1,
+            misses: 2,
+            evictions: 0,
+        }
+    );
+    assert_eq!(world.cache().len(), 2);
+}
+
+#[test]
+fn least_recently_used_programs_are_evicted() {
+    let inner = test_world();
+    let main_hash = code_hash(&inner, MAIN_ADDRESS);
+    let called_hash = code_hash(&inner, CALLED_ADDRESS);
+    let mut world = CachingWorld::new(inner, 1);
+
+    let program = world.decommit(main_hash);
+    assert_eq!(world.decommit(main_hash), program);
+    let _ = world.decommit(called_hash);
+    // The main program was evicted, so it's decoded again.
+    assert_ne!(world.decommit(main_hash), program);
+
+    assert_eq!(
+        world.cache().stats(),
+        CacheStats {
+            hits: 1,
+            misses: 3,
+            evictions: 2,
+        }
+    );
+    assert_eq!(world.cache().len(), 1);
+    let bytecode = world.inner_mut().decommit_code(main_hash);
+    assert_eq!(world.decommit_code(main_hash), bytecode);
+}
+
+#[test]
+fn cache_is_shared_across_threads() {
+    let main_hash = code_hash(&test_world(), MAIN_ADDRESS);
+    let cache = Arc::new(ProgramCache::new(16));
+
+    let programs: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                scope.spawn(move || {
+                    let mut world = CachingWorld::with_cache(test_world(), cache);
+                    world.decommit(main_hash)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    // Threads may decode the program concurrently, but they all get the cached one.
+    assert!(programs.iter().all(|program| *program == programs[0]));
+    let stats = cache.stats();
+    assert!(stats.misses >= 1);
+    assert_eq!(stats.hits + stats.misses, 4);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn compiled_bytecode_executes_as_in_inner_world() {
+    fn run<W: World<()>>(world: &mut W, address: Address) -> (ExecutionEnd, u32) {
+        let program = initial_decommit(world, address);
+        let mut vm = VirtualMachine::new(
+            address,
+            program,
+            Address::zero(),
+            &[],
+            10_000,
+            Settings {
+                default_aa_code_hash: [0; 32],
+                evm_interpreter_code_hash: [0; 32],
+                hook_address: 0,
+            },
+        );
+        let end = vm.run(world, &mut ());
+        (end, vm.current_frame().gas())
+    }
+
+    // Far calls an invalid address in a loop until it runs out of gas.
+    let bytecode = include_bytes!("bytecodes/call_far");
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let inner = TestWorld::new(&[(address, Program::new(bytecode, false))]);
+    let expected = run(&mut inner.clone(), address);
+    assert_eq!(expected.1, 0);
+
+    for fuse_instructions in [false, true] {
+        let mut world = CachingWorld::new(inner.clone(), 16).with_fusion(fuse_instructions);
+        assert_eq!(run(&mut world, address), expected);
+        assert_eq!(world.cache().len(), 1);
+    }
+}
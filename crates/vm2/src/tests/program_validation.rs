@@ -0,0 +1,68 @@
+use crate::{assemble, testonly::TestWorld, Program, ProgramError};
+
+fn try_load(bytecode: &[u8]) -> Result<Program<(), TestWorld<()>>, ProgramError> {
+    Program::try_new(bytecode, false)
+}
+
+#[test]
+fn valid_program_is_loaded() {
+    let bytecode = assemble(
+        "
+        near_call r0, @callee, @handler
+        jump @end
+    callee:
+        ret.ok r0
+    handler:
+        ret.panic.to_label r0, @end
+    end:
+        ret.ok r0
+        .cell 0x1234
+        ",
+    )
+    .unwrap();
+    let program = try_load(&bytecode).unwrap();
+    assert_eq!(program.code_page().len(), 3);
+}
+
+#[test]
+fn malformed_bytecode_is_rejected() {
+    assert_eq!(
+        try_load(&[0; 33]).unwrap_err(),
+        ProgramError::UnalignedLength(33)
+    );
+    assert_eq!(
+        try_load(&[]).unwrap_err(),
+        ProgramError::WordCountOutOfRange(0)
+    );
+    assert_eq!(
+        try_load(&vec![0; 32 << 16]).unwrap_err(),
+        ProgramError::WordCountOutOfRange(1 << 16)
+    );
+}
+
+#[test]
+fn compiled_bytecode_is_loaded() {
+    let bytecode = include_bytes!("bytecodes/call_far");
+    for fuse_instructions in [false, true] {
+        let program: Program<(), TestWorld<()>> =
+            Program::try_new_with_fusion(bytecode, false, fuse_instructions).unwrap();
+        let expected = Program::new_with_fusion(bytecode, false, fuse_instructions);
+        assert_eq!(program.code_page(), expected.code_page());
+        for pc in 0..=4 {
+            assert_eq!(
+                program.instruction(pc).map(ToString::to_string),
+                expected.instruction(pc).map(ToString::to_string),
+            );
+        }
+    }
+}
+
+#[test]
+fn out_of_range_labels_are_accepted() {
+    // The VM only panics when jumping past the end of the program, so such labels (e.g., in constants
+    // decoded as instructions) must not prevent loading.
+    let bytecode = assemble("add r1, r0, r2\njump 100").unwrap();
+    try_load(&bytecode).unwrap();
+    let bytecode = assemble("near_call r1, @next, 4\nnext: ret.ok r0\n.cell 0x1234").unwrap();
+    try_load(&bytecode).unwrap();
+}
@@ -0,0 +1,222 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{CallframeInterface, Flags, Opcode, ReturnType, StateInterface};
+
+use super::checkpoint::{create_test_world, new_vm, CALLED_ADDRESS, MAIN_ADDRESS};
+use crate::{
+    assemble,
+    fat_pointer::FatPointer,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, RecordingTracer, Replay, Settings, VirtualMachine,
+};
+
+#[derive(Debug, PartialEq)]
+struct FrameState {
+    address: Address,
+    pc: Option<u16>,
+    sp: u16,
+    gas: u32,
+    is_near_call: bool,
+    stack: Vec<(U256, bool)>,
+}
+
+#[derive(Debug, PartialEq)]
+struct ObservedState {
+    registers: Vec<(U256, bool)>,
+    flags: Flags,
+    frames: Vec<FrameState>,
+}
+
+fn observe(state: &mut impl StateInterface) -> ObservedState {
+    let frames = (0..state.number_of_callframes())
+        .map(|n| {
+            let frame = state.callframe(n);
+            FrameState {
+                address: frame.address(),
+                pc: frame.program_counter(),
+                sp: frame.stack_pointer(),
+                gas: frame.gas(),
+                is_near_call: frame.is_near_call(),
+                stack: (0..frame.stack_pointer())
+                    .map(|slot| frame.read_stack(slot))
+                    .collect(),
+            }
+        })
+        .collect();
+    ObservedState {
+        registers: (0..16).map(|i| state.read_register(i)).collect(),
+        flags: state.flags(),
+        frames,
+    }
+}
+
+/// Steps the VM until it stops, observing its state after each instruction.
+fn record<W: crate::World<RecordingTracer>>(
+    vm: &mut VirtualMachine<RecordingTracer, W>,
+    world: &mut W,
+) -> (ExecutionEnd, Replay, Vec<ObservedState>) {
+    let mut tracer = RecordingTracer::new();
+    let mut observed = vec![observe(vm)];
+    let end = loop {
+        let end = vm.step(world, &mut tracer);
+        observed.push(observe(vm));
+        if let Some(end) = end {
+            break end;
+        }
+    };
+    (end, tracer.into_replay().unwrap(), observed)
+}
+
+fn assert_replay_matches(replay: &mut Replay, observed: &[ObservedState]) {
+    assert_eq!(replay.len() + 1, observed.len());
+    assert_eq!(replay.position(), replay.len());
+    for (position, expected) in observed.iter().enumerate().rev() {
+        replay.seek(position);
+        assert_eq!(observe(replay), *expected, "position {position}");
+    }
+    assert!(!replay.step_backward());
+    for (position, expected) in observed.iter().enumerate().skip(1) {
+        assert!(replay.step_forward());
+        assert_eq!(observe(replay), *expected, "position {position}");
+    }
+    assert!(!replay.step_forward());
+}
+
+#[test]
+fn replay_reconstructs_state_across_far_calls() {
+    let mut world = create_test_world::<RecordingTracer>();
+    let mut vm = new_vm(&mut world);
+    let (end, mut replay, observed) = record(&mut vm, &mut world);
+    assert_eq!(end, ExecutionEnd::SuspendedOnHook(0));
+    assert_replay_matches(&mut replay, &observed);
+
+    // Step back from the hook into the called contract.
+    assert_eq!(replay.previous_opcode(), Some(Opcode::HeapWrite));
+    assert!(replay.step_backward());
+    assert_eq!(
+        replay.previous_opcode(),
+        Some(Opcode::Ret(ReturnType::Normal))
+    );
+    assert!(replay.step_backward());
+    assert_eq!(replay.number_of_callframes(), 2);
+    assert_eq!(replay.current_frame().address(), CALLED_ADDRESS);
+    assert_eq!(replay.next_opcode(), Some(Opcode::Ret(ReturnType::Normal)));
+
+    replay.seek(0);
+    assert_eq!(replay.previous_opcode(), None);
+    assert_eq!(replay.current_frame().address(), MAIN_ADDRESS);
+    assert_eq!(replay.current_frame().program_counter(), Some(0));
+}
+
+#[test]
+fn replay_reconstructs_heap_writes() {
+    let address = Address::from_low_u64_be(0x_1234_5678_90ab_cdef);
+    let bytecode = include_bytes!("bytecodes/call_far");
+    let mut world = TestWorld::new(&[(address, Program::new(bytecode, false))]);
+    let program = initial_decommit(&mut world, address);
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &[],
+        10_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let heap = vm.current_frame().heap();
+    let initial_heap: Vec<_> = (0..8).map(|i| vm.read_heap_u256(heap, i * 32)).collect();
+
+    let (end, mut replay, observed) = record(&mut vm, &mut world);
+    assert!(matches!(end, ExecutionEnd::Panicked(_)));
+    assert_eq!(
+        replay.previous_opcode(),
+        Some(Opcode::Ret(ReturnType::Panic))
+    );
+    let final_heap: Vec<_> = (0..8)
+        .map(|i| replay.read_heap_u256(heap, i * 32))
+        .collect();
+    let expected_final_heap: Vec<_> = (0..8).map(|i| vm.read_heap_u256(heap, i * 32)).collect();
+    assert_eq!(final_heap, expected_final_heap);
+
+    assert_replay_matches(&mut replay, &observed);
+    replay.seek(0);
+    let replayed_heap: Vec<_> = (0..8)
+        .map(|i| replay.read_heap_u256(heap, i * 32))
+        .collect();
+    assert_eq!(replayed_heap, initial_heap);
+}
+
+#[test]
+fn replay_captures_heap_reads_and_stack_writes_above_stack_pointer() {
+    const PROGRAM: &str = "
+        add 5, r0, r3
+        st.1 0, r3
+        ld.1 0, r4
+        add 6, r0, r3
+        st.1 0, r3
+        ld r1, r2
+        add r2, r0, stack[100]
+        add 7, r0, stack[100]
+        ret.ok r0
+    ";
+
+    let address = Address::repeat_byte(0x12);
+    let program = Program::new(&assemble(PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[(address, program)]);
+    let program = initial_decommit(&mut world, address);
+    let calldata = [0xab; 32];
+    let mut vm = VirtualMachine::new(
+        address,
+        program,
+        Address::zero(),
+        &calldata,
+        10_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let heap = vm.current_frame().heap();
+    let calldata_heap = FatPointer::from(vm.read_register(1).0).memory_page;
+    // The heap is written before the recording starts.
+    for _ in 0..2 {
+        assert_eq!(vm.step(&mut world, &mut RecordingTracer::new()), None);
+    }
+
+    let (end, mut replay, observed) = record(&mut vm, &mut world);
+    assert!(matches!(end, ExecutionEnd::ProgramFinished(_)));
+    assert_replay_matches(&mut replay, &observed);
+
+    let calldata_word = U256::from_big_endian(&calldata);
+    let expected = [
+        (5, (U256::zero(), false)),
+        (5, (U256::zero(), false)),
+        (5, (U256::zero(), false)),
+        (6, (U256::zero(), false)),
+        (6, (U256::zero(), false)),
+        (6, (calldata_word, false)),
+        (6, (U256::from(7), false)),
+    ];
+    for (position, (heap_word, stack_slot)) in expected.into_iter().enumerate() {
+        replay.seek(position);
+        assert_eq!(
+            replay.read_heap_u256(heap, 0),
+            heap_word.into(),
+            "position {position}"
+        );
+        assert_eq!(
+            replay.read_heap_u256(calldata_heap, 0),
+            calldata_word,
+            "position {position}"
+        );
+        assert_eq!(
+            replay.current_frame().read_stack(100),
+            stack_slot,
+            "position {position}"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use zkevm_opcode_defs::ethereum_types::Address;
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, Settings, StepTracer, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+
+const MAIN_PROGRAM: &str = "
+    add 0x2a, r0, r1
+    add 32, r0, r2
+    st.1 r2, r1
+    ld.1 r2, r3
+    ret.ok r0
+";
+
+fn run(tracer: &mut StepTracer<Vec<u8>>) {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[(MAIN_ADDRESS, main_program)]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    let end = vm.run(&mut world, tracer);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+}
+
+#[test]
+fn step_tracer_writes_line_per_instruction() {
+    let mut tracer = StepTracer::new(vec![]);
+    run(&mut tracer);
+    let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+    assert_eq!(lines.len(), 5, "{trace}");
+
+    // `add` costs 6 ergs.
+    assert!(lines[0].starts_with(r#"{"pc":0,"op":"Add","ergsAfterStaticCost":999994,"#));
+    assert!(lines[0].ends_with(
+        r#","sp":0,"depth":1,"registers":{},"flags":{"lt":false,"eq":false,"gt":false}}"#
+    ));
+    assert!(lines[2].starts_with(r#"{"pc":2,"op":"HeapWrite","#));
+    assert!(lines[2].contains(
+        r#""registers":{"r1":{"value":"0x2a","isPointer":false},"r2":{"value":"0x20","isPointer":false}}"#
+    ));
+    assert!(lines[4].starts_with(r#"{"pc":4,"op":"Ret(Normal)","#));
+    assert!(lines.iter().all(|line| !line.contains(r#""heap":"#)));
+}
+
+#[test]
+fn step_tracer_reports_heap_accesses() {
+    let mut tracer = StepTracer::new(vec![]).with_heap_accesses();
+    run(&mut tracer);
+    let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+
+    assert!(lines[0].ends_with(r#","heap":[]}"#));
+    let heap_access = r#"{"heap":10,"offset":32,"value":"0x2a","isWrite":true}"#;
+    assert!(lines[2].ends_with(&format!(r#","heap":[{heap_access}]}}"#)));
+    let heap_access = heap_access.replace("true", "false");
+    assert!(lines[3].ends_with(&format!(r#","heap":[{heap_access}]}}"#)));
+}
@@ -0,0 +1,79 @@
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::v2::{StorageAccess, Tracer};
+
+use crate::{
+    assemble,
+    testonly::{initial_decommit, TestWorld},
+    ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+const MAIN_ADDRESS: Address = Address::repeat_byte(0x23);
+
+const MAIN_PROGRAM: &str = "
+    add 3, r0, r1
+    add 7, r0, r2
+    log.twrite r1, r2
+    log.tread r1, r3
+    log.swrite r1, r2
+    log.sread r1, r4
+    ret.ok r0
+";
+
+#[derive(Debug, Default)]
+struct StorageAccessLog(Vec<StorageAccess>);
+
+impl Tracer for StorageAccessLog {
+    fn on_storage_access(&mut self, access: StorageAccess) {
+        self.0.push(access);
+    }
+}
+
+#[test]
+fn storage_accesses_are_reported() {
+    let main_program = Program::new(&assemble(MAIN_PROGRAM).unwrap(), false);
+    let mut world = TestWorld::new(&[(MAIN_ADDRESS, main_program)]);
+    let program = initial_decommit(&mut world, MAIN_ADDRESS);
+    let mut vm = VirtualMachine::new(
+        MAIN_ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        1_000_000,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+
+    let mut tracer = StorageAccessLog::default();
+    let end = vm.run(&mut world, &mut tracer);
+    assert_eq!(end, ExecutionEnd::ProgramFinished(vec![]));
+
+    let [transient_write, transient_read, write, read] = tracer.0.as_slice() else {
+        panic!("unexpected accesses: {:?}", tracer.0);
+    };
+    let key = U256::from(3);
+    assert_eq!(
+        *transient_write,
+        StorageAccess::transient(MAIN_ADDRESS, key, U256::zero(), Some(U256::from(7)))
+    );
+    assert_eq!(
+        *transient_read,
+        StorageAccess::transient(MAIN_ADDRESS, key, U256::from(7), None)
+    );
+
+    assert!(!write.is_transient && write.is_write());
+    assert_eq!((write.address, write.key), (MAIN_ADDRESS, U256::from(3)));
+    assert_eq!(write.read_value, U256::zero());
+    assert_eq!(write.written_value, Some(U256::from(7)));
+    assert!(!write.is_warm && write.is_initial);
+    assert_eq!((write.refund, write.pubdata_cost), (0, 50));
+
+    assert!(!read.is_transient && !read.is_write());
+    assert_eq!(read.read_value, U256::from(7));
+    assert!(read.is_warm && !read.is_initial);
+    assert!(read.refund > 0);
+    assert_eq!(read.pubdata_cost, 0);
+}
@@ -0,0 +1,226 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use primitive_types::U256;
+use zkevm_opcode_defs::ethereum_types::Address;
+use zksync_vm2_interface::{
+    v2::Tracer, CallframeInterface, GlobalStateInterface, Opcode, OpcodeType, ShouldStop,
+    StateInterface,
+};
+
+use crate::{
+    assemble,
+    instruction_handlers::address_into_u256,
+    testonly::{initial_decommit, TestWorld},
+    Breakpoint, ExecutionEnd, Program, Settings, VirtualMachine,
+};
+
+/// Contains every fused pair: stack pushes, `nop`s, a storage read followed by `add`, `sub` followed by a jump,
+/// stack pops, and a pair where both instructions are skipped because of their predicates.
+const PROGRAM: &str = "
+    add 3, r0, r1
+    add r1, r0, stack+=[1]
+    add r1, r0, stack+=[1]
+    nop stack+=[2]
+    nop stack-=[2]
+loop:
+    log.sread r1, r2
+    add r2, r1, r3
+    sub.s! 1, r1, r1
+    jump.ne @loop
+    add stack-=[1], r0, r4
+    add stack-=[1], r0, r5
+    add.lt 1, r0, r6
+    jump.lt @fail
+    ret.ok r0
+fail:
+    ret.panic r0
+";
+
+const ADDRESS: Address = Address::repeat_byte(0x12);
+const GAS: u32 = 100_000;
+
+/// Tracer logging all hooks, which can stop the VM or panic after the specified number of instructions.
+#[derive(Debug, Default)]
+struct HookLog {
+    hooks: Vec<(bool, Opcode, Option<u16>, u32)>,
+    stop_after: Option<usize>,
+    panic_after: Option<usize>,
+}
+
+impl Tracer for HookLog {
+    fn before_instruction<OP: OpcodeType, S: GlobalStateInterface>(&mut self, state: &mut S) {
+        let frame = state.current_frame();
+        self.hooks
+            .push((false, OP::VALUE, frame.program_counter(), frame.gas()));
+    }
+
+    fn after_instruction<OP: OpcodeType, S: GlobalStateInterface>(
+        &mut self,
+        state: &mut S,
+    ) -> ShouldStop {
+        let frame = state.current_frame();
+        self.hooks
+            .push((true, OP::VALUE, frame.program_counter(), frame.gas()));
+        assert_ne!(
+            self.panic_after,
+            Some(self.hooks.len() / 2),
+            "tracer panicked"
+        );
+        if self.stop_after == Some(self.hooks.len() / 2) {
+            ShouldStop::Stop
+        } else {
+            ShouldStop::Continue
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    end: ExecutionEnd,
+    hooks: Vec<(bool, Opcode, Option<u16>, u32)>,
+    registers: Vec<(U256, bool)>,
+    pc: Option<u16>,
+    sp: u16,
+    gas: u32,
+}
+
+fn code_hash(world: &TestWorld<HookLog>) -> U256 {
+    world.address_to_hash[&address_into_u256(ADDRESS)]
+}
+
+fn new_vm(
+    fuse_instructions: bool,
+    gas: u32,
+) -> (
+    VirtualMachine<HookLog, TestWorld<HookLog>>,
+    TestWorld<HookLog>,
+) {
+    let bytecode = assemble(PROGRAM).unwrap();
+    let program = Program::new_with_fusion(&bytecode, false, fuse_instructions);
+    let mut world = TestWorld::new(&[(ADDRESS, program)]);
+    let code_hash = code_hash(&world);
+    let program = initial_decommit(&mut world, ADDRESS).with_code_hash(code_hash);
+    let vm = VirtualMachine::new(
+        ADDRESS,
+        program,
+        Address::zero(),
+        &[],
+        gas,
+        Settings {
+            default_aa_code_hash: [0; 32],
+            evm_interpreter_code_hash: [0; 32],
+            hook_address: 0,
+        },
+    );
+    (vm, world)
+}
+
+fn run(fuse_instructions: bool, gas: u32, stop_after: Option<usize>) -> Outcome {
+    let (mut vm, mut world) = new_vm(fuse_instructions, gas);
+    let mut tracer = HookLog {
+        stop_after,
+        ..HookLog::default()
+    };
+    let end = vm.run(&mut world, &mut tracer);
+    let frame = vm.current_frame();
+    let (pc, sp, gas) = (frame.program_counter(), frame.stack_pointer(), frame.gas());
+    Outcome {
+        end,
+        hooks: tracer.hooks,
+        registers: (0..16).map(|i| vm.read_register(i)).collect(),
+        pc,
+        sp,
+        gas,
+    }
+}
+
+#[test]
+fn fused_program_runs_like_unfused_one() {
+    let outcome = run(false, GAS, None);
+    assert!(matches!(outcome.end, ExecutionEnd::ProgramFinished(_)));
+    assert_eq!(run(true, GAS, None), outcome);
+}
+
+#[test]
+fn fusion_preserves_gas_and_panic_semantics() {
+    let spent_gas = GAS - run(false, GAS, None).gas;
+    // Running out of gas at every instruction, including the second instruction of fused pairs
+    for gas in 0..=spent_gas {
+        assert_eq!(run(true, gas, None), run(false, gas, None), "gas = {gas}");
+    }
+}
+
+#[test]
+fn fusion_preserves_tracer_stops() {
+    let instruction_count = run(false, GAS, None).hooks.len() / 2;
+    for stop_after in 1..=instruction_count {
+        let outcome = run(false, GAS, Some(stop_after));
+        assert_eq!(outcome.end, ExecutionEnd::StoppedByTracer);
+        assert_eq!(run(true, GAS, Some(stop_after)), outcome);
+    }
+}
+
+#[test]
+fn fused_program_is_stepped_by_single_instructions() {
+    let instruction_count = run(false, GAS, None).hooks.len() / 2;
+    let (mut vm, mut world) = new_vm(true, GAS);
+    let mut tracer = HookLog::default();
+    let mut steps = 1;
+    while vm.step(&mut world, &mut tracer).is_none() {
+        assert_eq!(tracer.hooks.len(), 2 * steps);
+        steps += 1;
+    }
+    assert_eq!(steps, instruction_count);
+
+    let (mut vm, mut world) = new_vm(true, GAS);
+    let mut tracer = HookLog::default();
+    // Stops between the `sub` and `jump` of a fused pair
+    assert_eq!(
+        vm.run_for(&mut world, &mut tracer, 8),
+        ExecutionEnd::InstructionLimitReached
+    );
+    assert_eq!(vm.current_frame().program_counter(), Some(8));
+    assert_eq!(tracer.hooks.len(), 16);
+}
+
+#[test]
+fn breakpoint_stops_inside_fused_pair() {
+    let (mut vm, mut world) = new_vm(true, GAS);
+    let breakpoint = Breakpoint {
+        code_hash: code_hash(&world),
+        pc: 8,
+    };
+    vm.add_breakpoint(breakpoint);
+    let mut tracer = HookLog::default();
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::Breakpoint(breakpoint)
+    );
+    assert_eq!(vm.current_frame().program_counter(), Some(8));
+    assert_eq!(tracer.hooks.len(), 16);
+
+    assert!(vm.remove_breakpoint(breakpoint));
+    assert!(matches!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::ProgramFinished(_)
+    ));
+    assert_eq!(tracer.hooks, run(false, GAS, None).hooks);
+}
+
+#[test]
+fn panicking_tracer_does_not_leave_superinstructions_allowed() {
+    let (mut vm, mut world) = new_vm(true, GAS);
+    // Panics after the `sub` of a fused pair
+    let mut tracer = HookLog {
+        panic_after: Some(8),
+        ..HookLog::default()
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| vm.run(&mut world, &mut tracer)));
+    assert!(result.is_err());
+    assert!(!vm.allow_superinstructions);
+
+    // The `jump` of the pair is stepped on its own.
+    let mut tracer = HookLog::default();
+    assert_eq!(vm.step(&mut world, &mut tracer), None);
+    assert_eq!(tracer.hooks.len(), 2);
+}
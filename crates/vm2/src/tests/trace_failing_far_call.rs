@@ -3,8 +3,8 @@ use zkevm_opcode_defs::ethereum_types::Address;
 use crate::{
     addressing_modes::{Arguments, Immediate1, Register, Register1, Register2},
     interface::{
-        opcodes::Normal, CallingMode, GlobalStateInterface, Opcode, OpcodeType, ReturnType,
-        ShouldStop, Tracer,
+        opcodes::Normal, v2::Tracer, CallingMode, GlobalStateInterface, Opcode, OpcodeType,
+        ReturnType, ShouldStop,
     },
     testonly::{initial_decommit, TestWorld},
     Instruction, ModeRequirements, Predicate, Program, Settings, VirtualMachine,
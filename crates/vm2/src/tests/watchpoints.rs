@@ -0,0 +1,91 @@
+use primitive_types::U256;
+
+use super::checkpoint::{create_test_world, new_vm, StopAfterFarCall, CALLED_ADDRESS};
+use crate::{ExecutionEnd, Watchpoint, WatchpointHit};
+
+#[test]
+fn storage_watchpoint_stops_after_write() {
+    let mut world = create_test_world::<()>();
+    let mut vm = new_vm(&mut world);
+    let key = U256::from(0x_1234_u64);
+    let watchpoint = Watchpoint::Storage {
+        address: CALLED_ADDRESS,
+        key,
+    };
+    vm.add_watchpoint(watchpoint.clone());
+    assert_eq!(vm.watchpoints().collect::<Vec<_>>(), [watchpoint.clone()]);
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::Watchpoint(WatchpointHit::StorageWrite {
+            address: CALLED_ADDRESS,
+            key,
+            value: key,
+        })
+    );
+    // The writing contract is still executing, right after the write.
+    assert_eq!(vm.state.current_frame.address, CALLED_ADDRESS);
+    assert_eq!(vm.state.current_frame.get_pc_as_u16(), 3);
+    assert_eq!(vm.world_diff().get_storage_changes().count(), 1);
+
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+
+    assert!(vm.remove_watchpoint(&watchpoint));
+    assert!(!vm.remove_watchpoint(&watchpoint));
+    assert_eq!(
+        vm.run(&mut world, &mut ()),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
+
+#[test]
+fn heap_watchpoint_is_triggered_by_overlapping_writes() {
+    let mut world = create_test_world::<StopAfterFarCall>();
+    let mut vm = new_vm(&mut world);
+    let mut tracer = StopAfterFarCall;
+
+    // The called contract writes a word at 0x1234 to its heap.
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    let heap = vm.state.current_frame.heap;
+    vm.add_watchpoint(Watchpoint::Heap {
+        heap,
+        range: 0..0x1234,
+    });
+    vm.add_watchpoint(Watchpoint::Heap {
+        heap,
+        range: 0x1254..0x2000,
+    });
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::StoppedByTracer
+    );
+    let heap = vm.state.current_frame.heap;
+    vm.add_watchpoint(Watchpoint::Heap {
+        heap,
+        range: 0x1253..0x1254,
+    });
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::Watchpoint(WatchpointHit::HeapWrite {
+            heap,
+            offset: 0x1234,
+            value: U256::from(0x_1234_u64),
+        })
+    );
+    assert_eq!(vm.state.current_frame.get_pc_as_u16(), 2);
+    assert_eq!(
+        vm.run(&mut world, &mut tracer),
+        ExecutionEnd::SuspendedOnHook(0)
+    );
+}
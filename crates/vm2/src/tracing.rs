@@ -2,8 +2,8 @@ use std::cmp::Ordering;
 
 use primitive_types::{H160, U256};
 use zksync_vm2_interface::{
-    CallframeInterface, Event, Flags, GlobalStateInterface, HeapId, L2ToL1Log, StateInterface,
-    Tracer,
+    v2::Tracer, CallframeInterface, Event, Flags, GlobalStateInterface, HeapId, L2ToL1Log,
+    StateInterface,
 };
 
 use crate::{
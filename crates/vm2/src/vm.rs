@@ -1,15 +1,20 @@
 use std::fmt;
 
 use primitive_types::{H160, U256};
-use zksync_vm2_interface::{opcodes::TypeLevelCallingMode, CallingMode, HeapId, Tracer};
+use zksync_vm2_interface::{opcodes::TypeLevelCallingMode, v2::Tracer, CallingMode, HeapId};
 
+#[cfg(not(feature = "single_instruction_test"))]
+use crate::program::ProgramSource;
 use crate::{
+    breakpoints::Breakpoints,
     callframe::{Callframe, FrameRemnant},
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
     decommit::{materialize_decommit_page, u256_into_address},
     instruction::ExecutionStatus,
     page_ids::{aux_heap_page_from_base, code_page_from_base, heap_page_from_base},
     stack::StackPool,
     state::{State, StateSnapshot},
+    watchpoints::Watchpoints,
     world_diff::{ExternalSnapshot, Snapshot, WorldDiff},
     ExecutionEnd, Program, World,
 };
@@ -25,16 +30,52 @@ pub struct Settings {
     pub hook_address: u32,
 }
 
+impl Checkpoint for Settings {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.default_aa_code_hash);
+        encoder.put(&self.evm_interpreter_code_hash);
+        encoder.put(&self.hook_address);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            default_aa_code_hash: decoder.get()?,
+            evm_interpreter_code_hash: decoder.get()?,
+            hook_address: decoder.get()?,
+        })
+    }
+}
+
 /// High-performance out-of-circuit EraVM implementation.
+///
+/// The VM is [`Send`] if `T` and `W` are, so it can be moved to another thread, e.g. when suspended on a hook.
 #[derive(Debug)]
 pub struct VirtualMachine<T, W> {
     pub(crate) world_diff: WorldDiff,
     pub(crate) state: State<T, W>,
     pub(crate) settings: Settings,
     pub(crate) stack_pool: StackPool,
-    pub(crate) snapshot: Option<VmSnapshot>,
+    pub(crate) snapshots: Vec<VmSnapshot<T, W>>,
+    pub(crate) breakpoints: Breakpoints<T, W>,
+    pub(crate) watchpoints: Watchpoints,
+    /// Whether superinstructions may run their second instruction, which is only the case in [`Self::run()`].
+    #[cfg_attr(feature = "single_instruction_test", allow(dead_code))]
+    pub(crate) allow_superinstructions: bool,
+    /// Whether the outermost snapshot was popped inside a far call, so rollback history and bootloader returndata
+    /// heaps must be freed once the callstack unwinds to the bootloader frame.
+    pub(crate) commit_pending: bool,
 }
 
+// Mocked programs used in single instruction tests aren't thread-safe
+#[cfg(not(feature = "single_instruction_test"))]
+const _: () = {
+    const fn assert_send<V: Send>() {}
+    const fn assert_vm_is_send<T: Send, W: Send>() {
+        assert_send::<VirtualMachine<T, W>>();
+    }
+    assert_vm_is_send::<(), ()>();
+};
+
 impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
     /// Creates a new VM instance.
     pub fn new(
@@ -62,7 +103,11 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
             ),
             settings,
             stack_pool,
-            snapshot: None,
+            snapshots: Vec::new(),
+            breakpoints: Breakpoints::default(),
+            watchpoints: Watchpoints::default(),
+            allow_superinstructions: false,
+            commit_pending: false,
         }
     }
 
@@ -112,17 +157,47 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
 
     /// Runs this VM with the specified [`World`] and [`Tracer`] until an end of execution due to a hook, or an error.
     pub fn run(&mut self, world: &mut W, tracer: &mut T) -> ExecutionEnd {
+        let mut guard = SuperinstructionsAllowed::new(self);
+        let vm = &mut *guard.0;
         unsafe {
             loop {
                 if let ExecutionStatus::Stopped(end) =
-                    ((*self.state.current_frame.pc).handler)(self, world, tracer)
+                    ((*vm.state.current_frame.pc).handler)(vm, world, tracer)
                 {
-                    return end;
+                    break end;
                 }
             }
         }
     }
 
+    /// Executes a single instruction. Returns the end of execution if the VM stopped during this instruction.
+    ///
+    /// Executing a program step by step is exactly equivalent to [running](Self::run()) it.
+    pub fn step(&mut self, world: &mut W, tracer: &mut T) -> Option<ExecutionEnd> {
+        let status = unsafe { ((*self.state.current_frame.pc).handler)(self, world, tracer) };
+        match status {
+            ExecutionStatus::Running => None,
+            ExecutionStatus::Stopped(end) => Some(end),
+        }
+    }
+
+    /// Runs this VM like [`Self::run()`], but executes at most `max_instructions` instructions. If the VM doesn't stop
+    /// by itself within this budget, returns [`ExecutionEnd::InstructionLimitReached`]; execution can then be resumed
+    /// by another call to this method or [`Self::run()`] as if it was never interrupted.
+    pub fn run_for(
+        &mut self,
+        world: &mut W,
+        tracer: &mut T,
+        max_instructions: u64,
+    ) -> ExecutionEnd {
+        for _ in 0..max_instructions {
+            if let Some(end) = self.step(world, tracer) {
+                return end;
+            }
+        }
+        ExecutionEnd::InstructionLimitReached
+    }
+
     /// Returns how much of the extra gas limit is left and the stop reason,
     /// unless the extra gas limit was exceeded.
     ///
@@ -159,60 +234,127 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
 
     /// Creates a VM snapshot. The snapshot can then be rolled back to, or discarded.
     ///
-    /// # Panics
+    /// Snapshots can be nested; [`Self::rollback()`] and [`Self::pop_snapshot()`] always apply to the most recent one.
     ///
-    /// - Panics if called outside the initial (bootloader) callframe.
-    /// - Panics if this VM already has a snapshot.
+    /// Snapshots can be made at any instruction boundary. Snapshots made inside far calls capture the entire callstack,
+    /// heaps and [`WorldDiff`], so they are much more expensive than the ones made in the initial (bootloader) frame.
     pub fn make_snapshot(&mut self) {
-        assert!(self.snapshot.is_none(), "VM already has a snapshot");
-        assert!(
-            self.state.previous_frames.is_empty(),
-            "Snapshotting is only allowed in the bootloader"
-        );
-
-        self.snapshot = Some(VmSnapshot {
-            world_snapshot: self.world_diff.external_snapshot(),
-            state_snapshot: self.state.snapshot(),
-        });
+        let snapshot = if self.state.previous_frames.is_empty() {
+            VmSnapshot::Bootloader {
+                world_snapshot: self.world_diff.external_snapshot(),
+                state_snapshot: self.state.snapshot(),
+            }
+        } else {
+            VmSnapshot::Nested {
+                world_diff: Box::new(self.world_diff.clone()),
+                state: Box::new(self.state.clone()),
+            }
+        };
+        self.snapshots.push(snapshot);
     }
 
-    /// Returns the VM to the state it was in when [`Self::make_snapshot()`] was called.
+    /// Returns the VM to the state it was in when the most recent [snapshot](Self::make_snapshot()) was made,
+    /// and discards that snapshot. The callstack is restored as well, so this can be called at any instruction boundary.
     ///
     /// # Panics
     ///
-    /// - Panics if this VM doesn't hold a snapshot.
-    /// - Panics if called outside the initial (bootloader) callframe.
+    /// Panics if this VM doesn't hold a snapshot.
     pub fn rollback(&mut self) {
-        assert!(
-            self.state.previous_frames.is_empty(),
-            "Rolling back is only allowed in the bootloader"
-        );
-
         let snapshot = self
-            .snapshot
-            .take()
+            .snapshots
+            .pop()
             .expect("`rollback()` called without a snapshot");
-        self.world_diff.external_rollback(snapshot.world_snapshot);
-        self.state.rollback(snapshot.state_snapshot, |heap| {
-            self.world_diff.is_decommit_page_pinned(heap)
-        });
-        self.delete_history();
+        match snapshot {
+            VmSnapshot::Bootloader {
+                world_snapshot,
+                state_snapshot,
+            } => {
+                self.unwind_to_bootloader();
+                self.world_diff.external_rollback(world_snapshot);
+                self.state.rollback(state_snapshot, |heap| {
+                    self.world_diff.is_decommit_page_pinned(heap)
+                });
+            }
+            VmSnapshot::Nested { world_diff, state } => {
+                self.world_diff = *world_diff;
+                let state = std::mem::replace(&mut self.state, *state);
+                for frame in state
+                    .previous_frames
+                    .into_iter()
+                    .chain([state.current_frame])
+                {
+                    self.stack_pool.recycle(frame.stack);
+                }
+                // Programs in the snapshot may have been patched for a different set of breakpoints.
+                #[cfg(not(feature = "single_instruction_test"))]
+                self.patch_callstack(None);
+            }
+        }
+        #[cfg(not(feature = "single_instruction_test"))]
+        self.reset_breakpoint_hit();
+        // Outer snapshots and unfinished far calls still need the history to be rolled back to.
+        if self.snapshots.is_empty() && self.state.previous_frames.is_empty() {
+            if self.commit_pending {
+                self.commit(None);
+            } else {
+                self.delete_history();
+            }
+        }
     }
 
-    /// Pops a [previously made](Self::make_snapshot()) snapshot without rolling back to it. This effectively commits
-    /// all changes made up to this point, so that they cannot be rolled back.
+    /// Pops the most recent [snapshot](Self::make_snapshot()) without rolling back to it. If this was the outermost
+    /// snapshot, this effectively commits all changes made up to this point, so that they cannot be rolled back.
+    /// Otherwise, the changes can still be rolled back together with the enclosing snapshot.
+    ///
+    /// Rollback history and bootloader returndata heaps are freed right away if this is called in the initial
+    /// (bootloader) frame; otherwise, they are freed once the callstack unwinds to the bootloader frame.
     ///
     /// # Panics
     ///
-    /// - Panics if called outside the initial (bootloader) callframe.
+    /// Panics if this VM doesn't hold a snapshot.
     pub fn pop_snapshot(&mut self) {
-        assert!(
-            self.state.previous_frames.is_empty(),
-            "Popping a snapshot is only allowed in the bootloader"
-        );
-        self.snapshot = None;
+        self.snapshots
+            .pop()
+            .expect("`pop_snapshot()` called without a snapshot");
+        if self.snapshots.is_empty() {
+            if self.state.previous_frames.is_empty() {
+                self.commit(None);
+            } else {
+                // Unfinished far calls can still revert, which needs the history.
+                self.commit_pending = true;
+            }
+        }
+    }
+
+    /// Finishes a commit deferred by [`Self::pop_snapshot()`] once a far call has returned to the bootloader frame
+    /// and its world changes have been rolled back if it failed. `returned_heap` holds the returndata
+    /// the bootloader is about to read, so it is kept alive.
+    pub(crate) fn finish_pending_commit(&mut self, returned_heap: Option<HeapId>) {
+        if self.commit_pending && self.snapshots.is_empty() && self.state.previous_frames.is_empty()
+        {
+            self.commit(returned_heap);
+        }
+    }
+
+    fn commit(&mut self, heap_to_keep: Option<HeapId>) {
+        self.commit_pending = false;
         self.delete_history();
-        self.reclaim_bootloader_returndata_heaps();
+        self.reclaim_bootloader_returndata_heaps(heap_to_keep);
+    }
+
+    /// Drops all far call frames, making the initial (bootloader) frame current. Heaps of the dropped frames
+    /// are not deallocated; this is only used before rolling back to a snapshot made in the bootloader frame,
+    /// which frees all heaps allocated after it.
+    fn unwind_to_bootloader(&mut self) {
+        if self.state.previous_frames.is_empty() {
+            return;
+        }
+        let mut frames = std::mem::take(&mut self.state.previous_frames).into_iter();
+        let bootloader_frame = frames.next().unwrap();
+        let current_frame = std::mem::replace(&mut self.state.current_frame, bootloader_frame);
+        for frame in frames.chain([current_frame]) {
+            self.stack_pool.recycle(frame.stack);
+        }
     }
 
     /// Frees the returndata heaps that accumulated on the bootloader frame while
@@ -225,15 +367,17 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
     /// dominant heap-memory consumer on large batches).
     ///
     /// This is the safe point to release them: the callstack is unwound to the
-    /// bootloader (`previous_frames` is empty), the external snapshot has just
-    /// been discarded (`self.snapshot` is `None`) and history deleted, so no
+    /// bootloader (`previous_frames` is empty), the outermost external snapshot
+    /// has been discarded (`self.snapshots` is empty) and history deleted, so no
     /// rollback can reference these heaps; and a committed transaction's
     /// returndata is dead once the bootloader moves on. Decommit-pinned code
     /// pages (shared across transactions by hash) are kept — the same predicate
     /// [`Self::pop_frame`] uses. Freed chunks return to the heap `ChunkPool` and
     /// are reused by the next transaction, so peak memory stays at roughly one
     /// transaction's worth instead of growing with the transaction count.
-    fn reclaim_bootloader_returndata_heaps(&mut self) {
+    ///
+    /// `heap_to_keep` is spared; see [`Self::finish_pending_commit()`].
+    fn reclaim_bootloader_returndata_heaps(&mut self, heap_to_keep: Option<HeapId>) {
         // `kept` is owned after the take, so the `retain` closure can borrow
         // `self.world_diff`/`self.state.heaps` without conflicting with the
         // borrow of the field it compacts. Retain drops the deallocated heaps
@@ -242,11 +386,11 @@ impl<T: Tracer, W: World<T>> VirtualMachine<T, W> {
         // snapshot / tail-drain rollback path in `Callframe`).
         let mut kept = std::mem::take(&mut self.state.current_frame.heaps_i_am_keeping_alive);
         kept.retain(|&heap| {
-            let pinned = self.world_diff.is_decommit_page_pinned(heap);
-            if !pinned {
+            let keep = Some(heap) == heap_to_keep || self.world_diff.is_decommit_page_pinned(heap);
+            if !keep {
                 self.state.heaps.deallocate(heap);
             }
-            pinned
+            keep
         });
         self.state.current_frame.heaps_i_am_keeping_alive = kept;
     }
@@ -274,6 +418,8 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
         calldata_heap: HeapId,
         world_before_this_frame: Snapshot,
     ) {
+        #[cfg(not(feature = "single_instruction_test"))]
+        let program = self.breakpoints.patch(program);
         let base_page = self.state.allocate_base_page();
         let heap_page = heap_page_from_base(base_page);
         let aux_heap_page = aux_heap_page_from_base(base_page);
@@ -400,6 +546,23 @@ impl<T: Tracer, W> VirtualMachine<T, W> {
     }
 }
 
+/// Allows superinstructions while alive, so that they are disallowed again even if a handler or tracer panics
+/// during [`VirtualMachine::run()`].
+struct SuperinstructionsAllowed<'a, T, W>(&'a mut VirtualMachine<T, W>);
+
+impl<'a, T, W> SuperinstructionsAllowed<'a, T, W> {
+    fn new(vm: &'a mut VirtualMachine<T, W>) -> Self {
+        vm.allow_superinstructions = true;
+        Self(vm)
+    }
+}
+
+impl<T, W> Drop for SuperinstructionsAllowed<'_, T, W> {
+    fn drop(&mut self) {
+        self.0.allow_superinstructions = false;
+    }
+}
+
 impl<T: fmt::Debug, W: fmt::Debug> VirtualMachine<T, W> {
     /// Dumps an opaque representation of the current VM state.
     #[doc(hidden)] // should only be used in tests
@@ -410,7 +573,64 @@ impl<T: fmt::Debug, W: fmt::Debug> VirtualMachine<T, W> {
 
 /// Snapshot of a [`VirtualMachine`].
 #[derive(Debug)]
-pub(crate) struct VmSnapshot {
-    world_snapshot: ExternalSnapshot,
-    state_snapshot: StateSnapshot,
+pub(crate) enum VmSnapshot<T, W> {
+    /// Snapshot made in the initial (bootloader) frame. It relies on the rollback history of the world diff and
+    /// the bootloader heaps, and on the bootloader frame outliving all other frames.
+    Bootloader {
+        world_snapshot: ExternalSnapshot,
+        state_snapshot: StateSnapshot,
+    },
+    /// Snapshot made inside a far call. Frames that exist at this point can revert later, rolling back the world diff
+    /// past the snapshot, so the entire world diff and VM state are copied instead.
+    Nested {
+        world_diff: Box<WorldDiff>,
+        state: Box<State<T, W>>,
+    },
+}
+
+#[cfg(not(feature = "single_instruction_test"))]
+impl<T: Tracer, W: World<T>> VmSnapshot<T, W> {
+    /// Returns the state captured by this snapshot, if it is captured in full.
+    pub(crate) fn full_state(&self) -> Option<&State<T, W>> {
+        match self {
+            Self::Bootloader { .. } => None,
+            Self::Nested { state, .. } => Some(state),
+        }
+    }
+
+    pub(crate) fn encode_checkpoint(&self, encoder: &mut Encoder) {
+        match self {
+            Self::Bootloader {
+                world_snapshot,
+                state_snapshot,
+            } => {
+                encoder.put(&0_u8);
+                encoder.put(world_snapshot);
+                encoder.put(state_snapshot);
+            }
+            Self::Nested { world_diff, state } => {
+                encoder.put(&1_u8);
+                encoder.put(&**world_diff);
+                state.encode_checkpoint(encoder);
+            }
+        }
+    }
+
+    /// Decodes a snapshot, obtaining frame programs of a fully captured state from the provided closure.
+    pub(crate) fn decode_checkpoint(
+        decoder: &mut Decoder<'_>,
+        get_program: impl FnMut(usize, ProgramSource) -> Result<Program<T, W>, CheckpointError>,
+    ) -> Result<Self, CheckpointError> {
+        match decoder.get::<u8>()? {
+            0 => Ok(Self::Bootloader {
+                world_snapshot: decoder.get()?,
+                state_snapshot: decoder.get()?,
+            }),
+            1 => Ok(Self::Nested {
+                world_diff: Box::new(decoder.get()?),
+                state: Box::new(State::decode_checkpoint(decoder, get_program)?),
+            }),
+            _ => Err(CheckpointError::Malformed("invalid snapshot kind")),
+        }
+    }
 }
@@ -0,0 +1,191 @@
+//! Storage and heap watchpoints.
+
+use std::{collections::BTreeSet, ops::Range};
+
+use primitive_types::{H160, U256};
+use zksync_vm2_interface::HeapId;
+
+use crate::{
+    instruction::ExecutionStatus, page_ids::static_memory_page, ExecutionEnd, VirtualMachine,
+};
+
+/// Condition that stops VM execution with [`ExecutionEnd::Watchpoint`] once it is met.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Watchpoint {
+    /// Triggered when a storage slot is read by `StorageRead` or written by `StorageWrite`.
+    Storage {
+        /// Address of the contract owning the slot.
+        address: H160,
+        /// Storage key.
+        key: U256,
+    },
+    /// Triggered when any byte in `range` of `heap` is written by `HeapWrite`, `AuxHeapWrite` or `StaticMemoryWrite`.
+    Heap {
+        /// Heap to watch. For static memory, use [`Self::static_memory()`].
+        heap: HeapId,
+        /// Byte offsets to watch.
+        range: Range<u32>,
+    },
+}
+
+impl Watchpoint {
+    /// Creates a watchpoint triggered by writes to the specified range of static memory.
+    pub fn static_memory(range: Range<u32>) -> Self {
+        Self::Heap {
+            heap: static_memory_page(),
+            range,
+        }
+    }
+}
+
+/// Access that has triggered a [`Watchpoint`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchpointHit {
+    /// Watched storage slot was read.
+    StorageRead {
+        /// Address of the contract owning the slot.
+        address: H160,
+        /// Storage key.
+        key: U256,
+        /// Read value.
+        value: U256,
+    },
+    /// Watched storage slot was written.
+    StorageWrite {
+        /// Address of the contract owning the slot.
+        address: H160,
+        /// Storage key.
+        key: U256,
+        /// Written value.
+        value: U256,
+    },
+    /// A 32-byte word overlapping a watched heap range was written.
+    HeapWrite {
+        /// Written heap.
+        heap: HeapId,
+        /// Offset of the written word.
+        offset: u32,
+        /// Written value.
+        value: U256,
+    },
+}
+
+/// Watchpoints set on a [`VirtualMachine`].
+#[derive(Debug, Default)]
+pub(crate) struct Watchpoints {
+    storage: BTreeSet<(H160, U256)>,
+    heap: Vec<(HeapId, Range<u32>)>,
+}
+
+impl Watchpoints {
+    #[inline(always)]
+    pub(crate) fn storage_read(&self, address: H160, key: U256, value: U256) -> ExecutionStatus {
+        if self.is_storage_watched(address, key) {
+            Self::hit(WatchpointHit::StorageRead {
+                address,
+                key,
+                value,
+            })
+        } else {
+            ExecutionStatus::Running
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn storage_write(&self, address: H160, key: U256, value: U256) -> ExecutionStatus {
+        if self.is_storage_watched(address, key) {
+            Self::hit(WatchpointHit::StorageWrite {
+                address,
+                key,
+                value,
+            })
+        } else {
+            ExecutionStatus::Running
+        }
+    }
+
+    /// Must be called after a write of a 32-byte word at `offset`, which must not exceed `u32::MAX - 32`.
+    #[inline(always)]
+    pub(crate) fn heap_write(&self, heap: HeapId, offset: u32, value: U256) -> ExecutionStatus {
+        if self.heap.is_empty() {
+            return ExecutionStatus::Running;
+        }
+        let is_watched = self.heap.iter().any(|(watched_heap, range)| {
+            *watched_heap == heap && range.start < offset + 32 && offset < range.end
+        });
+        if is_watched {
+            Self::hit(WatchpointHit::HeapWrite {
+                heap,
+                offset,
+                value,
+            })
+        } else {
+            ExecutionStatus::Running
+        }
+    }
+
+    fn is_storage_watched(&self, address: H160, key: U256) -> bool {
+        !self.storage.is_empty() && self.storage.contains(&(address, key))
+    }
+
+    fn hit(hit: WatchpointHit) -> ExecutionStatus {
+        ExecutionStatus::Stopped(ExecutionEnd::Watchpoint(hit))
+    }
+}
+
+impl<T, W> VirtualMachine<T, W> {
+    /// Sets a watchpoint. [`Self::run()`] and related methods return [`ExecutionEnd::Watchpoint`] right after
+    /// the VM executes an instruction triggering the watchpoint, so the state of the VM (e.g., the current frame
+    /// and its program counter) identifies the accessing contract. Execution can be resumed as usual.
+    ///
+    /// If a heap write both triggers a watchpoint and suspends the VM on a hook, only
+    /// [`ExecutionEnd::SuspendedOnHook`] is returned.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        match watchpoint {
+            Watchpoint::Storage { address, key } => {
+                self.watchpoints.storage.insert((address, key));
+            }
+            Watchpoint::Heap { heap, range } => {
+                if !self.watchpoints.heap.contains(&(heap, range.clone())) {
+                    self.watchpoints.heap.push((heap, range));
+                }
+            }
+        }
+    }
+
+    /// Removes a previously [set](Self::add_watchpoint()) watchpoint. Returns `false` if the watchpoint wasn't set.
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        match watchpoint {
+            Watchpoint::Storage { address, key } => {
+                self.watchpoints.storage.remove(&(*address, *key))
+            }
+            Watchpoint::Heap { heap, range } => {
+                let len_before = self.watchpoints.heap.len();
+                self.watchpoints
+                    .heap
+                    .retain(|(watched_heap, watched_range)| {
+                        watched_heap != heap || watched_range != range
+                    });
+                self.watchpoints.heap.len() < len_before
+            }
+        }
+    }
+
+    /// Returns all watchpoints set on this VM.
+    pub fn watchpoints(&self) -> impl Iterator<Item = Watchpoint> + '_ {
+        let storage = self
+            .watchpoints
+            .storage
+            .iter()
+            .map(|&(address, key)| Watchpoint::Storage { address, key });
+        let heap = self
+            .watchpoints
+            .heap
+            .iter()
+            .map(|(heap, range)| Watchpoint::Heap {
+                heap: *heap,
+                range: range.clone(),
+            });
+        storage.chain(heap)
+    }
+}
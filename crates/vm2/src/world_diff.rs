@@ -6,9 +6,14 @@ use zkevm_opcode_defs::system_params::{
     STORAGE_ACCESS_COLD_READ_COST, STORAGE_ACCESS_COLD_WRITE_COST, STORAGE_ACCESS_WARM_READ_COST,
     STORAGE_ACCESS_WARM_WRITE_COST, STORAGE_AUX_BYTE,
 };
-use zksync_vm2_interface::{CycleStats, Event, HeapId, L2ToL1Log, Tracer};
+use zksync_vm2_interface::{
+    v2::{StorageAccess, Tracer},
+    CycleStats, Event, HeapId, L2ToL1Log,
+};
 
 use crate::{
+    checkpoint::{Checkpoint, CheckpointError, Decoder, Encoder},
+    prestate::{PrestateDiff, SlotState},
     rollback::{Rollback, RollbackableLog, RollbackableMap, RollbackablePod, RollbackableSet},
     StorageInterface, StorageSlot,
 };
@@ -35,7 +40,7 @@ const SLOT_PROTECTIVE_READ: u8 = 1 << 2;
 
 /// Pending modifications to the global state that are executed at the end of a block.
 /// In other words, side effects.
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct WorldDiff {
     // These are rolled back on revert or panic (and when the whole VM is rolled back).
     /// Pending storage writes (value + pubdata paid), merged from the former
@@ -182,6 +187,16 @@ impl WorldDiff {
             0
         };
         self.storage_refunds.push(refund);
+        tracer.on_storage_access(StorageAccess::persistent(
+            contract,
+            key,
+            value,
+            None,
+            !newly_added,
+            false,
+            refund,
+            0,
+        ));
         (value, refund)
     }
 
@@ -310,63 +325,79 @@ impl WorldDiff {
                 ..log_query
             });
         }
-        let initial_value = self
+        let initial_value = *self
             .storage_initial_values
             .entry((contract, key))
             .or_insert_with(|| world.read_storage(contract, key));
+        let newly_written = self.slot_add_flag((contract, key), SLOT_WRITTEN);
+        if newly_written {
+            tracer.on_extra_prover_cycles(CycleStats::StorageWrite);
+        }
+        // A slot is warm if it was read or written before. Writes mark the slot as read too,
+        // so this is the case iff the read flag is already set.
+        let is_warm = !self.slot_add_flag((contract, key), SLOT_READ);
+        let mut access = StorageAccess::persistent(
+            contract,
+            key,
+            initial_value.value,
+            Some(value),
+            is_warm,
+            initial_value.is_write_initial,
+            WARM_WRITE_REFUND,
+            0,
+        );
 
         if world.is_free_storage_slot(&contract, &key) {
             // Free write: the value changes but no pubdata is paid, so the entry
             // keeps its prior paid amount. One journaling traversal — no
             // separate read-back of the prior entry.
-            self.storage_writes
-                .update((contract, key), |prev| StorageWriteEntry {
+            self.storage_writes.update((contract, key), |prev| {
+                if let Some(prev) = prev {
+                    access.read_value = prev.value;
+                }
+                StorageWriteEntry {
                     value,
                     paid: prev.map_or(0, |e| e.paid),
-                });
-            if self.slot_add_flag((contract, key), SLOT_WRITTEN) {
-                tracer.on_extra_prover_cycles(CycleStats::StorageWrite);
-            }
-            self.slot_add_flag((contract, key), SLOT_READ);
-
+                }
+            });
             self.storage_refunds.push(WARM_WRITE_REFUND);
             self.pubdata_costs.push(0);
+            tracer.on_storage_access(access);
             return WARM_WRITE_REFUND;
         }
 
-        let update_cost = world.cost_of_writing_storage(*initial_value, value);
+        let update_cost = world.cost_of_writing_storage(initial_value, value);
         // Single insert with the final paid amount; `prepaid` (the prior paid)
         // comes from the replaced entry, avoiding a separate lookup.
-        let prepaid = self
-            .storage_writes
-            .insert(
-                (contract, key),
-                StorageWriteEntry {
-                    value,
-                    paid: update_cost,
-                },
-            )
-            .map_or(0, |e| e.paid);
-
-        let refund = if self.slot_add_flag((contract, key), SLOT_WRITTEN) {
-            tracer.on_extra_prover_cycles(CycleStats::StorageWrite);
+        let prev = self.storage_writes.insert(
+            (contract, key),
+            StorageWriteEntry {
+                value,
+                paid: update_cost,
+            },
+        );
+        let prepaid = prev.map_or(0, |e| e.paid);
+        if let Some(prev) = prev {
+            access.read_value = prev.value;
+        }
 
-            if self.slot_add_flag((contract, key), SLOT_READ) {
-                0
-            } else {
-                COLD_WRITE_AFTER_WARM_READ_REFUND
-            }
+        let refund = if !is_warm {
+            0
+        } else if newly_written {
+            COLD_WRITE_AFTER_WARM_READ_REFUND
         } else {
             WARM_WRITE_REFUND
         };
 
         #[allow(clippy::cast_possible_wrap)]
-        {
-            let pubdata_cost = (update_cost as i32) - (prepaid as i32);
-            self.pubdata.0 += pubdata_cost;
-            self.storage_refunds.push(refund);
-            self.pubdata_costs.push(pubdata_cost);
-        }
+        let pubdata_cost = (update_cost as i32) - (prepaid as i32);
+        self.pubdata.0 += pubdata_cost;
+        self.storage_refunds.push(refund);
+        self.pubdata_costs.push(pubdata_cost);
+
+        access.refund = refund;
+        access.pubdata_cost = pubdata_cost;
+        tracer.on_storage_access(access);
         refund
     }
 
@@ -397,6 +428,16 @@ impl WorldDiff {
             .map(|(k, _)| *k)
     }
 
+    /// Iterates over slots read or written during execution, including accesses in reverted frames.
+    /// Sorted by (address, key).
+    pub(crate) fn accessed_storage_slots(&self) -> impl Iterator<Item = (H160, U256)> + '_ {
+        self.slot_flags
+            .as_ref()
+            .iter()
+            .filter(|(_, f)| **f & (SLOT_READ | SLOT_WRITTEN) != 0)
+            .map(|(k, _)| *k)
+    }
+
     /// Returns the initial (pre-batch) value of a slot if it has been
     /// touched by a read or write during execution. Used by per-slot summary
     /// derivation in place of walking the `storage_logs` trace.
@@ -409,6 +450,46 @@ impl WorldDiff {
         self.storage_initial_values.get(&(contract, key)).copied()
     }
 
+    /// Collects the state touched by execution so far: storage slots read or written with their initial
+    /// and pending values, non-zero transient storage slots and decommitted bytecode hashes.
+    ///
+    /// Initial values are taken from [`Self::initial_storage_value()`] if they are cached, and are read from
+    /// the `world` otherwise (e.g., for read-only slots if storage logs are recorded). Thus, `world` must be
+    /// the storage the VM was started with.
+    ///
+    /// Transient storage is cleared when the bootloader increments the transaction number, so only slots
+    /// written in the current transaction are returned.
+    pub fn prestate_diff(&self, world: &mut impl StorageInterface) -> PrestateDiff {
+        let mut diff = PrestateDiff::default();
+        for (address, key) in self.accessed_storage_slots() {
+            let initial = self
+                .storage_initial_values
+                .get(&(address, key))
+                .map_or_else(|| world.read_storage_value(address, key), |slot| slot.value);
+            let written = self
+                .storage_writes
+                .as_ref()
+                .get(&(address, key))
+                .map(|entry| entry.value);
+            diff.accounts
+                .entry(address)
+                .or_default()
+                .storage
+                .insert(key, SlotState { initial, written });
+        }
+        for (&(address, key), &value) in self.transient_storage_changes.as_ref() {
+            if !value.is_zero() {
+                diff.accounts
+                    .entry(address)
+                    .or_default()
+                    .transient_storage
+                    .insert(key, value);
+            }
+        }
+        diff.decommitted_hashes = self.decommitted_hashes().collect();
+        diff
+    }
+
     /// Returns all recorded storage log queries.
     ///
     /// These logs are sufficient for vm2 state-transition checks and diagnostics.
@@ -481,10 +562,17 @@ impl WorldDiff {
             .unwrap_or_default()
     }
 
-    pub(crate) fn write_transient_storage(&mut self, contract: H160, key: U256, value: U256) {
+    /// Returns the previous value of the slot.
+    pub(crate) fn write_transient_storage(
+        &mut self,
+        contract: H160,
+        key: U256,
+        value: U256,
+    ) -> U256 {
         self.pubdata_costs.push(0);
         self.transient_storage_changes
-            .insert((contract, key), value);
+            .insert((contract, key), value)
+            .unwrap_or_default()
     }
 
     pub(crate) fn get_transient_storage_state(&self) -> &BTreeMap<(H160, U256), U256> {
@@ -667,6 +755,157 @@ pub struct StorageChange {
     pub is_initial: bool,
 }
 
+// region:Checkpoint implementations
+
+impl Checkpoint for WorldDiff {
+    fn encode(&self, encoder: &mut Encoder) {
+        let Self {
+            storage_writes,
+            transient_storage_changes,
+            events,
+            l2_to_l1_logs,
+            pubdata,
+            storage_refunds,
+            pubdata_costs,
+            storage_logs,
+            rollback_storage_logs,
+            decommitted_hashes,
+            decommit_pinned_pages,
+            slot_flags,
+            storage_initial_values,
+            skip_storage_logs,
+        } = self;
+        encoder.put(storage_writes);
+        encoder.put(transient_storage_changes);
+        encoder.put(events);
+        encoder.put(l2_to_l1_logs);
+        encoder.put(pubdata);
+        encoder.put(storage_refunds);
+        encoder.put(pubdata_costs);
+        encoder.put(storage_logs);
+        encoder.put(rollback_storage_logs);
+        encoder.put(decommitted_hashes);
+        encoder.put(decommit_pinned_pages);
+        encoder.put(slot_flags);
+        encoder.put(storage_initial_values);
+        encoder.put(skip_storage_logs);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            storage_writes: decoder.get()?,
+            transient_storage_changes: decoder.get()?,
+            events: decoder.get()?,
+            l2_to_l1_logs: decoder.get()?,
+            pubdata: decoder.get()?,
+            storage_refunds: decoder.get()?,
+            pubdata_costs: decoder.get()?,
+            storage_logs: decoder.get()?,
+            rollback_storage_logs: decoder.get()?,
+            decommitted_hashes: decoder.get()?,
+            decommit_pinned_pages: decoder.get()?,
+            slot_flags: decoder.get()?,
+            storage_initial_values: decoder.get()?,
+            skip_storage_logs: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for StorageWriteEntry {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.value);
+        encoder.put(&self.paid);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            value: decoder.get()?,
+            paid: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for StorageSlot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.value);
+        encoder.put(&self.is_write_initial);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            value: decoder.get()?,
+            is_write_initial: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for DecommitState {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Self::Unsuccessful => encoder.put(&0_u8),
+            Self::Succeeded(page) => {
+                encoder.put(&1_u8);
+                encoder.put(page);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        match decoder.get::<u8>()? {
+            0 => Ok(Self::Unsuccessful),
+            1 => Ok(Self::Succeeded(decoder.get()?)),
+            _ => Err(CheckpointError::Malformed("invalid decommit state")),
+        }
+    }
+}
+
+impl Checkpoint for Snapshot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.storage_writes);
+        encoder.put(&self.events);
+        encoder.put(&self.l2_to_l1_logs);
+        encoder.put(&self.transient_storage_changes);
+        encoder.put(&self.pubdata);
+        encoder.put(&self.storage_logs_len);
+        encoder.put(&self.rollback_storage_logs_len);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            storage_writes: decoder.get()?,
+            events: decoder.get()?,
+            l2_to_l1_logs: decoder.get()?,
+            transient_storage_changes: decoder.get()?,
+            pubdata: decoder.get()?,
+            storage_logs_len: decoder.get()?,
+            rollback_storage_logs_len: decoder.get()?,
+        })
+    }
+}
+
+impl Checkpoint for ExternalSnapshot {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.put(&self.internal_snapshot);
+        encoder.put(&self.decommitted_hashes);
+        encoder.put(&self.decommit_pinned_pages);
+        encoder.put(&self.slot_flags);
+        encoder.put(&self.storage_refunds);
+        encoder.put(&self.pubdata_costs);
+    }
+
+    fn decode(decoder: &mut Decoder<'_>) -> Result<Self, CheckpointError> {
+        Ok(Self {
+            internal_snapshot: decoder.get()?,
+            decommitted_hashes: decoder.get()?,
+            decommit_pinned_pages: decoder.get()?,
+            slot_flags: decoder.get()?,
+            storage_refunds: decoder.get()?,
+            pubdata_costs: decoder.get()?,
+        })
+    }
+}
+// endregion
+
 const WARM_READ_REFUND: u32 = STORAGE_ACCESS_COLD_READ_COST - STORAGE_ACCESS_WARM_READ_COST;
 const WARM_WRITE_REFUND: u32 = STORAGE_ACCESS_COLD_WRITE_COST - STORAGE_ACCESS_WARM_WRITE_COST;
 const COLD_WRITE_AFTER_WARM_READ_REFUND: u32 = STORAGE_ACCESS_COLD_READ_COST;
@@ -678,7 +917,7 @@ mod tests {
     use proptest::{bits, collection::btree_map, prelude::*};
 
     use super::*;
-    use crate::StorageSlot;
+    use crate::{AccountState, StorageSlot};
 
     fn test_storage_changes(
         initial_values: &BTreeMap<(H160, U256), StorageSlot>,
@@ -913,6 +1152,111 @@ mod tests {
         assert_eq!((e.value, e.paid), (U256::from(5), 100));
     }
 
+    #[derive(Default)]
+    struct StorageAccessLog(Vec<StorageAccess>);
+
+    impl Tracer for StorageAccessLog {
+        fn on_storage_access(&mut self, access: StorageAccess) {
+            self.0.push(access);
+        }
+    }
+
+    #[test]
+    fn storage_accesses_are_reported_to_tracer() {
+        let mut world_diff = WorldDiff::default();
+        let mut tracer = StorageAccessLog::default();
+        let contract = H160::repeat_byte(1);
+        let (key, other_key) = (U256::from(1), U256::from(2));
+
+        world_diff.read_storage(&mut CostWorld, &mut tracer, contract, key, 0);
+        world_diff.read_storage(&mut CostWorld, &mut tracer, contract, key, 0);
+        world_diff.write_storage(&mut CostWorld, &mut tracer, contract, key, U256::from(5), 0);
+        world_diff.write_storage(&mut CostWorld, &mut tracer, contract, key, U256::from(6), 0);
+        world_diff.write_storage(
+            &mut CostWorld,
+            &mut tracer,
+            contract,
+            other_key,
+            7.into(),
+            0,
+        );
+
+        let access =
+            |key, read_value: u8, written_value: Option<u8>, is_warm, refund, pubdata_cost| {
+                StorageAccess::persistent(
+                    contract,
+                    key,
+                    read_value.into(),
+                    written_value.map(Into::into),
+                    is_warm,
+                    written_value.is_some(),
+                    refund,
+                    pubdata_cost,
+                )
+            };
+        let expected = [
+            access(key, 0, None, false, 0, 0),
+            access(key, 0, None, true, WARM_READ_REFUND, 0),
+            access(
+                key,
+                0,
+                Some(5),
+                true,
+                COLD_WRITE_AFTER_WARM_READ_REFUND,
+                100,
+            ),
+            access(key, 5, Some(6), true, WARM_WRITE_REFUND, 0),
+            access(other_key, 0, Some(7), false, 0, 100),
+        ];
+        assert_eq!(tracer.0, expected);
+        assert_eq!(
+            world_diff.storage_refunds(),
+            expected.map(|access| access.refund)
+        );
+        assert_eq!(
+            world_diff.pubdata_costs(),
+            expected.map(|access| access.pubdata_cost)
+        );
+    }
+
+    /// [`CostWorld`] where all slots are free, so writes take the free-slot path.
+    struct FreeSlotWorld;
+
+    impl StorageInterface for FreeSlotWorld {
+        fn read_storage(&mut self, _: H160, _: U256) -> StorageSlot {
+            StorageSlot::EMPTY
+        }
+
+        fn cost_of_writing_storage(&mut self, _: StorageSlot, _: U256) -> u32 {
+            100
+        }
+
+        fn is_free_storage_slot(&self, _: &H160, _: &U256) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn warm_accesses_are_the_same_for_free_and_paid_slots() {
+        fn warm_accesses(world: &mut impl StorageInterface) -> Vec<bool> {
+            let mut world_diff = WorldDiff::default();
+            let mut tracer = StorageAccessLog::default();
+            let contract = H160::repeat_byte(1);
+            let (written_key, read_key) = (U256::from(1), U256::from(2));
+
+            world_diff.write_storage(world, &mut tracer, contract, written_key, 5.into(), 0);
+            world_diff.write_storage(world, &mut tracer, contract, written_key, 6.into(), 0);
+            world_diff.read_storage(world, &mut tracer, contract, written_key, 0);
+            world_diff.read_storage(world, &mut tracer, contract, read_key, 0);
+            world_diff.write_storage(world, &mut tracer, contract, read_key, 7.into(), 0);
+            tracer.0.iter().map(|access| access.is_warm).collect()
+        }
+
+        let expected = [false, true, true, false, true];
+        assert_eq!(warm_accesses(&mut CostWorld), expected);
+        assert_eq!(warm_accesses(&mut FreeSlotWorld), expected);
+    }
+
     #[derive(Default)]
     struct TestWorld {
         values: BTreeMap<(H160, U256), U256>,
@@ -1209,4 +1553,132 @@ mod tests {
         assert_eq!(logs[0].read_value, value);
         assert_eq!(logs[0].written_value, value);
     }
+
+    #[test]
+    fn prestate_diff_includes_initial_values_of_all_accessed_slots() {
+        for record_storage_logs in [true, false] {
+            let mut world_diff = WorldDiff::default();
+            world_diff.set_record_storage_logs(record_storage_logs);
+            let mut world = TestWorld::default();
+            let contract = H160::repeat_byte(1);
+            let (read_key, written_key, reverted_key) =
+                (U256::from(1), U256::from(2), U256::from(3));
+            world.values.insert((contract, read_key), U256::from(10));
+            world.values.insert((contract, written_key), U256::from(20));
+
+            world_diff.read_storage(&mut world, &mut (), contract, read_key, 0);
+            world_diff.write_storage(
+                &mut world,
+                &mut (),
+                contract,
+                written_key,
+                U256::from(21),
+                0,
+            );
+            let snapshot = world_diff.snapshot();
+            world_diff.write_storage(
+                &mut world,
+                &mut (),
+                contract,
+                reverted_key,
+                U256::from(5),
+                0,
+            );
+            world_diff.rollback(snapshot);
+            world_diff.write_transient_storage(contract, U256::from(4), U256::from(7));
+            world_diff.write_transient_storage(contract, U256::from(5), U256::zero());
+            world_diff.set_decommit_page(U256::from(123), HeapId::from_u32_unchecked(8));
+
+            let diff = world_diff.prestate_diff(&mut world);
+            assert_eq!(diff.accounts.len(), 1);
+            let account = &diff.accounts[&contract];
+            let expected_storage = BTreeMap::from([
+                (
+                    read_key,
+                    SlotState {
+                        initial: U256::from(10),
+                        written: None,
+                    },
+                ),
+                (
+                    written_key,
+                    SlotState {
+                        initial: U256::from(20),
+                        written: Some(U256::from(21)),
+                    },
+                ),
+                (
+                    reverted_key,
+                    SlotState {
+                        initial: U256::zero(),
+                        written: None,
+                    },
+                ),
+            ]);
+            assert_eq!(account.storage, expected_storage);
+            assert_eq!(
+                account.transient_storage,
+                BTreeMap::from([(U256::from(4), U256::from(7))])
+            );
+            assert_eq!(diff.decommitted_hashes, BTreeSet::from([U256::from(123)]));
+        }
+    }
+
+    #[test]
+    fn prestate_diff_is_serialized_to_json() {
+        let contract = H160::repeat_byte(1);
+        let read_only_contract = H160::repeat_byte(2);
+        let diff = PrestateDiff {
+            accounts: BTreeMap::from([
+                (
+                    contract,
+                    AccountState {
+                        storage: BTreeMap::from([
+                            (
+                                U256::from(1),
+                                SlotState {
+                                    initial: U256::from(10),
+                                    written: None,
+                                },
+                            ),
+                            (
+                                U256::from(2),
+                                SlotState {
+                                    initial: U256::from(20),
+                                    written: Some(U256::from(21)),
+                                },
+                            ),
+                        ]),
+                        transient_storage: BTreeMap::from([(U256::from(3), U256::from(7))]),
+                    },
+                ),
+                (
+                    read_only_contract,
+                    AccountState {
+                        storage: BTreeMap::from([(
+                            U256::from(1),
+                            SlotState {
+                                initial: U256::from(1),
+                                written: Some(U256::from(1)),
+                            },
+                        )]),
+                        transient_storage: BTreeMap::new(),
+                    },
+                ),
+            ]),
+            decommitted_hashes: BTreeSet::from([U256::from(0xff)]),
+        };
+
+        let word = |value: u8| format!("0x{}{value:02x}", "00".repeat(31));
+        let (w1, w2, w3, w7) = (word(1), word(2), word(3), word(7));
+        let (w10, w20, w21, hash) = (word(10), word(20), word(21), word(0xff));
+        let contract = format!("0x{}", "01".repeat(20));
+        let read_only_contract = format!("0x{}", "02".repeat(20));
+        assert_eq!(
+            diff.to_json(),
+            format!(
+                r#"{{"pre":{{"{contract}":{{"storage":{{"{w1}":"{w10}","{w2}":"{w20}"}}}},"{read_only_contract}":{{"storage":{{"{w1}":"{w1}"}}}}}},"post":{{"{contract}":{{"storage":{{"{w2}":"{w21}"}},"transientStorage":{{"{w3}":"{w7}"}}}}}},"decommittedHashes":["{hash}"]}}"#
+            )
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use arbitrary::Arbitrary;
 use zksync_vm2::{single_instruction_test::MockWorld, VirtualMachine};
-use zksync_vm2_interface::Tracer;
+use zksync_vm2_interface::v2::Tracer;
 
 #[derive(Arbitrary, Debug)]
 pub struct VmAndWorld<T: Tracer> {